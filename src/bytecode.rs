@@ -0,0 +1,176 @@
+use crate::value::Value;
+use crate::context::ContextId;
+
+pub use crate::resolver::{Lookup, Step};
+
+use std::rc::Rc;
+
+// The instructions of the virtual machine. Operands index the tables of the chunk being run, or
+// are jump targets within its code. Every expression leaves exactly one value on the stack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(u32),
+    Null,
+    Pop,
+    // Pops the top value and writes it `n` values below the new top, replacing what was there.
+    Stash(u32),
+
+    Load(u32),
+    Define(Target),
+    Closure(u32),
+
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXOr,
+    LeftShift,
+    RightShift,
+    And,
+    Or,
+    In,
+    Negate,
+    Plus,
+    BitwiseNot,
+    Not,
+
+    MakeList(u32),
+    MakeRange(bool),
+    Index,
+    GetField(u32),
+    SetField(u32),
+
+    // Looks a method up on the receiver, pushing the function and the receiver to pass it, or
+    // null when it is called without one.
+    Method(u32),
+    Call(u32),
+    // Calls a script function in place of the function making the call, reusing its frame.
+    TailCall(u32),
+    CallMethod(u32, u32),
+    TailCallMethod(u32, u32),
+    Return,
+
+    Jump(u32),
+    // Jumps back to the start of a loop, checking for interruptions.
+    Loop(u32),
+    JumpIfFalse(u32),
+    JumpIfNull(u32),
+    JumpIfNotNull(u32),
+
+    EnterScope(u32),
+    ExitScope,
+    // Exchanges the current scope with the one it was entered from, so that a while loop can
+    // evaluate its condition outside the scope of its body.
+    SwapScope,
+
+    // Turns the iterable on top of the stack into the snapshot and position `Next` reads.
+    Iterate,
+    // Pushes the next item of the iteration, or jumps when there is none.
+    Next(u32),
+
+    MakeStruct(u32),
+    // Tests the value on top of the stack against a pattern, keeping its bindings for `Bind`, or jumps.
+    Match(u32, u32),
+    Bind(u32),
+    NoMatch,
+
+    PushHandler(Handler, u32),
+    PopHandler,
+    Rethrow,
+    Throw,
+
+    Import(u32),
+    Export(u32),
+    Fail(u32)
+}
+
+// Where a declaration is stored: a slot of the current frame, or a symbol named by the chunk's
+// name table, for scopes whose names are not all known before they run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Slot(u32),
+    Name(u32)
+}
+
+// What a handler does with an error. A catch handler runs its catch block with the error as a
+// value, and a finally handler runs its finally block before raising the error again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handler {
+    Catch,
+    Finally
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Binding,
+    Constant(Value),
+    Load(u32),
+    Variant(String, String, Vec<Pattern>)
+}
+
+// A pattern with the targets of its bindings, in the order they are bound.
+#[derive(Debug, Clone)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub targets: Vec<Target>
+}
+
+#[derive(Debug, Clone)]
+pub struct StructInfo {
+    pub name: String,
+    pub fields: Vec<String>
+}
+
+// The module an import names, and the names it imports, or `None` for all of its exports.
+#[derive(Debug, Clone)]
+pub struct ImportInfo {
+    pub module: String,
+    pub names: Option<Vec<String>>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub lookups: Vec<Lookup>,
+    pub functions: Vec<Rc<Prototype>>,
+    pub structs: Vec<StructInfo>,
+    pub imports: Vec<ImportInfo>,
+    pub arms: Vec<Arm>
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk::default()
+    }
+}
+
+// A compiled function. Calling it creates a frame with `size` slots, or with none for a
+// function whose scope binds names, and stores the receiver and the arguments in their targets.
+#[derive(Debug)]
+pub struct Prototype {
+    pub name: String,
+    pub params: Vec<String>,
+    pub targets: Vec<Target>,
+    pub receiver: Option<Target>,
+    pub size: u32,
+    pub chunk: Chunk
+}
+
+// A compiled function with the context it was defined in.
+#[derive(Debug)]
+pub struct Closure {
+    pub prototype: Rc<Prototype>,
+    pub context: ContextId
+}
@@ -2,6 +2,6 @@ pub const DIGITS: &str = "0123456789";
 pub const LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
 pub const LETTERS_AND_DIGITS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_";
 
-pub const KEYWORDS: [&str; 5] = ["let", "function", "if", "else", "while"];
+pub const KEYWORDS: [&str; 17] = ["let", "function", "if", "else", "while", "for", "in", "import", "export", "from", "struct", "enum", "match", "throw", "try", "catch", "finally"];
 
 pub const SPECIAL_CHARACTERS: [[char; 2]; 1] = [['n', '\n']];
\ No newline at end of file
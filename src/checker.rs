@@ -0,0 +1,663 @@
+use crate::node::*;
+use crate::token::TokenType;
+use crate::error::TypeError;
+use crate::resolver::Lookup;
+use crate::context::{ContextId, ContextManager};
+use crate::value::Value;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+enum Symbol {
+    // A variable, and whether its type rests on an annotation.
+    Var(Type, bool),
+    Func(Vec<Type>, Type),
+    Struct(String),
+    Enum(String, Vec<(String, usize)>)
+}
+
+// Infers what it can about a program before it runs and reports operations and annotations that are
+// guaranteed to fail. Anything it cannot infer is `Type::Any`, which is compatible with every type.
+// Only mismatches involving an annotation are reported: a type inferred from literals alone may
+// belong to a branch that never runs or that a check on the value's type guards, so unannotated
+// code produces no errors.
+pub struct Checker {
+    scopes: Vec<HashMap<String, Symbol>>,
+    errors: Vec<TypeError>
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checker {
+    pub fn new() -> Checker {
+        Checker {
+            scopes: vec![HashMap::new()],
+            errors: vec![]
+        }
+    }
+
+    // Declares the names defined outside the program, the prelude's included, with what their
+    // current values tell of them. Host functions take and return anything.
+    pub fn set_globals(&mut self, manager: &ContextManager, context_id: ContextId) {
+        for name in manager.names(context_id) {
+            if let Some(value) = manager.get(context_id, &name) {
+                let symbol = Checker::symbol(value);
+
+                self.define(&name, symbol);
+            }
+        }
+    }
+
+    pub fn check(&mut self, node: &Node) -> Result<Type, Vec<TypeError>> {
+        let result = self.infer(node);
+
+        if self.errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(self.errors.drain(..).collect())
+        }
+    }
+
+    fn infer(&mut self, node: &Node) -> Type {
+        match node {
+            Node::Int(_) => Type::Int,
+            Node::Float(_) => Type::Float,
+            Node::Str(_) => Type::Str,
+            Node::ListDef(nodes) => {
+                for node in nodes {
+                    self.infer(node);
+                }
+
+                Type::List
+            },
+            Node::Range(start, end, _) => {
+                for bound in [start, end] {
+                    let bound_type = self.infer(bound);
+
+                    if !matches!(bound_type, Type::Any | Type::Int | Type::Number) && self.declared(bound) {
+                        self.error(String::from("Range bounds must be integers, got '") + &bound_type.to_string() + "'");
+                    }
+                }
+
+                Type::Range
+            },
+            Node::Index(object, index) => {
+                let object_type = self.infer(object);
+                let index_type = self.infer(index);
+
+                if !matches!(object_type, Type::Any | Type::List | Type::Str | Type::Range | Type::Named(_)) && self.declared(object) {
+                    self.error(String::from("Type '") + &object_type.to_string() + "' cannot be indexed");
+                }
+
+                match (object_type, index_type) {
+                    (Type::Str, _) => Type::Str,
+                    (object_type, Type::Range) => object_type,
+                    (Type::Range, _) => Type::Int,
+                    _ => Type::Any
+                }
+            },
+            Node::VarAcc(name) | Node::ResolvedAcc(Lookup { name, .. }) => match self.lookup(name) {
+                Some(Symbol::Var(var_type, _)) => var_type,
+                Some(Symbol::Func(..)) => Type::Func,
+                _ => Type::Any
+            },
+            Node::VarDef(name, annotation, value_node) => {
+                let value_type = self.infer(value_node);
+                let declared = annotation.is_some() || self.declared(value_node);
+
+                let var_type = match annotation {
+                    Some(annotation) => {
+                        if !Checker::accepts(annotation, &value_type) {
+                            self.error(String::from("Cannot assign a value of type '") + &value_type.to_string() + "' to '" + name + "' of type '" + &annotation.to_string() + "'");
+                        }
+
+                        annotation.clone()
+                    },
+                    None => value_type
+                };
+
+                self.define(name, Symbol::Var(var_type.clone(), declared));
+
+                var_type
+            },
+            Node::FuncDef(..) => self.infer_func_def(node),
+            Node::FuncCall(..) => self.infer_func_call(node),
+            Node::StructDef(name, _, methods) => {
+                self.define(name, Symbol::Struct(name.clone()));
+
+                self.push_scope();
+                self.define("self", Symbol::Var(Type::Named(name.clone()), false));
+
+                for method in methods {
+                    self.infer(method);
+                }
+
+                self.pop_scope();
+
+                Type::Any
+            },
+            Node::EnumDef(name, variants) => {
+                let variants = variants.iter().map(|(variant, fields)| (variant.clone(), fields.len())).collect();
+
+                self.define(name, Symbol::Enum(name.clone(), variants));
+
+                Type::Any
+            },
+            Node::FieldAcc(object, field) => {
+                if let Node::VarAcc(name) = object.as_ref() {
+                    if let Some(Symbol::Enum(enum_name, variants)) = self.lookup(name) {
+                        return match variants.iter().find(|(variant, _)| variant == field) {
+                            Some((_, 0)) => Type::Named(enum_name),
+                            Some(_) => Type::Func,
+                            None => {
+                                self.error(String::from("Enum '") + &enum_name + "' has no variant '" + field + "'");
+
+                                Type::Any
+                            }
+                        };
+                    }
+                }
+
+                let object_type = self.infer(object);
+
+                if Checker::is_primitive(&object_type) && self.declared(object) {
+                    self.error(String::from("Type '") + &object_type.to_string() + "' has no field '" + field + "'");
+                }
+
+                Type::Any
+            },
+            Node::OptionalFieldAcc(object, field) => {
+                let object_type = self.infer(object);
+
+                if object_type != Type::Null && Checker::is_primitive(&object_type) && self.declared(object) {
+                    self.error(String::from("Type '") + &object_type.to_string() + "' has no field '" + field + "'");
+                }
+
+                Type::Any
+            },
+            Node::OptionalCall(callee, args) => {
+                self.infer(callee);
+
+                for arg in args {
+                    self.infer(arg);
+                }
+
+                Type::Any
+            },
+            Node::FieldAssign(object, _, value_node) => {
+                self.infer(object);
+
+                self.infer(value_node)
+            },
+            Node::UnaryOp(node, token) => {
+                let operand = self.infer(node);
+                let declared = self.declared(node);
+
+                match token {
+                    TokenType::Plus | TokenType::Minus => {
+                        if operand != Type::Any && !Checker::is_numeric(&operand) {
+                            if !declared {
+                                return Type::Any;
+                            }
+
+                            self.error(String::from("Operator '") + &token.to_string() + "' cannot be applied to '" + &operand.to_string() + "'");
+
+                            return Type::Any;
+                        }
+
+                        operand
+                    },
+                    TokenType::BitwiseNot => {
+                        if !matches!(operand, Type::Any | Type::Int | Type::Number) && declared {
+                            self.error(String::from("Operator '~' cannot be applied to '") + &operand.to_string() + "'");
+                        }
+
+                        Type::Int
+                    },
+                    TokenType::Not => Type::Bool,
+                    _ => operand
+                }
+            },
+            Node::BinaryOp(left, TokenType::NullCoalesce, right) => {
+                let left = self.infer(left);
+                let right = self.infer(right);
+
+                match left {
+                    Type::Null => right,
+                    Type::Any => Checker::join(&left, &right),
+                    left => left
+                }
+            },
+            Node::BinaryOp(left_node, token, right_node) => {
+                let left = self.infer(left_node);
+                let right = self.infer(right_node);
+                let declared = self.declared(left_node) || self.declared(right_node);
+
+                self.infer_binary_op(&left, token, &right, declared)
+            },
+            Node::Statements(nodes, should_return_last) => {
+                let mut last = Type::Null;
+
+                for node in nodes {
+                    last = self.infer(node);
+                }
+
+                if *should_return_last {
+                    last
+                } else {
+                    Type::Null
+                }
+            },
+            Node::If(condition, body, else_body) => {
+                self.infer(condition);
+
+                let body_type = self.infer_scoped(body);
+
+                match else_body {
+                    Some(else_body) => {
+                        let else_type = self.infer_scoped(else_body);
+
+                        Checker::join(&body_type, &else_type)
+                    },
+                    None => Type::Any
+                }
+            },
+            Node::WhileLoop(condition, body) => {
+                self.infer(condition);
+                self.infer_scoped(body);
+
+                Type::Any
+            },
+            Node::ForLoop(name, iterable, body) => {
+                let iterable_type = self.infer(iterable);
+                let declared = self.declared(iterable);
+
+                if !matches!(iterable_type, Type::Any | Type::List | Type::Str | Type::Range | Type::Named(_)) && declared {
+                    self.error(String::from("Type '") + &iterable_type.to_string() + "' is not iterable");
+                }
+
+                self.push_scope();
+
+                self.define(name, Symbol::Var(match iterable_type {
+                    Type::Range => Type::Int,
+                    Type::Str => Type::Str,
+                    _ => Type::Any
+                }, declared));
+
+                self.infer(body);
+
+                self.pop_scope();
+
+                Type::Any
+            },
+            Node::Match(subject, arms) => {
+                self.infer(subject);
+
+                let mut result: Option<Type> = None;
+
+                for (pattern, body) in arms {
+                    self.push_scope();
+
+                    let mut bindings = vec![];
+
+                    Checker::pattern_bindings(pattern, &mut bindings);
+
+                    for binding in bindings {
+                        self.define(&binding, Symbol::Var(Type::Any, false));
+                    }
+
+                    let arm_type = self.infer(body);
+
+                    self.pop_scope();
+
+                    result = Some(match result {
+                        Some(result) => Checker::join(&result, &arm_type),
+                        None => arm_type
+                    });
+                }
+
+                result.unwrap_or(Type::Any)
+            },
+            Node::Throw(value) => {
+                self.infer(value);
+
+                Type::Any
+            },
+            Node::Try(body, catch, finally) => {
+                let body_type = self.infer_scoped(body);
+
+                let mut result = body_type.clone();
+
+                if let Some((binding, catch_body)) = catch {
+                    self.push_scope();
+
+                    if let Some(binding) = binding {
+                        self.define(binding, Symbol::Var(Type::Any, false));
+                    }
+
+                    let catch_type = self.infer(catch_body);
+
+                    self.pop_scope();
+
+                    result = Checker::join(&body_type, &catch_type);
+                }
+
+                if let Some(finally) = finally {
+                    self.infer_scoped(finally);
+                }
+
+                result
+            },
+            Node::Import(_, names) => {
+                if let Some(names) = names {
+                    for name in names {
+                        self.define(name, Symbol::Var(Type::Any, false));
+                    }
+                }
+
+                Type::Null
+            },
+            Node::Export(declaration) | Node::Frame(_, declaration) | Node::Define(_, declaration) => self.infer(declaration),
+            Node::Empty | Node::EOF => Type::Null
+        }
+    }
+
+    fn infer_func_def(&mut self, node: &Node) -> Type {
+        if let Node::FuncDef(name, params, return_type, body) = node {
+            let param_types: Vec<Type> = params.iter().map(|(_, annotation)| annotation.clone().unwrap_or(Type::Any)).collect();
+
+            self.define(name, Symbol::Func(param_types.clone(), return_type.clone().unwrap_or(Type::Any)));
+
+            self.push_scope();
+
+            for ((param, annotation), param_type) in params.iter().zip(param_types) {
+                self.define(param, Symbol::Var(param_type, annotation.is_some()));
+            }
+
+            let body_type = self.infer(body);
+
+            self.pop_scope();
+
+            if let Some(return_type) = return_type {
+                if !Checker::accepts(return_type, &body_type) {
+                    self.error(String::from("Function '") + name + "' is declared to return '" + &return_type.to_string() + "' but returns '" + &body_type.to_string() + "'");
+                }
+            }
+        }
+
+        Type::Func
+    }
+
+    fn infer_func_call(&mut self, node: &Node) -> Type {
+        if let Node::FuncCall(callee, args) = node {
+            let arg_types: Vec<Type> = args.iter().map(|arg| self.infer(arg)).collect();
+
+            match callee.as_ref() {
+            Node::VarAcc(name) => match self.lookup(name) {
+                    Some(Symbol::Func(param_types, return_type)) => {
+                        for (i, (param_type, arg_type)) in param_types.iter().zip(arg_types.iter()).enumerate() {
+                            if !Checker::accepts(param_type, arg_type) {
+                                self.error(String::from("Argument ") + &(i + 1).to_string() + " of '" + name + "' expects '" + &param_type.to_string() + "', got '" + &arg_type.to_string() + "'");
+                            }
+                        }
+
+                        return return_type;
+                    },
+                    Some(Symbol::Struct(struct_name)) => return Type::Named(struct_name),
+                    Some(Symbol::Var(var_type, true)) if Checker::is_primitive(&var_type) => {
+                        self.error(String::from("'") + name + "' of type '" + &var_type.to_string() + "' is not callable");
+                    },
+                    _ => {}
+                },
+                Node::FieldAcc(object, method) => {
+                    if let Node::VarAcc(name) = object.as_ref() {
+                        if let Some(Symbol::Enum(enum_name, _)) = self.lookup(name) {
+                            self.infer(callee);
+
+                            return Type::Named(enum_name);
+                        }
+                    }
+
+                    let object_type = self.infer(object);
+
+                    match (&object_type, method.as_str()) {
+                        (Type::List, "push") => return Type::Null,
+                        (Type::List, "pop") => return Type::Any,
+                        _ => {}
+                    }
+
+                    if Checker::is_primitive(&object_type) && self.declared(object) {
+                        self.error(String::from("Type '") + &object_type.to_string() + "' has no method '" + method + "'");
+                    }
+                },
+                Node::OptionalFieldAcc(object, method) => {
+                    let object_type = self.infer(object);
+
+                    match (&object_type, method.as_str()) {
+                        (Type::List, "push") | (Type::List, "pop") | (Type::Null, _) => {},
+                        _ if Checker::is_primitive(&object_type) && self.declared(object) => {
+                            self.error(String::from("Type '") + &object_type.to_string() + "' has no method '" + method + "'");
+                        },
+                        _ => {}
+                    }
+                },
+                _ => {
+                    let callee_type = self.infer(callee);
+
+                    if Checker::is_primitive(&callee_type) && self.declared(callee) {
+                        self.error(String::from("Value of type '") + &callee_type.to_string() + "' is not callable");
+                    }
+                }
+            }
+        }
+
+        Type::Any
+    }
+
+    fn infer_binary_op(&mut self, left: &Type, token: &TokenType, right: &Type, declared: bool) -> Type {
+        let either_any = *left == Type::Any || *right == Type::Any;
+        let both_numeric = Checker::is_numeric(left) && Checker::is_numeric(right);
+
+        let result = match token {
+            TokenType::Plus => {
+                if *left == Type::Str || *right == Type::Str {
+                    Some(Type::Str)
+                } else if both_numeric {
+                    Some(Checker::numeric_result(left, right))
+                } else if either_any {
+                    Some(Type::Any)
+                } else {
+                    None
+                }
+            },
+            TokenType::Minus | TokenType::Mul => {
+                if both_numeric {
+                    Some(Checker::numeric_result(left, right))
+                } else if either_any {
+                    Some(Type::Any)
+                } else {
+                    None
+                }
+            },
+            TokenType::Div | TokenType::Pow => {
+                if both_numeric {
+                    match (left, right) {
+                        (Type::Float, _) | (_, Type::Float) => Some(Type::Float),
+                        _ => Some(Type::Number)
+                    }
+                } else if either_any {
+                    Some(Type::Any)
+                } else {
+                    None
+                }
+            },
+            TokenType::GT | TokenType::GTE | TokenType::LT | TokenType::LTE => {
+                if both_numeric || either_any {
+                    Some(Type::Bool)
+                } else {
+                    None
+                }
+            },
+            TokenType::EE | TokenType::NE => {
+                let comparable = match (left, right) {
+                    (Type::Bool, Type::Bool) | (Type::Str, Type::Str) | (Type::Null, Type::Null) | (Type::Range, Type::Range) | (Type::Named(_), Type::Named(_)) => true,
+                    _ => both_numeric || either_any
+                };
+
+                if comparable {
+                    Some(Type::Bool)
+                } else {
+                    if declared {
+                        self.error(String::from("Cannot compare '") + &left.to_string() + "' with '" + &right.to_string() + "'");
+                    }
+
+                    return Type::Bool;
+                }
+            },
+            TokenType::BitwiseAnd | TokenType::BitwiseOr | TokenType::BitwiseXOr | TokenType::BitwiseLeftShift | TokenType::BitwiseRightShift => {
+                let integral = |operand: &Type| matches!(operand, Type::Any | Type::Int | Type::Number);
+
+                if integral(left) && integral(right) {
+                    Some(Type::Int)
+                } else {
+                    None
+                }
+            },
+            TokenType::And | TokenType::Or => Some(Checker::join(left, right)),
+            TokenType::Keyword(keyword) if keyword == "in" => {
+                if matches!(right, Type::Any | Type::List | Type::Str | Type::Range | Type::Named(_)) {
+                    Some(Type::Bool)
+                } else {
+                    None
+                }
+            },
+            _ => Some(Type::Any)
+        };
+
+        match result {
+            Some(result) => result,
+            None => {
+                if declared {
+                    self.error(String::from("Operator '") + &token.to_string() + "' cannot be applied to '" + &left.to_string() + "' and '" + &right.to_string() + "'");
+                }
+
+                Type::Any
+            }
+        }
+    }
+
+    // Whether the type inferred for `node` rests on an annotation rather than on literals alone.
+    fn declared(&self, node: &Node) -> bool {
+        match node {
+            Node::VarAcc(name) | Node::ResolvedAcc(Lookup { name, .. }) => matches!(self.lookup(name), Some(Symbol::Var(_, true))),
+            Node::FuncCall(callee, _) => match callee.as_ref() {
+                Node::VarAcc(name) => matches!(self.lookup(name), Some(Symbol::Func(..))),
+                _ => false
+            },
+            Node::UnaryOp(node, _) | Node::Frame(_, node) => self.declared(node),
+            Node::BinaryOp(left, _, right) => self.declared(left) || self.declared(right),
+            _ => false
+        }
+    }
+
+    // What a global's value tells of it: only its type, since a later script may bind the name again.
+    fn symbol(value: &Value) -> Symbol {
+        match value {
+            Value::Func(..) | Value::Closure(..) | Value::NativeFunc(..) | Value::Variant(..) => Symbol::Func(vec![], Type::Any),
+            Value::Struct(struct_type) => Symbol::Struct(struct_type.name.clone()),
+            Value::Enum(name, variants) => Symbol::Enum(name.clone(), variants.iter().map(|(variant, fields)| (variant.clone(), fields.len())).collect()),
+            Value::Int(_) => Symbol::Var(Type::Int, false),
+            Value::Float(_) => Symbol::Var(Type::Float, false),
+            Value::Str(_) => Symbol::Var(Type::Str, false),
+            Value::Boolean(_) => Symbol::Var(Type::Bool, false),
+            Value::List(_) => Symbol::Var(Type::List, false),
+            Value::Range(..) => Symbol::Var(Type::Range, false),
+            Value::Null => Symbol::Var(Type::Null, false),
+            _ => Symbol::Var(Type::Any, false)
+        }
+    }
+
+    fn infer_scoped(&mut self, node: &Node) -> Type {
+        self.push_scope();
+
+        let result = self.infer(node);
+
+        self.pop_scope();
+
+        result
+    }
+
+    fn pattern_bindings(pattern: &Pattern, bindings: &mut Vec<String>) {
+        match pattern {
+            Pattern::Binding(name) => bindings.push(name.clone()),
+            Pattern::Variant(_, _, patterns) => {
+                for pattern in patterns {
+                    Checker::pattern_bindings(pattern, bindings);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn accepts(expected: &Type, actual: &Type) -> bool {
+        match (expected, actual) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Float, Type::Int) => true,
+            (Type::Number, Type::Int) | (Type::Number, Type::Float) => true,
+            (Type::Int, Type::Number) | (Type::Float, Type::Number) => true,
+            _ => expected == actual
+        }
+    }
+
+    fn join(left: &Type, right: &Type) -> Type {
+        if left == right {
+            left.clone()
+        } else if Checker::is_numeric(left) && Checker::is_numeric(right) {
+            Type::Number
+        } else {
+            Type::Any
+        }
+    }
+
+    fn numeric_result(left: &Type, right: &Type) -> Type {
+        match (left, right) {
+            (Type::Int, Type::Int) => Type::Int,
+            (Type::Float, _) | (_, Type::Float) => Type::Float,
+            _ => Type::Number
+        }
+    }
+
+    fn is_numeric(value_type: &Type) -> bool {
+        matches!(value_type, Type::Int | Type::Float | Type::Number)
+    }
+
+    fn is_primitive(value_type: &Type) -> bool {
+        matches!(value_type, Type::Int | Type::Float | Type::Number | Type::Str | Type::Bool | Type::List | Type::Null)
+    }
+
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    fn define(&mut self, name: &str, symbol: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(String::from(name), symbol);
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn error(&mut self, msg: String) {
+        self.errors.push(TypeError::new(msg));
+    }
+}
@@ -0,0 +1,713 @@
+use crate::node::{self, Node};
+use crate::bytecode::*;
+use crate::token::TokenType;
+use crate::value::Value;
+use crate::resolver::{self, Scopes};
+
+use std::mem;
+use std::rc::Rc;
+
+// Compiles a parsed program to bytecode for `vm::Vm`. Scopes are laid out exactly as the
+// interpreter creates its contexts, so both evaluate every program the same way.
+pub struct Compiler {
+    chunk: Chunk,
+    scopes: Scopes
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler {
+            chunk: Chunk::new(),
+            scopes: Scopes::new()
+        }
+    }
+
+    // Compiles a script or module, which runs in the context it is given.
+    pub fn compile(mut self, node: &Node) -> Rc<Prototype> {
+        self.scopes.open_named(&[], []);
+
+        self.node(node);
+        self.emit(Op::Return);
+
+        Rc::new(Prototype {
+            name: String::from("<script>"),
+            params: vec![],
+            targets: vec![],
+            receiver: None,
+            size: 0,
+            chunk: self.chunk
+        })
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    // Points the jump at `index` to the next instruction.
+    fn patch(&mut self, index: usize) {
+        let target = self.here();
+
+        self.chunk.code[index] = match self.chunk.code[index] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfNull(_) => Op::JumpIfNull(target),
+            Op::JumpIfNotNull(_) => Op::JumpIfNotNull(target),
+            Op::Next(_) => Op::Next(target),
+            Op::Match(arm, _) => Op::Match(arm, target),
+            Op::PushHandler(handler, _) => Op::PushHandler(handler, target),
+            op => op
+        };
+    }
+
+    fn constant(&mut self, value: Value) -> u32 {
+        self.chunk.constants.push(value);
+
+        (self.chunk.constants.len() - 1) as u32
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        match self.chunk.names.iter().position(|existing| existing == name) {
+            Some(index) => index as u32,
+            None => {
+                self.chunk.names.push(String::from(name));
+
+                (self.chunk.names.len() - 1) as u32
+            }
+        }
+    }
+
+    fn open_scope(&mut self, bindings: &[String], body: &Node) {
+        self.scopes.open(bindings, body);
+    }
+
+    // Closes the innermost scope, returning its number of slots.
+    fn close_scope(&mut self) -> u32 {
+        self.scopes.close().map_or(0, |layout| layout.len() as u32)
+    }
+
+    // Where a declaration of `name` in the innermost scope is stored.
+    fn declare(&mut self, name: &str) -> Target {
+        match self.scopes.declare(name) {
+            Some(slot) => Target::Slot(slot),
+            None => Target::Name(self.name(name))
+        }
+    }
+
+    fn lookup(&mut self, name: &str, skip: usize) -> u32 {
+        self.chunk.lookups.push(self.scopes.lookup(name, skip));
+
+        (self.chunk.lookups.len() - 1) as u32
+    }
+
+    // Compiles `body` in a scope of its own, as the interpreter does for the branches of `if`
+    // and the blocks of `try`.
+    fn block(&mut self, body: &Node) {
+        self.block_with(body, Compiler::node);
+    }
+
+    fn block_with(&mut self, body: &Node, compile: fn(&mut Compiler, &Node)) {
+        self.open_scope(&[], body);
+
+        let enter = self.emit(Op::EnterScope(0));
+
+        compile(self, body);
+
+        self.emit(Op::ExitScope);
+        self.end_block(enter);
+    }
+
+    fn end_block(&mut self, enter: usize) {
+        let size = self.close_scope();
+
+        self.chunk.code[enter] = Op::EnterScope(size);
+    }
+
+    fn function(&mut self, name: &str, params: &[String], body: &Node, method: bool) -> u32 {
+        let mut bindings = params.to_vec();
+
+        if method {
+            bindings.push(String::from("self"));
+        }
+
+        self.open_scope(&bindings, body);
+
+        let enclosing = mem::replace(&mut self.chunk, Chunk::new());
+
+        let receiver = if method { Some(self.declare("self")) } else { None };
+        let targets = params.iter().map(|param| self.declare(param)).collect();
+
+        self.tail(body);
+        self.emit(Op::Return);
+
+        let size = self.close_scope();
+        let chunk = mem::replace(&mut self.chunk, enclosing);
+
+        self.chunk.functions.push(Rc::new(Prototype {
+            name: String::from(name),
+            params: params.to_vec(),
+            targets,
+            receiver,
+            size,
+            chunk
+        }));
+
+        (self.chunk.functions.len() - 1) as u32
+    }
+
+    fn if_else(&mut self, condition: &Node, body: &Node, else_body: Option<&Node>, compile: fn(&mut Compiler, &Node)) {
+        self.node(condition);
+
+        let skip_body = self.emit(Op::JumpIfFalse(0));
+
+        self.block_with(body, compile);
+
+        let skip_else = self.emit(Op::Jump(0));
+
+        self.patch(skip_body);
+
+        match else_body {
+            Some(else_body) => self.block_with(else_body, compile),
+            None => { self.emit(Op::Null); }
+        }
+
+        self.patch(skip_else);
+    }
+
+    // Compiles the body of a function, whose calls in tail position replace its frame. Tail
+    // position is the last statement of a block and the branches of an `if` in it, as in the
+    // interpreter.
+    fn tail(&mut self, node: &Node) {
+        match node {
+            Node::Frame(_, body) => self.tail(body),
+            Node::Statements(nodes, true) if !nodes.is_empty() => {
+                for node in &nodes[..nodes.len() - 1] {
+                    self.node(node);
+                    self.emit(Op::Pop);
+                }
+
+                self.tail(&nodes[nodes.len() - 1]);
+            },
+            Node::If(condition, body, else_body) => self.if_else(condition, body, else_body.as_deref(), Compiler::tail),
+            Node::FuncCall(func, args) => {
+                let mut skips = vec![];
+
+                match func.as_ref() {
+                    Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => self.method_call(func, args, &mut skips, true),
+                    _ => {
+                        self.object(func, &mut skips);
+
+                        for arg in args {
+                            self.node(arg);
+                        }
+
+                        self.emit(Op::TailCall(args.len() as u32));
+                    }
+                }
+
+                for skip in skips {
+                    self.patch(skip);
+                }
+            },
+            node => self.node(node)
+        }
+    }
+
+    // Compiles a call of `object.name(args)`, which replaces the calling frame if `tail` is set.
+    fn method_call(&mut self, func: &Node, args: &[Box<Node>], skips: &mut Vec<usize>, tail: bool) {
+        if let Node::FieldAcc(object, name) | Node::OptionalFieldAcc(object, name) = func {
+            self.object(object, skips);
+
+            if let Node::OptionalFieldAcc(..) = func {
+                skips.push(self.emit(Op::JumpIfNull(0)));
+            }
+
+            let name = self.name(name);
+
+            self.emit(Op::Method(name));
+
+            for arg in args {
+                self.node(arg);
+            }
+
+            let count = args.len() as u32;
+
+            self.emit(if tail { Op::TailCallMethod(name, count) } else { Op::CallMethod(name, count) });
+        }
+    }
+
+    // Compiles a field access, call or index, which may continue a chain of them. Each `?.` in
+    // the chain adds a jump to `skips`, which the caller points past the end of the whole chain,
+    // so that a null it finds skips the rest.
+    fn link(&mut self, node: &Node, skips: &mut Vec<usize>) {
+        match node {
+            Node::Index(object, index) => {
+                self.object(object, skips);
+                self.node(index);
+
+                self.emit(Op::Index);
+            },
+            Node::FuncCall(func, args) => {
+                match func.as_ref() {
+                    Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => self.method_call(func, args, skips, false),
+                    _ => {
+                        self.object(func, skips);
+
+                        for arg in args {
+                            self.node(arg);
+                        }
+
+                        self.emit(Op::Call(args.len() as u32));
+                    }
+                }
+            },
+            Node::OptionalCall(func, args) => {
+                self.object(func, skips);
+
+                skips.push(self.emit(Op::JumpIfNull(0)));
+
+                for arg in args {
+                    self.node(arg);
+                }
+
+                self.emit(Op::Call(args.len() as u32));
+            },
+            Node::FieldAcc(object, field) => {
+                self.object(object, skips);
+
+                let name = self.name(field);
+
+                self.emit(Op::GetField(name));
+            },
+            Node::OptionalFieldAcc(object, field) => {
+                self.object(object, skips);
+
+                skips.push(self.emit(Op::JumpIfNull(0)));
+
+                let name = self.name(field);
+
+                self.emit(Op::GetField(name));
+            },
+            _ => self.node(node)
+        }
+    }
+
+    fn object(&mut self, node: &Node, skips: &mut Vec<usize>) {
+        match node {
+            Node::Index(..) | Node::FuncCall(..) | Node::OptionalCall(..) | Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => self.link(node, skips),
+            _ => self.node(node)
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Statements(nodes, should_return_last) => {
+                for (index, node) in nodes.iter().enumerate() {
+                    if index > 0 {
+                        self.emit(Op::Pop);
+                    }
+
+                    self.node(node);
+                }
+
+                if nodes.is_empty() {
+                    self.emit(Op::Null);
+                } else if !should_return_last {
+                    self.emit(Op::Pop);
+                    self.emit(Op::Null);
+                }
+            },
+            Node::Int(n) => {
+                let index = self.constant(Value::Int(*n));
+
+                self.emit(Op::Constant(index));
+            },
+            Node::Float(n) => {
+                let index = self.constant(Value::Float(*n));
+
+                self.emit(Op::Constant(index));
+            },
+            Node::Str(string) => {
+                let index = self.constant(Value::Str(string.as_str().into()));
+
+                self.emit(Op::Constant(index));
+            },
+            Node::UnaryOp(value, token) => {
+                self.node(value);
+
+                match token {
+                    TokenType::Plus => { self.emit(Op::Plus); },
+                    TokenType::Minus => { self.emit(Op::Negate); },
+                    TokenType::BitwiseNot => { self.emit(Op::BitwiseNot); },
+                    TokenType::Not => { self.emit(Op::Not); },
+                    _ => {}
+                }
+            },
+            Node::BinaryOp(left, TokenType::NullCoalesce, right) => {
+                self.node(left);
+
+                let jump = self.emit(Op::JumpIfNotNull(0));
+
+                self.emit(Op::Pop);
+                self.node(right);
+
+                self.patch(jump);
+            },
+            Node::BinaryOp(left, token, right) => {
+                self.node(left);
+                self.node(right);
+
+                let op = match token {
+                    TokenType::Plus => Op::Add,
+                    TokenType::Minus => Op::Subtract,
+                    TokenType::Mul => Op::Multiply,
+                    TokenType::Div => Op::Divide,
+                    TokenType::Pow => Op::Power,
+                    TokenType::EE => Op::Equal,
+                    TokenType::NE => Op::NotEqual,
+                    TokenType::GT => Op::Greater,
+                    TokenType::GTE => Op::GreaterEqual,
+                    TokenType::LT => Op::Less,
+                    TokenType::LTE => Op::LessEqual,
+                    TokenType::BitwiseAnd => Op::BitwiseAnd,
+                    TokenType::BitwiseOr => Op::BitwiseOr,
+                    TokenType::BitwiseXOr => Op::BitwiseXOr,
+                    TokenType::BitwiseLeftShift => Op::LeftShift,
+                    TokenType::BitwiseRightShift => Op::RightShift,
+                    TokenType::And => Op::And,
+                    TokenType::Or => Op::Or,
+                    TokenType::Keyword(keyword) if keyword == "in" => Op::In,
+                    _ => Op::Fail(self.constant(Value::Str((String::from("Illegal token '") + &token.to_string() + "'").into())))
+                };
+
+                self.emit(op);
+            },
+            Node::VarDef(name, _, value) => {
+                self.node(value);
+
+                let target = self.declare(name);
+
+                self.emit(Op::Define(target));
+            },
+            Node::VarAcc(name) | Node::ResolvedAcc(resolver::Lookup { name, .. }) => {
+                let lookup = self.lookup(name, 0);
+
+                self.emit(Op::Load(lookup));
+            },
+            // Slots are laid out again as the virtual machine creates its frames, which do not
+            // include the context a function captures.
+            Node::Frame(_, body) | Node::Define(_, body) => self.node(body),
+            Node::ListDef(nodes) => {
+                for node in nodes {
+                    self.node(node);
+                }
+
+                self.emit(Op::MakeList(nodes.len() as u32));
+            },
+            Node::Range(start, end, inclusive) => {
+                self.node(start);
+                self.node(end);
+
+                self.emit(Op::MakeRange(*inclusive));
+            },
+            Node::Index(..) | Node::FuncCall(..) | Node::OptionalCall(..) | Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => {
+                let mut skips = vec![];
+
+                self.link(node, &mut skips);
+
+                for skip in skips {
+                    self.patch(skip);
+                }
+            },
+            Node::FuncDef(name, params, _, body) => {
+                let params: Vec<String> = params.iter().map(|(param, _)| param.clone()).collect();
+                let function = self.function(name, &params, body, false);
+
+                self.emit(Op::Closure(function));
+
+                let target = self.declare(name);
+
+                self.emit(Op::Define(target));
+            },
+            Node::StructDef(name, fields, methods) => {
+                self.scopes.open_named(&[], []);
+                self.emit(Op::EnterScope(0));
+
+                for method in methods {
+                    match method.as_ref() {
+                        Node::FuncDef(method_name, params, _, body) => {
+                            let params: Vec<String> = params.iter().map(|(param, _)| param.clone()).collect();
+                            let function = self.function(method_name, &params, body, true);
+
+                            self.emit(Op::Closure(function));
+
+                            let target = self.declare(method_name);
+
+                            self.emit(Op::Define(target));
+                        },
+                        method => self.node(method)
+                    }
+
+                    self.emit(Op::Pop);
+                }
+
+                self.chunk.structs.push(StructInfo { name: name.clone(), fields: fields.clone() });
+
+                self.emit(Op::MakeStruct((self.chunk.structs.len() - 1) as u32));
+                self.emit(Op::ExitScope);
+                self.close_scope();
+
+                let target = self.declare(name);
+
+                self.emit(Op::Define(target));
+            },
+            Node::FieldAssign(object, field, value) => {
+                self.node(value);
+                self.node(object);
+
+                let name = self.name(field);
+
+                self.emit(Op::SetField(name));
+            },
+            Node::EnumDef(name, variants) => {
+                let index = self.constant(Value::Enum(name.clone(), variants.clone()));
+
+                self.emit(Op::Constant(index));
+
+                let target = self.declare(name);
+
+                self.emit(Op::Define(target));
+            },
+            Node::Match(subject, arms) => {
+                self.node(subject);
+
+                let mut ends = vec![];
+
+                for (pattern, body) in arms {
+                    let mut bindings = vec![];
+                    let pattern = self.pattern(pattern, &mut bindings);
+
+                    self.chunk.arms.push(Arm { pattern, targets: vec![] });
+
+                    let arm = (self.chunk.arms.len() - 1) as u32;
+                    let test = self.emit(Op::Match(arm, 0));
+
+                    self.open_scope(&bindings, body);
+
+                    let enter = self.emit(Op::EnterScope(0));
+
+                    self.chunk.arms[arm as usize].targets = bindings.iter().map(|name| self.declare(name)).collect();
+
+                    self.emit(Op::Bind(arm));
+                    self.node(body);
+                    self.emit(Op::ExitScope);
+                    self.end_block(enter);
+
+                    self.emit(Op::Stash(0));
+
+                    ends.push(self.emit(Op::Jump(0)));
+
+                    self.patch(test);
+                }
+
+                self.emit(Op::NoMatch);
+
+                for end in ends {
+                    self.patch(end);
+                }
+            },
+            Node::Throw(value) => {
+                self.node(value);
+
+                self.emit(Op::Throw);
+            },
+            Node::Try(body, catch, finally) => {
+                let finally_handler = finally.as_ref().map(|_| self.emit(Op::PushHandler(Handler::Finally, 0)));
+
+                match catch {
+                    Some((binding, catch_body)) => {
+                        let handler = self.emit(Op::PushHandler(Handler::Catch, 0));
+
+                        self.block(body);
+
+                        self.emit(Op::PopHandler);
+
+                        let skip = self.emit(Op::Jump(0));
+
+                        self.patch(handler);
+
+                        // The handler pushes the error before the catch scope exists, as the
+                        // interpreter holds it while creating that context.
+                        let bindings: Vec<String> = binding.iter().cloned().collect();
+
+                        self.open_scope(&bindings, catch_body);
+
+                        let enter = self.emit(Op::EnterScope(0));
+
+                        if let Some(name) = binding {
+                            let target = self.declare(name);
+
+                            self.emit(Op::Define(target));
+                        }
+
+                        self.emit(Op::Pop);
+                        self.node(catch_body);
+                        self.emit(Op::ExitScope);
+                        self.end_block(enter);
+
+                        self.patch(skip);
+                    },
+                    None => self.block(body)
+                }
+
+                if let (Some(handler), Some(finally_body)) = (finally_handler, finally) {
+                    self.emit(Op::PopHandler);
+
+                    self.block(finally_body);
+                    self.emit(Op::Pop);
+
+                    let skip = self.emit(Op::Jump(0));
+
+                    self.patch(handler);
+
+                    self.block(finally_body);
+                    self.emit(Op::Pop);
+                    self.emit(Op::Rethrow);
+
+                    self.patch(skip);
+                }
+            },
+            Node::If(condition, body, else_body) => self.if_else(condition, body, else_body.as_deref(), Compiler::node),
+            Node::WhileLoop(condition, body) => {
+                // The body's scope is created once, before the first test of the condition, and
+                // the condition is evaluated in the enclosing scope.
+                self.emit(Op::Null);
+
+                let enter = self.emit(Op::EnterScope(0));
+
+                self.emit(Op::SwapScope);
+
+                let start = self.here();
+
+                self.node(condition);
+
+                let exit = self.emit(Op::JumpIfFalse(0));
+
+                self.emit(Op::SwapScope);
+
+                self.open_scope(&[], body);
+                self.node(body);
+                self.end_block(enter);
+
+                self.emit(Op::Stash(0));
+                self.emit(Op::SwapScope);
+                self.emit(Op::Loop(start));
+
+                self.patch(exit);
+
+                self.emit(Op::SwapScope);
+                self.emit(Op::ExitScope);
+            },
+            Node::ForLoop(name, iterable, body) => {
+                self.emit(Op::Null);
+                self.node(iterable);
+
+                self.open_scope(std::slice::from_ref(name), body);
+
+                let enter = self.emit(Op::EnterScope(0));
+
+                self.emit(Op::Iterate);
+
+                let start = self.here();
+                let next = self.emit(Op::Next(0));
+                let target = self.declare(name);
+
+                self.emit(Op::Define(target));
+                self.emit(Op::Pop);
+
+                self.node(body);
+
+                self.emit(Op::Stash(2));
+                self.emit(Op::Loop(start));
+
+                self.patch(next);
+
+                self.emit(Op::Pop);
+                self.emit(Op::Pop);
+                self.emit(Op::ExitScope);
+                self.end_block(enter);
+            },
+            Node::Import(module, names) => {
+                self.chunk.imports.push(ImportInfo { module: module.clone(), names: names.clone() });
+
+                self.emit(Op::Import((self.chunk.imports.len() - 1) as u32));
+            },
+            Node::Export(declaration) => {
+                self.node(declaration);
+
+                match declaration.as_ref() {
+                    Node::VarDef(name, _, _) | Node::FuncDef(name, _, _, _) | Node::StructDef(name, _, _) | Node::EnumDef(name, _) => {
+                        let name = self.name(name);
+
+                        self.emit(Op::Export(name));
+                    },
+                    _ => {
+                        let message = self.constant(Value::Str("Only declarations can be exported".into()));
+
+                        self.emit(Op::Fail(message));
+                    }
+                }
+            },
+            Node::Empty | Node::EOF => { self.emit(Op::Null); }
+        }
+    }
+
+    // Compiles a pattern of a match arm, listing the names it binds in order. Its literals are
+    // evaluated in the scope the match is in.
+    fn pattern(&mut self, pattern: &node::Pattern, bindings: &mut Vec<String>) -> Pattern {
+        match pattern {
+            node::Pattern::Wildcard => Pattern::Wildcard,
+            node::Pattern::Binding(name) => {
+                bindings.push(name.clone());
+
+                Pattern::Binding
+            },
+            node::Pattern::Literal(literal) => self.literal(literal),
+            node::Pattern::Variant(enum_name, variant, patterns) => {
+                let patterns = patterns.iter().map(|pattern| self.pattern(pattern, bindings)).collect();
+
+                Pattern::Variant(enum_name.clone(), variant.clone(), patterns)
+            }
+        }
+    }
+
+    // The parser only produces numbers, negated numbers, strings and `true`, `false` and `null`
+    // as literal patterns.
+    fn literal(&mut self, literal: &Node) -> Pattern {
+        match literal {
+            Node::Int(n) => Pattern::Constant(Value::Int(*n)),
+            Node::Float(n) => Pattern::Constant(Value::Float(*n)),
+            Node::Str(string) => Pattern::Constant(Value::Str(string.as_str().into())),
+            Node::UnaryOp(number, TokenType::Minus) => match self.literal(number) {
+                Pattern::Constant(value) => match value.negate() {
+                    Ok(value) => Pattern::Constant(value),
+                    Err(_) => Pattern::Constant(value)
+                },
+                pattern => pattern
+            },
+            Node::VarAcc(name) | Node::ResolvedAcc(resolver::Lookup { name, .. }) => Pattern::Load(self.lookup(name, 0)),
+            _ => Pattern::Constant(Value::Null)
+        }
+    }
+}
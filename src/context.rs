@@ -1,62 +1,205 @@
-use crate::value::Value;
-use std::collections::HashMap;
+use crate::value::{Function, Object, StructType, Value};
+use crate::bytecode::Closure;
+use crate::resolver::{Layout, Lookup, Step};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
 
-use rand;
-use rand::Rng;
+// A handle to a context in the `ContextManager` arena. The generation tells a handle to a freed
+// context apart from one to the context that later reuses its slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContextId {
+    index: u32,
+    generation: u32
+}
 
+// Besides its symbols, looked up by name, a context has slots, which resolved and compiled code
+// addresses by index. A slot is empty until its variable is defined. The layout of a context the
+// resolver laid out names its slots, so that they can still be found by name.
 #[derive(Debug, Clone)]
 pub struct Context {
-    pub id: i32,
-    pub parent: Option<i32>,
-    symbols: HashMap<String, Value>
+    pub id: ContextId,
+    pub parent: Option<ContextId>,
+    symbols: HashMap<String, Value>,
+    slots: Vec<Option<Value>>,
+    layout: Option<Rc<Layout>>
 }
 
 impl Context {
-    pub fn new(manager: &ContextManager, parent: Option<i32>) -> Context {
-
-        let mut id: i32;
-
-        let mut rng = rand::thread_rng();
-
-        loop {
-            id = rng.gen_range(0..0xFFFF);
-
-            if !manager.has_id(id) { break; };
-        }
+    pub fn new(id: ContextId, parent: Option<ContextId>) -> Context {
+        Context::with_slots(id, parent, 0)
+    }
 
+    pub fn with_slots(id: ContextId, parent: Option<ContextId>, size: usize) -> Context {
         Context {
             id,
             parent,
-            symbols: HashMap::new()
+            symbols: HashMap::new(),
+            slots: vec![None; size],
+            layout: None
         }
     }
 
+    pub fn with_layout(id: ContextId, parent: Option<ContextId>, layout: Rc<Layout>) -> Context {
+        let mut context = Context::with_slots(id, parent, layout.len());
+
+        context.layout = Some(layout);
+
+        context
+    }
+
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.layout.as_ref()?.slot(name)
+    }
+
     fn set(&mut self, name: &str, value: Value) {
-        self.symbols.insert(String::from(name), value);
+        match self.slot(name) {
+            Some(slot) => self.slots[slot] = Some(value),
+            None => { self.symbols.insert(String::from(name), value); }
+        }
     }
 
     pub fn get(&self, name: &str) -> Option<&Value> {
-        match self.symbols.get(name) {
-            Some(value) => Some(&value),
-            None => None
+        if let Some(slot) = self.slot(name) {
+            if let Some(value) = &self.slots[slot] {
+                return Some(value);
+            }
+        }
+
+        self.symbols.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match self.slot(name) {
+            Some(slot) if self.slots[slot].is_some() => self.slots[slot].as_mut(),
+            _ => self.symbols.get_mut(name)
         }
     }
+
+    fn values(&self) -> impl Iterator<Item = &Value> {
+        self.symbols.values().chain(self.slots.iter().flatten())
+    }
+
+    // The names of the variables defined in this context.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.symbols.keys().cloned().collect();
+
+        if let Some(layout) = &self.layout {
+            names.extend(layout.names().into_iter().filter(|name| self.get(name).is_some()));
+        }
+
+        names
+    }
+}
+
+struct Slot {
+    generation: u32,
+    context: Option<Context>
+}
+
+// A value that refers to contexts, and so must keep them alive wherever it is held, or a list
+// that a value was stored into, and so may be part of a cycle.
+enum Tracked {
+    Func(Weak<Function>),
+    Closure(Weak<Closure>),
+    Struct(Weak<StructType>),
+    List(Weak<RefCell<Vec<Value>>>),
+    Instance(Weak<RefCell<Object>>)
 }
 
+impl Tracked {
+    fn new(value: &Value) -> Option<Tracked> {
+        match value {
+            Value::Func(function) => Some(Tracked::Func(Rc::downgrade(function))),
+            Value::Closure(closure) => Some(Tracked::Closure(Rc::downgrade(closure))),
+            Value::Struct(struct_type) => Some(Tracked::Struct(Rc::downgrade(struct_type))),
+            Value::List(list) => Some(Tracked::List(Rc::downgrade(list))),
+            Value::Instance(object) => Some(Tracked::Instance(Rc::downgrade(object))),
+            _ => None
+        }
+    }
+
+    fn value(&self) -> Option<Value> {
+        match self {
+            Tracked::Func(function) => function.upgrade().map(Value::Func),
+            Tracked::Closure(closure) => closure.upgrade().map(Value::Closure),
+            Tracked::Struct(struct_type) => struct_type.upgrade().map(Value::Struct),
+            Tracked::List(list) => list.upgrade().map(Value::List),
+            Tracked::Instance(object) => object.upgrade().map(Value::Instance)
+        }
+    }
+}
+
+// The number of contexts, and of tracked objects, below which the collector never runs.
+const MIN_THRESHOLD: usize = 1024;
+
+// Contexts are freed by tracing: the prelude and root contexts are always live, and so is every
+// context the caller names and every context reachable from them through parents and the values
+// they hold. Lists and instances are reference counted, which frees them except in cycles, so the
+// collector also clears every one it can find that is unreachable, which breaks its cycles. It
+// finds them through the contexts and through the objects the interpreter tracks: every function,
+// struct and instance, and the lists a cycle can be closed through. One the heap refers to fewer
+// times than it is referenced in all is held from outside it, by the host or the interpreter, and
+// is live along with the contexts it refers to. The interpreter collects once the table or the
+// tracked objects have doubled since the last collection. Contexts live in an arena of slots, and
+// a freed slot is reused with the next generation.
 pub struct ContextManager {
-    contexts: HashMap<i32, Context>
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    live: usize,
+    prelude: Option<ContextId>,
+    roots: Vec<ContextId>,
+    threshold: usize,
+    objects: Vec<Tracked>,
+    lists: HashMap<usize, Tracked>,
+    object_threshold: usize,
+    allocated: usize
+}
+
+impl Default for ContextManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ContextManager {
 
     pub fn new() -> ContextManager {
         ContextManager {
-            contexts: HashMap::new()
+            slots: vec![],
+            free: vec![],
+            live: 0,
+            prelude: None,
+            roots: vec![],
+            threshold: MIN_THRESHOLD,
+            objects: vec![],
+            lists: HashMap::new(),
+            object_threshold: MIN_THRESHOLD,
+            allocated: 0
         }
     }
 
-    pub fn get(&self, context_id: i32, name: &str) -> Option<&Value> {
-        let context = self.contexts.get(&context_id)?;
+    // The context `id` refers to, or `None` if it has been freed.
+    pub fn context(&self, id: ContextId) -> Option<&Context> {
+        match self.slots.get(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.context.as_ref(),
+            _ => None
+        }
+    }
+
+    fn context_mut(&mut self, id: ContextId) -> Option<&mut Context> {
+        match self.slots.get_mut(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.context.as_mut(),
+            _ => None
+        }
+    }
+
+    pub fn is_live(&self, id: ContextId) -> bool {
+        self.context(id).is_some()
+    }
+
+    pub fn get(&self, context_id: ContextId, name: &str) -> Option<&Value> {
+        let context = self.context(context_id)?;
 
         match context.get(name) {
             Some(value) => Some(value),
@@ -69,29 +212,411 @@ impl ContextManager {
         }
     }
 
-    pub fn set(&mut self, context_id: i32, name: &str, value: Value) -> Option<&Value> {
-        let context = self.contexts.get_mut(&context_id)?;
+    pub fn get_local(&self, context_id: ContextId, name: &str) -> Option<&Value> {
+        self.context(context_id)?.get(name)
+    }
+
+    pub fn get_mut(&mut self, context_id: ContextId, name: &str) -> Option<&mut Value> {
+        let owner = self.find_owner(context_id, name)?;
+
+        self.context_mut(owner)?.get_mut(name)
+    }
+
+    // The names visible from `context_id`, from its own out to the prelude's.
+    pub fn names(&self, context_id: ContextId) -> Vec<String> {
+        let mut names = vec![];
+        let mut id = Some(context_id);
+
+        while let Some(context) = id.and_then(|id| self.context(id)) {
+            names.extend(context.names());
+
+            id = context.parent;
+        }
+
+        names
+    }
+
+    // The context `depth` parents above `context_id`.
+    pub fn ancestor(&self, context_id: ContextId, depth: usize) -> Option<ContextId> {
+        let mut id = context_id;
+
+        for _ in 0..depth {
+            id = self.context(id)?.parent?;
+        }
+
+        Some(id)
+    }
+
+    pub fn get_slot(&self, context_id: ContextId, depth: usize, index: usize) -> Option<&Value> {
+        let context = self.context(self.ancestor(context_id, depth)?)?;
+
+        context.slots.get(index)?.as_ref()
+    }
+
+    pub fn set_slot(&mut self, context_id: ContextId, index: usize, value: Value) {
+        if let Some(slot) = self.context_mut(context_id).and_then(|context| context.slots.get_mut(index)) {
+            *slot = Some(value);
+        }
+    }
+
+    // Follows the steps of a resolved lookup, returning the first value found.
+    pub fn lookup(&self, context_id: ContextId, lookup: &Lookup) -> Option<&Value> {
+        lookup.steps.iter().find_map(|step| match *step {
+            Step::Slot(depth, slot) => self.get_slot(context_id, depth as usize, slot as usize),
+            Step::Local(depth) => self.get_local(self.ancestor(context_id, depth as usize)?, &lookup.name),
+            Step::Global(depth) => self.get(self.ancestor(context_id, depth as usize)?, &lookup.name)
+        })
+    }
+
+    // Returns the id of the nearest context in the parent chain that defines `name`.
+    pub fn find_owner(&self, context_id: ContextId, name: &str) -> Option<ContextId> {
+        let context = self.context(context_id)?;
+
+        match context.get(name) {
+            Some(_) => Some(context_id),
+            None => self.find_owner(context.parent?, name)
+        }
+    }
+
+    pub fn set(&mut self, context_id: ContextId, name: &str, value: Value) -> Option<&Value> {
+        let context = self.context_mut(context_id)?;
 
         context.set(name, value);
 
         self.get(context_id, name)
     }
 
-    pub fn add_context(&mut self, context: Context) {
-        self.contexts.insert(context.id, context);
+    pub fn create_context(&mut self, parent: Option<ContextId>) -> ContextId {
+        self.create_frame(parent, 0)
+    }
+
+    // Creates a context with `size` empty slots.
+    pub fn create_frame(&mut self, parent: Option<ContextId>, size: usize) -> ContextId {
+        self.allocate(|id| Context::with_slots(id, parent, size))
+    }
+
+    // Creates a context with an empty slot for each variable in `layout`.
+    pub fn create_scope(&mut self, parent: Option<ContextId>, layout: Rc<Layout>) -> ContextId {
+        self.allocate(|id| Context::with_layout(id, parent, layout))
+    }
+
+    fn allocate(&mut self, context: impl FnOnce(ContextId) -> Context) -> ContextId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, context: None });
+
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        let id = ContextId { index, generation: slot.generation };
+
+        slot.context = Some(context(id));
+        self.live += 1;
+
+        id
+    }
+
+    fn free_context(&mut self, index: u32) {
+        let slot = &mut self.slots[index as usize];
+
+        slot.context = None;
+        slot.generation += 1;
+
+        self.free.push(index);
+        self.live -= 1;
+    }
+
+    // The parentless context holding the language constants and the built-ins, created on first use.
+    pub fn prelude(&mut self) -> ContextId {
+        if let Some(id) = self.prelude {
+            return id;
+        }
+
+        let id = self.create_context(None);
+
+        self.set(id, "true", Value::Boolean(true));
+        self.set(id, "false", Value::Boolean(false));
+        self.set(id, "null", Value::Null);
+
+        self.prelude = Some(id);
+        self.roots.push(id);
+
+        id
     }
 
-    pub fn create_context(&mut self, parent: Option<i32>) -> i32 {
-        let context = Context::new(self, parent);
+    // Creates a context whose only parent is the prelude, used for the entry script and every module.
+    pub fn create_root_context(&mut self) -> ContextId {
+        let prelude = self.prelude();
+
+        let id = self.create_context(Some(prelude));
+
+        self.roots.push(id);
+
+        id
+    }
 
-        let id = context.id;
+    pub fn context_count(&self) -> usize {
+        self.live
+    }
 
-        self.add_context(context);
+    pub fn should_collect(&self) -> bool {
+        self.live >= self.threshold || self.objects.len() + self.lists.len() >= self.object_threshold
+    }
 
-        return id;
+    // Records a new function, struct or instance, so that the collector keeps the contexts it
+    // refers to alive while anything holds it, the host included.
+    pub fn track(&mut self, value: &Value) {
+        self.objects.extend(Tracked::new(value));
     }
 
-    pub fn has_id(&self, id: i32) -> bool {
-        self.contexts.contains_key(&id)
+    // Records that `stored` was stored into `object`, so that the collector can find it if that
+    // closed a cycle. Only a list, an instance or a variant holding one can close one, and
+    // instances are tracked already. A list is tracked once however often it is stored into.
+    pub fn track_store(&mut self, object: &Value, stored: &Value) {
+        if let (Value::List(list), Value::List(_) | Value::Instance(_) | Value::Tagged(..)) = (object, stored) {
+            self.lists.entry(address(object)).or_insert_with(|| Tracked::List(Rc::downgrade(list)));
+        }
     }
-}
\ No newline at end of file
+
+    // Frees every context that is not reachable from the root contexts, `contexts`, `values` or
+    // the objects held from outside the heap, clears every unreachable list and instance, and
+    // returns how many contexts were freed.
+    pub fn collect(&mut self, contexts: &[ContextId], values: &[Value]) -> usize {
+        self.objects.retain(|object| object.value().is_some());
+        self.lists.retain(|_, list| list.value().is_some());
+
+        let mut heap = self.heap();
+
+        let mut marked = vec![false; self.slots.len()];
+        let mut pending: Vec<ContextId> = self.roots.iter().chain(contexts).copied().collect();
+        let mut objects = HashSet::new();
+
+        for value in values.iter().chain(heap.values().filter(|entry| entry.held_outside()).map(|entry| &entry.value)) {
+            references(value, &mut pending, &mut objects);
+        }
+
+        while let Some(id) = pending.pop() {
+            let context = match self.context(id) {
+                Some(context) if !marked[id.index as usize] => context,
+                _ => continue
+            };
+
+            marked[id.index as usize] = true;
+
+            pending.extend(context.parent);
+
+            for value in context.values() {
+                references(value, &mut pending, &mut objects);
+            }
+        }
+
+        // Only lists and instances are cleared, and functions are freed sooner with their contexts.
+        heap.retain(|_, entry| matches!(entry.value, Value::List(_) | Value::Instance(_)));
+
+        let before = self.live;
+
+        for (index, marked) in marked.into_iter().enumerate() {
+            if !marked && self.slots[index].context.is_some() {
+                self.free_context(index as u32);
+            }
+        }
+
+        for (key, entry) in heap {
+            if !objects.contains(&key) {
+                clear(&entry.value);
+            }
+        }
+
+        self.threshold = MIN_THRESHOLD.max(self.live * 2);
+        self.object_threshold = MIN_THRESHOLD.max((self.objects.len() + self.lists.len()) * 2);
+
+        before - self.live
+    }
+
+    // Counts `size` more bytes of strings or items of lists, and returns whether those counted since
+    // the live ones were last measured could take their total over `max_size`.
+    pub fn charge(&mut self, size: usize, max_size: usize) -> bool {
+        self.allocated = self.allocated.saturating_add(size);
+        self.allocated > max_size
+    }
+
+    // The total size of the strings and lists reachable from the contexts, the tracked objects and
+    // `values`, with `size` more about to be added to them, which `charge` counts on from. Only
+    // after a collection is every context reachable.
+    pub fn measure(&mut self, values: &[Value], size: usize) -> usize {
+        let mut pending: Vec<Value> = values.iter().filter(|value| sized(value)).cloned().collect();
+
+        pending.extend(self.objects.iter().chain(self.lists.values()).filter_map(Tracked::value));
+
+        for context in self.slots.iter().filter_map(|slot| slot.context.as_ref()) {
+            pending.extend(context.values().filter(|value| sized(value)).cloned());
+        }
+
+        let mut seen = HashSet::new();
+        let mut size = size;
+
+        while let Some(value) = pending.pop() {
+            match &value {
+                Value::Str(string) if seen.insert(Rc::as_ptr(string) as *const () as usize) => size += string.len(),
+                Value::List(items) if seen.insert(address(&value)) => {
+                    let items = items.borrow();
+
+                    size += items.len();
+                    pending.extend(items.iter().filter(|item| sized(item)).cloned());
+                },
+                Value::Instance(object) if seen.insert(address(&value)) => {
+                    pending.extend(object.borrow().fields.iter().map(|(_, field)| field).filter(|field| sized(field)).cloned());
+                },
+                Value::Tagged(_, _, values) => pending.extend(values.iter().filter(|value| sized(value)).cloned()),
+                _ => {}
+            }
+        }
+
+        self.allocated = size;
+
+        size
+    }
+
+    // Every function, struct, list and instance reachable from a context or a tracked object, with
+    // the number of references to it from the contexts and from the lists and instances.
+    fn heap(&self) -> HashMap<usize, HeapEntry> {
+        let mut heap = HashMap::new();
+        let mut pending = vec![];
+
+        for context in self.slots.iter().filter_map(|slot| slot.context.as_ref()) {
+            for value in context.values() {
+                count(value, &mut heap, &mut pending);
+            }
+        }
+
+        for object in self.objects.iter().chain(self.lists.values()) {
+            if let Some(value) = object.value() {
+                heap.entry(address(&value)).or_insert_with(|| {
+                    pending.push(value.clone());
+
+                    HeapEntry { value, internal: 0 }
+                });
+            }
+        }
+
+        while let Some(value) = pending.pop() {
+            match &value {
+                Value::List(items) => {
+                    for item in items.borrow().iter() {
+                        count(item, &mut heap, &mut pending);
+                    }
+                },
+                Value::Instance(object) => {
+                    for (_, field) in &object.borrow().fields {
+                        count(field, &mut heap, &mut pending);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        heap
+    }
+}
+
+struct HeapEntry {
+    value: Value,
+    internal: usize
+}
+
+impl HeapEntry {
+    // The entry's own handle is one of the references, and the rest come from the heap.
+    fn held_outside(&self) -> bool {
+        let strong = match &self.value {
+            Value::Func(function) => Rc::strong_count(function),
+            Value::Closure(closure) => Rc::strong_count(closure),
+            Value::Struct(struct_type) => Rc::strong_count(struct_type),
+            Value::List(items) => Rc::strong_count(items),
+            Value::Instance(object) => Rc::strong_count(object),
+            _ => 0
+        };
+
+        strong > self.internal + 1
+    }
+}
+
+fn address(value: &Value) -> usize {
+    match value {
+        Value::Func(function) => Rc::as_ptr(function) as *const () as usize,
+        Value::Closure(closure) => Rc::as_ptr(closure) as *const () as usize,
+        Value::Struct(struct_type) => Rc::as_ptr(struct_type) as *const () as usize,
+        Value::List(items) => Rc::as_ptr(items) as *const () as usize,
+        Value::Instance(object) => Rc::as_ptr(object) as *const () as usize,
+        _ => 0
+    }
+}
+
+// Counts a reference from the heap to each function, struct, list and instance `value` is or
+// holds directly, and queues the ones seen for the first time to have their own references
+// counted.
+fn count(value: &Value, heap: &mut HashMap<usize, HeapEntry>, pending: &mut Vec<Value>) {
+    match value {
+        Value::Func(_) | Value::Closure(_) | Value::Struct(_) | Value::List(_) | Value::Instance(_) => {
+            let entry = heap.entry(address(value)).or_insert_with(|| {
+                pending.push(value.clone());
+
+                HeapEntry { value: value.clone(), internal: 0 }
+            });
+
+            entry.internal += 1;
+        },
+        Value::Tagged(_, _, values) => {
+            for value in values {
+                count(value, heap, pending);
+            }
+        },
+        _ => {}
+    }
+}
+
+// Whether `value` is or may hold a string or a list.
+fn sized(value: &Value) -> bool {
+    matches!(value, Value::Str(_) | Value::List(_) | Value::Instance(_) | Value::Tagged(..))
+}
+
+// Empties an unreachable list or instance, dropping its references to the rest of its cycle.
+fn clear(value: &Value) {
+    match value {
+        Value::List(items) => drop(items.take()),
+        Value::Instance(object) => drop(std::mem::take(&mut object.borrow_mut().fields)),
+        _ => {}
+    }
+}
+
+// Pushes the ids of the contexts `value` keeps alive. Lists and instances already in `objects`
+// are skipped, since they can contain themselves.
+fn references(value: &Value, ids: &mut Vec<ContextId>, objects: &mut HashSet<usize>) {
+    match value {
+        Value::Func(function) => ids.push(function.context),
+        Value::Closure(closure) => ids.push(closure.context),
+        Value::Struct(struct_type) => ids.push(struct_type.methods),
+        Value::Instance(object) if objects.insert(Rc::as_ptr(object) as *const () as usize) => {
+            let object = object.borrow();
+
+            ids.push(object.methods);
+
+            for (_, field) in &object.fields {
+                references(field, ids, objects);
+            }
+        },
+        Value::List(values) if objects.insert(Rc::as_ptr(values) as *const () as usize) => {
+            for value in values.borrow().iter() {
+                references(value, ids, objects);
+            }
+        },
+        Value::Tagged(_, _, values) => {
+            for value in values {
+                references(value, ids, objects);
+            }
+        },
+        _ => {}
+    }
+}
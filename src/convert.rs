@@ -0,0 +1,248 @@
+use crate::value::Value;
+use crate::interpreter::RuntimeResult;
+use crate::error::{Error, ErrorKind, RuntimeError};
+
+use std::convert::TryFrom;
+
+// Conversions between `Value`s and Rust types, used to pass values across the host boundary.
+// Integers that do not fit in an `i32` fail to convert in either direction, and `()` becomes null.
+pub trait IntoValue {
+    fn into_value(self) -> Result<Value, RuntimeError>;
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError>;
+}
+
+// What a host function may return: any `IntoValue`, or a `Result` to report a runtime error.
+// `name` is the function's, for reporting a return value that does not convert.
+pub trait IntoRuntimeResult {
+    fn into_runtime_result(self, name: &str) -> RuntimeResult;
+}
+
+impl<T: IntoValue> IntoRuntimeResult for T {
+    fn into_runtime_result(self, name: &str) -> RuntimeResult {
+        self.into_value().map_err(|error| {
+            RuntimeError::with_kind(error.kind(), String::from("Result of '") + name + "': " + error.msg())
+        })
+    }
+}
+
+impl<T: IntoValue> IntoRuntimeResult for Result<T, RuntimeError> {
+    fn into_runtime_result(self, name: &str) -> RuntimeResult {
+        self?.into_runtime_result(name)
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> RuntimeError {
+    RuntimeError::with_kind(ErrorKind::Type, String::from("Expected '") + expected + "', got '" + &value.type_name() + "'")
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(self)
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Null)
+    }
+}
+
+macro_rules! integer {
+    ($($integer:ty),*) => {
+        $(
+            impl IntoValue for $integer {
+                fn into_value(self) -> Result<Value, RuntimeError> {
+                    i32::try_from(self).map(Value::Int).map_err(|_| RuntimeError::with_kind(ErrorKind::Type, self.to_string() + " is out of range for int"))
+                }
+            }
+
+            impl FromValue for $integer {
+                fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+                    match value {
+                        Value::Int(n) => <$integer>::try_from(*n).map_err(|_| RuntimeError::with_kind(ErrorKind::Type, n.to_string() + " is out of range for " + stringify!($integer))),
+                        other => Err(mismatch("int", other))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! float {
+    ($($float:ty),*) => {
+        $(
+            impl IntoValue for $float {
+                fn into_value(self) -> Result<Value, RuntimeError> {
+                    Ok(Value::Float(self as f32))
+                }
+            }
+
+            impl FromValue for $float {
+                fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+                    match value {
+                        Value::Float(n) => Ok(*n as $float),
+                        Value::Int(n) => Ok(*n as $float),
+                        other => Err(mismatch("float", other))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+float!(f32, f64);
+
+impl IntoValue for bool {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Boolean(self))
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(mismatch("bool", other))
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(self.into()))
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(self.into()))
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Str(string) => Ok(String::from(&**string)),
+            other => Err(mismatch("str", other))
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        Ok(Value::list(self.into_iter().map(IntoValue::into_value).collect::<Result<_, _>>()?))
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::List(items) => items.borrow().iter().map(T::from_value).collect(),
+            other => Err(mismatch("list", other))
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Result<Value, RuntimeError> {
+        match self {
+            Some(value) => value.into_value(),
+            None => Ok(Value::Null)
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some)
+        }
+    }
+}
+
+// Tuples convert to and from lists of the same length.
+macro_rules! tuple {
+    ($length:expr; $($name:ident: $index:tt),*) => {
+        impl<$($name: IntoValue),*> IntoValue for ($($name,)*) {
+            fn into_value(self) -> Result<Value, RuntimeError> {
+                Ok(Value::list(vec![$(self.$index.into_value()?),*]))
+            }
+        }
+
+        impl<$($name: FromValue),*> FromValue for ($($name,)*) {
+            fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+                let items = match value {
+                    Value::List(items) => items.borrow(),
+                    other => return Err(mismatch("list", other))
+                };
+
+                if items.len() != $length {
+                    return Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Expected a list of ") + &$length.to_string() + " items, got " + &items.len().to_string()));
+                }
+
+                Ok(($($name::from_value(&items[$index])?,)*))
+            }
+        }
+    };
+}
+
+tuple!(1; A: 0);
+tuple!(2; A: 0, B: 1);
+tuple!(3; A: 0, B: 1, C: 2);
+tuple!(4; A: 0, B: 1, C: 2, D: 3);
+tuple!(5; A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple!(6; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+
+// A Rust function with typed parameters that can be registered as a native function. `Args`
+// is the tuple of its parameter types; each argument is converted with `FromValue`.
+pub trait HostFunction<Args> {
+    fn arity(&self) -> usize;
+
+    fn invoke(&self, name: &str, args: &[Value]) -> RuntimeResult;
+}
+
+fn argument<T: FromValue>(name: &str, args: &[Value], index: usize) -> Result<T, RuntimeError> {
+    T::from_value(&args[index]).map_err(|error| {
+        RuntimeError::with_kind(ErrorKind::Argument, String::from("Argument ") + &(index + 1).to_string() + " of '" + name + "': " + error.msg())
+    })
+}
+
+macro_rules! host_function {
+    ($length:expr; $($name:ident: $index:tt),*) => {
+        impl<Func, Return, $($name),*> HostFunction<($($name,)*)> for Func
+        where
+            Func: Fn($($name),*) -> Return,
+            Return: IntoRuntimeResult,
+            $($name: FromValue),*
+        {
+            fn arity(&self) -> usize {
+                $length
+            }
+
+            #[allow(unused_variables)]
+            fn invoke(&self, name: &str, args: &[Value]) -> RuntimeResult {
+                (self)($(argument::<$name>(name, args, $index)?),*).into_runtime_result(name)
+            }
+        }
+    };
+}
+
+host_function!(0;);
+host_function!(1; A: 0);
+host_function!(2; A: 0, B: 1);
+host_function!(3; A: 0, B: 1, C: 2);
+host_function!(4; A: 0, B: 1, C: 2, D: 3);
+host_function!(5; A: 0, B: 1, C: 2, D: 3, E: 4);
+host_function!(6; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
@@ -0,0 +1,431 @@
+// Machine-readable dumps of the token stream and the syntax tree, in JSON or as S-expressions.
+//
+// JSON: the token stream is an array of token objects and the tree is a single node object.
+// Every token and node is an object whose first key is "type" (the `TokenType` or `Node`
+// variant name) followed by "span" and the fields below. A span is
+// {"start": {"line": 1, "column": 1}, "end": {...}}, with 1-based positions and `end` just
+// past the last character, or null when unknown. Absent optional values are null. Floats JSON
+// cannot represent are written as the strings "NaN", "Infinity" and "-Infinity".
+//
+//   tokens        Int, Float, Str, Keyword, Identifier: "value"; every other token: no fields
+//   Int, Float    "value"                      Str                 "value"
+//   VarAcc        "name"                       ListDef             "items"
+//   BinaryOp      "left", "op", "right"        UnaryOp             "op", "operand"
+//   Range         "start", "end", "inclusive"  Index               "object", "index"
+//   VarDef        "name", "annotation", "value"
+//   FuncDef       "name", "params" ([{"name", "annotation"}]), "return_type", "body"
+//   FuncCall, OptionalCall              "callee", "args"
+//   FieldAcc, OptionalFieldAcc          "object", "field"
+//   FieldAssign   "object", "field", "value"
+//   StructDef     "name", "fields", "methods"
+//   EnumDef       "name", "variants" ([{"name", "fields"}])
+//   Match         "subject", "arms" ([{"pattern", "body"}])
+//   Throw         "value"
+//   Try           "body", "catch" ({"binding", "body"}), "finally"
+//   Statements    "statements", "returns_last"
+//   If            "condition", "body", "else"
+//   WhileLoop     "condition", "body"          ForLoop             "name", "iterable", "body"
+//   Import        "path", "names"              Export              "declaration"
+//   Empty, EOF    no fields
+//
+// Patterns are objects too: Wildcard, Binding ("name"), Literal ("value", a node) and
+// Variant ("enum", "variant", "patterns"). Operators are given as their source text.
+//
+// S-expressions transliterate the JSON: an object is `(Type :key value ...)`, or `(:key value ...)`
+// without a type, an array is `(item ...)`, null is `nil` and booleans are `true` and `false`.
+
+use crate::node::*;
+use crate::token::{TokenType, Span, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    SExpr
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "sexp" => Some(Format::SExpr),
+            _ => None
+        }
+    }
+}
+
+pub fn dump_tokens(tokens: &[TokenType], spans: &[Span], format: Format) -> String {
+    let tokens = tokens.iter()
+        .enumerate()
+        .map(|(i, token)| token_data(token, spans.get(i).copied()))
+        .collect();
+
+    render(&Data::List(tokens), format)
+}
+
+pub fn dump_node(node: &Node, spans: &NodeSpans, format: Format) -> String {
+    render(&node_data(node, spans), format)
+}
+
+enum Data {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Str(String),
+    List(Vec<Data>),
+    Object(Option<&'static str>, Vec<(&'static str, Data)>)
+}
+
+fn render(data: &Data, format: Format) -> String {
+    let mut output = String::new();
+
+    match format {
+        Format::Json => json(data, &mut output),
+        Format::SExpr => sexp(data, &mut output)
+    }
+
+    output
+}
+
+fn json(data: &Data, output: &mut String) {
+    match data {
+        Data::Null => output.push_str("null"),
+        Data::Bool(value) => output.push_str(&value.to_string()),
+        Data::Int(value) => output.push_str(&value.to_string()),
+        Data::Float(value) if value.is_finite() => output.push_str(&value.to_string()),
+        Data::Float(value) if value.is_nan() => quote("NaN", output),
+        Data::Float(value) => quote(if *value > 0.0 { "Infinity" } else { "-Infinity" }, output),
+        Data::Str(string) => quote(string, output),
+        Data::List(items) => {
+            output.push('[');
+
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+
+                json(item, output);
+            }
+
+            output.push(']');
+        },
+        Data::Object(name, fields) => {
+            output.push('{');
+
+            if let Some(name) = name {
+                output.push_str("\"type\":");
+                quote(name, output);
+            }
+
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 || name.is_some() {
+                    output.push(',');
+                }
+
+                quote(key, output);
+                output.push(':');
+                json(value, output);
+            }
+
+            output.push('}');
+        }
+    }
+}
+
+fn sexp(data: &Data, output: &mut String) {
+    match data {
+        Data::Null => output.push_str("nil"),
+        Data::List(items) => {
+            output.push('(');
+
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(' ');
+                }
+
+                sexp(item, output);
+            }
+
+            output.push(')');
+        },
+        Data::Object(name, fields) => {
+            output.push('(');
+
+            if let Some(name) = name {
+                output.push_str(name);
+            }
+
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 || name.is_some() {
+                    output.push(' ');
+                }
+
+                output.push(':');
+                output.push_str(key);
+                output.push(' ');
+                sexp(value, output);
+            }
+
+            output.push(')');
+        },
+        _ => json(data, output)
+    }
+}
+
+fn quote(string: &str, output: &mut String) {
+    output.push('"');
+
+    for character in string.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            _ if (character as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", character as u32)),
+            _ => output.push(character)
+        }
+    }
+
+    output.push('"');
+}
+
+fn span_data(span: Option<Span>) -> Data {
+    match span {
+        Some(span) => Data::Object(None, vec![
+            ("start", position_data(span.start)),
+            ("end", position_data(span.end))
+        ]),
+        None => Data::Null
+    }
+}
+
+fn position_data(position: Position) -> Data {
+    Data::Object(None, vec![
+        ("line", Data::Int(position.line as i64)),
+        ("column", Data::Int(position.column as i64))
+    ])
+}
+
+fn token_data(token: &TokenType, span: Option<Span>) -> Data {
+    let (name, value) = match token {
+        TokenType::Int(number) => ("Int", Some(Data::Int(*number as i64))),
+        TokenType::Float(number) => ("Float", Some(Data::Float(*number))),
+        TokenType::Str(string) => ("Str", Some(Data::Str(string.clone()))),
+        TokenType::Keyword(string) => ("Keyword", Some(Data::Str(string.clone()))),
+        TokenType::Identifier(string) => ("Identifier", Some(Data::Str(string.clone()))),
+        TokenType::Plus => ("Plus", None),
+        TokenType::Minus => ("Minus", None),
+        TokenType::Mul => ("Mul", None),
+        TokenType::Div => ("Div", None),
+        TokenType::Pow => ("Pow", None),
+        TokenType::Eq => ("Eq", None),
+        TokenType::Arrow => ("Arrow", None),
+        TokenType::EE => ("EE", None),
+        TokenType::NE => ("NE", None),
+        TokenType::GT => ("GT", None),
+        TokenType::GTE => ("GTE", None),
+        TokenType::LT => ("LT", None),
+        TokenType::LTE => ("LTE", None),
+        TokenType::Not => ("Not", None),
+        TokenType::And => ("And", None),
+        TokenType::Or => ("Or", None),
+        TokenType::NullCoalesce => ("NullCoalesce", None),
+        TokenType::QuestionDot => ("QuestionDot", None),
+        TokenType::BitwiseNot => ("BitwiseNot", None),
+        TokenType::BitwiseAnd => ("BitwiseAnd", None),
+        TokenType::BitwiseOr => ("BitwiseOr", None),
+        TokenType::BitwiseXOr => ("BitwiseXOr", None),
+        TokenType::BitwiseRightShift => ("BitwiseRightShift", None),
+        TokenType::BitwiseLeftShift => ("BitwiseLeftShift", None),
+        TokenType::Semicolon => ("Semicolon", None),
+        TokenType::Comma => ("Comma", None),
+        TokenType::Colon => ("Colon", None),
+        TokenType::Dot => ("Dot", None),
+        TokenType::DotDot => ("DotDot", None),
+        TokenType::DotDotEq => ("DotDotEq", None),
+        TokenType::LeftParen => ("LeftParen", None),
+        TokenType::RightParen => ("RightParen", None),
+        TokenType::LeftBracket => ("LeftBracket", None),
+        TokenType::RightBracket => ("RightBracket", None),
+        TokenType::LeftSquare => ("LeftSquare", None),
+        TokenType::RightSquare => ("RightSquare", None),
+        TokenType::EOF => ("EOF", None)
+    };
+
+    let mut fields = vec![("span", span_data(span))];
+
+    if let Some(value) = value {
+        fields.push(("value", value));
+    }
+
+    Data::Object(Some(name), fields)
+}
+
+fn optional_node(node: &Option<Box<Node>>, spans: &NodeSpans) -> Data {
+    match node {
+        Some(node) => node_data(node, spans),
+        None => Data::Null
+    }
+}
+
+fn nodes_data(nodes: &[Box<Node>], spans: &NodeSpans) -> Data {
+    Data::List(nodes.iter().map(|node| node_data(node, spans)).collect())
+}
+
+fn names_data(names: &[String]) -> Data {
+    Data::List(names.iter().map(|name| Data::Str(name.clone())).collect())
+}
+
+fn type_data(annotation: &Option<Type>) -> Data {
+    match annotation {
+        Some(annotation) => Data::Str(annotation.to_string()),
+        None => Data::Null
+    }
+}
+
+fn node_data(node: &Node, spans: &NodeSpans) -> Data {
+    let (name, fields) = match node {
+        Node::Int(number) => ("Int", vec![("value", Data::Int(*number as i64))]),
+        Node::Float(number) => ("Float", vec![("value", Data::Float(*number))]),
+        Node::Str(string) => ("Str", vec![("value", Data::Str(string.clone()))]),
+        Node::VarAcc(name) => ("VarAcc", vec![("name", Data::Str(name.clone()))]),
+        Node::ListDef(items) => ("ListDef", vec![("items", nodes_data(items, spans))]),
+        Node::BinaryOp(left, op_token, right) => ("BinaryOp", vec![
+            ("left", node_data(left, spans)),
+            ("op", Data::Str(op_token.to_string())),
+            ("right", node_data(right, spans))
+        ]),
+        Node::UnaryOp(operand, op_token) => ("UnaryOp", vec![
+            ("op", Data::Str(op_token.to_string())),
+            ("operand", node_data(operand, spans))
+        ]),
+        Node::Range(start, end, inclusive) => ("Range", vec![
+            ("start", node_data(start, spans)),
+            ("end", node_data(end, spans)),
+            ("inclusive", Data::Bool(*inclusive))
+        ]),
+        Node::Index(object, index) => ("Index", vec![
+            ("object", node_data(object, spans)),
+            ("index", node_data(index, spans))
+        ]),
+        Node::VarDef(name, annotation, value) => ("VarDef", vec![
+            ("name", Data::Str(name.clone())),
+            ("annotation", type_data(annotation)),
+            ("value", node_data(value, spans))
+        ]),
+        Node::FuncDef(name, params, return_type, body) => ("FuncDef", vec![
+            ("name", Data::Str(name.clone())),
+            ("params", Data::List(params.iter().map(|(param, annotation)| Data::Object(None, vec![
+                ("name", Data::Str(param.clone())),
+                ("annotation", type_data(annotation))
+            ])).collect())),
+            ("return_type", type_data(return_type)),
+            ("body", node_data(body, spans))
+        ]),
+        Node::FuncCall(callee, args) => ("FuncCall", vec![
+            ("callee", node_data(callee, spans)),
+            ("args", nodes_data(args, spans))
+        ]),
+        Node::OptionalCall(callee, args) => ("OptionalCall", vec![
+            ("callee", node_data(callee, spans)),
+            ("args", nodes_data(args, spans))
+        ]),
+        Node::FieldAcc(object, field) => ("FieldAcc", vec![
+            ("object", node_data(object, spans)),
+            ("field", Data::Str(field.clone()))
+        ]),
+        Node::OptionalFieldAcc(object, field) => ("OptionalFieldAcc", vec![
+            ("object", node_data(object, spans)),
+            ("field", Data::Str(field.clone()))
+        ]),
+        Node::FieldAssign(object, field, value) => ("FieldAssign", vec![
+            ("object", node_data(object, spans)),
+            ("field", Data::Str(field.clone())),
+            ("value", node_data(value, spans))
+        ]),
+        Node::StructDef(name, fields, methods) => ("StructDef", vec![
+            ("name", Data::Str(name.clone())),
+            ("fields", names_data(fields)),
+            ("methods", nodes_data(methods, spans))
+        ]),
+        Node::EnumDef(name, variants) => ("EnumDef", vec![
+            ("name", Data::Str(name.clone())),
+            ("variants", Data::List(variants.iter().map(|(variant, fields)| Data::Object(None, vec![
+                ("name", Data::Str(variant.clone())),
+                ("fields", names_data(fields))
+            ])).collect()))
+        ]),
+        Node::Match(subject, arms) => ("Match", vec![
+            ("subject", node_data(subject, spans)),
+            ("arms", Data::List(arms.iter().map(|(pattern, body)| Data::Object(None, vec![
+                ("pattern", pattern_data(pattern, spans)),
+                ("body", node_data(body, spans))
+            ])).collect()))
+        ]),
+        Node::Throw(value) => ("Throw", vec![("value", node_data(value, spans))]),
+        Node::Try(body, catch, finally) => ("Try", vec![
+            ("body", node_data(body, spans)),
+            ("catch", match catch {
+                Some((binding, catch_body)) => Data::Object(None, vec![
+                    ("binding", binding.clone().map_or(Data::Null, Data::Str)),
+                    ("body", node_data(catch_body, spans))
+                ]),
+                None => Data::Null
+            }),
+            ("finally", optional_node(finally, spans))
+        ]),
+        Node::Statements(statements, returns_last) => ("Statements", vec![
+            ("statements", nodes_data(statements, spans)),
+            ("returns_last", Data::Bool(*returns_last))
+        ]),
+        Node::If(condition, body, else_body) => ("If", vec![
+            ("condition", node_data(condition, spans)),
+            ("body", node_data(body, spans)),
+            ("else", optional_node(else_body, spans))
+        ]),
+        Node::WhileLoop(condition, body) => ("WhileLoop", vec![
+            ("condition", node_data(condition, spans)),
+            ("body", node_data(body, spans))
+        ]),
+        Node::ForLoop(name, iterable, body) => ("ForLoop", vec![
+            ("name", Data::Str(name.clone())),
+            ("iterable", node_data(iterable, spans)),
+            ("body", node_data(body, spans))
+        ]),
+        Node::Import(path, names) => ("Import", vec![
+            ("path", Data::Str(path.clone())),
+            ("names", names.as_ref().map_or(Data::Null, |names| names_data(names)))
+        ]),
+        Node::Export(declaration) => ("Export", vec![("declaration", node_data(declaration, spans))]),
+        Node::ResolvedAcc(lookup) => ("ResolvedAcc", vec![("name", Data::Str(lookup.name.clone()))]),
+        Node::Frame(layout, body) => ("Frame", vec![
+            ("slots", names_data(&layout.names())),
+            ("body", node_data(body, spans))
+        ]),
+        Node::Define(slot, definition) => ("Define", vec![
+            ("slot", Data::Int(*slot as i64)),
+            ("definition", node_data(definition, spans))
+        ]),
+        Node::Empty => ("Empty", vec![]),
+        Node::EOF => ("EOF", vec![])
+    };
+
+    let mut all_fields = vec![("span", span_data(spans.get(node)))];
+    all_fields.extend(fields);
+
+    Data::Object(Some(name), all_fields)
+}
+
+fn pattern_data(pattern: &Pattern, spans: &NodeSpans) -> Data {
+    match pattern {
+        Pattern::Wildcard => Data::Object(Some("Wildcard"), vec![]),
+        Pattern::Binding(name) => Data::Object(Some("Binding"), vec![("name", Data::Str(name.clone()))]),
+        Pattern::Literal(node) => Data::Object(Some("Literal"), vec![("value", node_data(node, spans))]),
+        Pattern::Variant(enum_name, variant, patterns) => Data::Object(Some("Variant"), vec![
+            ("enum", Data::Str(enum_name.clone())),
+            ("variant", Data::Str(variant.clone())),
+            ("patterns", Data::List(patterns.iter().map(|pattern| pattern_data(pattern, spans)).collect()))
+        ])
+    }
+}
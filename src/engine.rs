@@ -0,0 +1,270 @@
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::checker::Checker;
+use crate::resolver::Resolver;
+use crate::optimizer::{shadowing, Optimizer};
+use crate::interpreter::{Interpreter, RuntimeResult, DEFAULT_MAX_DEPTH, DEFAULT_STACK_SIZE};
+use crate::context::{ContextId, ContextManager};
+use crate::module::ModuleLoader;
+use crate::native::{Arity, NativeFunc, Registry};
+use crate::value::Value;
+use crate::error::{Error, ErrorKind, RuntimeError, TypeErrors};
+use crate::convert::{HostFunction, IntoValue};
+use crate::limits::{InterruptHandle, Limits};
+use crate::compiler::Compiler;
+use crate::vm::Vm;
+
+use std::cell::Cell;
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::thread;
+
+thread_local! {
+    // The size of this thread's stack, if `run_with_stack_size` started it.
+    static STACK_SIZE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Which of the two ways of running scripts an engine uses. Functions defined under one cannot be
+// called by the other, so choose before the first `eval`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    #[default]
+    Interpreter,
+    Vm
+}
+
+// An embeddable interpreter. Every `eval` runs in the same global context, so definitions made
+// by one script are visible to the next, to `call` and to `get_global`. Host functions are
+// installed in the prelude, which modules imported by the scripts see as well.
+pub struct Engine {
+    manager: ContextManager,
+    modules: ModuleLoader,
+    context_id: ContextId,
+    max_depth: usize,
+    stack_size: usize,
+    limits: Limits,
+    interrupt: InterruptHandle,
+    backend: Backend
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        let mut manager = ContextManager::new();
+
+        let prelude = manager.prelude();
+        Registry::prelude().install(&mut manager, prelude);
+
+        let context_id = manager.create_root_context();
+
+        Engine {
+            manager,
+            modules: ModuleLoader::new(),
+            context_id,
+            max_depth: DEFAULT_MAX_DEPTH,
+            stack_size: STACK_SIZE.with(Cell::get).unwrap_or(DEFAULT_STACK_SIZE),
+            limits: Limits::new(),
+            interrupt: InterruptHandle::new(),
+            backend: Backend::Interpreter
+        }
+    }
+
+    // Sets how deeply script functions may recurse before a RecursionError is raised. The
+    // interpreter also raises one before nested nodes overflow its stack, however few calls
+    // are active; see `set_stack_size`.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // Sets the size of the stack of the thread the engine runs on. It is 8 MiB, that of the main
+    // thread, unless the engine was created in `run_with_stack_size`, which sets it.
+    pub fn set_stack_size(&mut self, stack_size: usize) {
+        self.stack_size = stack_size;
+    }
+
+    // Sets the budgets every later `eval` and `call` runs under. The step budget applies to each
+    // run separately.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // A handle that stops the `eval` or `call` in progress. Unlike the engine it is `Send`, so it
+    // can be passed to the thread that decides when to stop. Interrupting between runs stops the next
+    // one as soon as it checks the handle.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    // Sets whether scripts are interpreted from their syntax tree or compiled to bytecode for the
+    // virtual machine. The results are the same.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    fn interpreter(&mut self) -> Interpreter<'_> {
+        let mut interpreter = Interpreter::new(&mut self.manager, &mut self.modules);
+
+        interpreter.set_max_depth(self.max_depth);
+        interpreter.set_stack_size(self.stack_size);
+        interpreter.set_limits(self.limits.clone());
+        interpreter.set_interrupt_handle(self.interrupt.clone());
+
+        interpreter
+    }
+
+    fn vm(&mut self) -> Vm<'_> {
+        let mut vm = Vm::new(&mut self.manager, &mut self.modules);
+
+        vm.set_max_depth(self.max_depth);
+        vm.set_limits(self.limits.clone());
+        vm.set_interrupt_handle(self.interrupt.clone());
+
+        vm
+    }
+
+    pub fn add_search_path(&mut self, path: &Path) {
+        self.modules.add_search_path(path);
+    }
+
+    // Lexes, parses, type checks, resolves, optimizes and runs `source` in the global context.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Box<dyn Error>> {
+        let result = self.run(source);
+
+        // An interrupt stops only the run in progress, so it ends with the run.
+        self.interrupt.reset();
+
+        result
+    }
+
+    fn run(&mut self, source: &str) -> Result<Value, Box<dyn Error>> {
+        let tokens = Lexer::new(source).tokenize().map_err(|error| Box::new(error) as Box<dyn Error>)?;
+        let node = Parser::new(tokens).parse().map_err(|error| Box::new(error) as Box<dyn Error>)?;
+
+        let context_id = self.context_id;
+        let mut checker = Checker::new();
+
+        checker.set_globals(&self.manager, context_id);
+
+        if let Err(errors) = checker.check(&node) {
+            return Err(Box::new(TypeErrors::new(errors)));
+        }
+
+        let mut optimizer = Optimizer::new();
+        let mut resolver = Resolver::new();
+
+        optimizer.set_globals(shadowing(&self.manager, context_id));
+        resolver.set_globals(self.manager.names(context_id));
+
+        let node = resolver.resolve(node).map_err(|error| Box::new(error) as Box<dyn Error>)?;
+        let node = optimizer.optimize(node);
+
+        let result = match self.backend {
+            Backend::Interpreter => self.interpreter().visit(&node, context_id),
+            Backend::Vm => {
+                let prototype = Compiler::new().compile(&node);
+
+                self.vm().run(prototype, context_id)
+            }
+        };
+
+        result.map_err(|error| Box::new(error) as Box<dyn Error>)
+    }
+
+    // Like `eval`, but imports in the file resolve relative to its directory.
+    pub fn eval_file(&mut self, path: &Path) -> Result<Value, Box<dyn Error>> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| Box::new(RuntimeError::new(String::from("Cannot read '") + &path.display().to_string() + "': " + &error.to_string())) as Box<dyn Error>)?;
+
+        self.modules.begin(&path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+
+        let result = self.eval(&source);
+
+        self.modules.abort();
+
+        result
+    }
+
+    // Calls the global function `name` with `args`.
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> RuntimeResult {
+        let function = match self.manager.get(self.context_id, name) {
+            Some(function) => function.clone(),
+            None => return Err(RuntimeError::with_kind(ErrorKind::Name, String::from(name) + " is not defined"))
+        };
+
+        let result = match function {
+            Value::Closure(_) => self.vm().call_value(function, args),
+            function => self.interpreter().call_value(function, args)
+        };
+
+        self.interrupt.reset();
+
+        result
+    }
+
+    // Fails, leaving `name` unset, when `value` does not convert, like an integer beyond `i32`.
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) -> Result<(), RuntimeError> {
+        self.manager.set(self.context_id, name, value.into_value()?);
+
+        Ok(())
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.manager.get(self.context_id, name).cloned()
+    }
+
+    // Makes a Rust function callable from scripts as `name`.
+    pub fn register<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Value]) -> RuntimeResult + 'static
+    {
+        let prelude = self.manager.prelude();
+
+        self.manager.set(prelude, name, Value::NativeFunc(NativeFunc::new(name, arity, function)));
+    }
+
+    // Like `register`, with arguments and the return value converted through `FromValue` and `IntoValue`.
+    pub fn register_fn<Args, F>(&mut self, name: &str, function: F)
+    where
+        F: HostFunction<Args> + 'static
+    {
+        let prelude = self.manager.prelude();
+
+        self.manager.set(prelude, name, Value::NativeFunc(NativeFunc::typed(name, function)));
+    }
+
+    // The contexts scripts have defined, for inspecting the scope table.
+    pub fn manager(&self) -> &ContextManager {
+        &self.manager
+    }
+}
+
+// Runs `f` on a new thread with a stack of `size` bytes and waits for it, for scripts that recurse
+// deeper than the current thread's stack allows. Engines are not `Send`, so create the engine
+// inside `f`, which also tells it the size of its stack.
+pub fn run_with_stack_size<F, R>(size: usize, f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send
+{
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(size)
+            .spawn_scoped(scope, || {
+                STACK_SIZE.with(|stack_size| stack_size.set(Some(size)));
+
+                f()
+            })
+            .expect("Could not spawn the interpreter thread");
+
+        match handle.join() {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload)
+        }
+    })
+}
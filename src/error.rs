@@ -1,4 +1,8 @@
-pub trait Error {
+use crate::value::Value;
+
+use std::fmt::Debug;
+
+pub trait Error: Debug {
     fn msg(&self) -> &str;
 
     fn name(&self) -> &str;
@@ -59,16 +63,162 @@ impl Error for ParseError {
 }
 
 #[derive(Debug)]
-pub struct RuntimeError {
+pub struct TypeError {
     msg: String,
     name: String
 }
 
+impl TypeError {
+    pub fn new(msg: String) -> Self {
+        TypeError {
+            msg,
+            name: String::from("Type Error")
+        }
+    }
+}
+
+impl Error for TypeError {
+    fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// Every error the type checker found in a program, reported together.
+#[derive(Debug)]
+pub struct TypeErrors {
+    errors: Vec<TypeError>,
+    msg: String,
+    name: String
+}
+
+impl TypeErrors {
+    pub fn new(errors: Vec<TypeError>) -> Self {
+        let messages: Vec<&str> = errors.iter().map(|error| error.msg()).collect();
+
+        TypeErrors {
+            msg: messages.join("; "),
+            errors,
+            name: String::from("Type Error")
+        }
+    }
+
+    pub fn errors(&self) -> &[TypeError] {
+        &self.errors
+    }
+}
+
+impl Error for TypeErrors {
+    fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn to_string(&self) -> String {
+        let lines: Vec<String> = self.errors.iter().map(|error| error.to_string()).collect();
+
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Runtime,
+    Type,
+    Name,
+    Field,
+    Index,
+    Argument,
+    Match,
+    Import,
+    Recursion,
+    Fuel,
+    Timeout,
+    ContextLimit,
+    SizeLimit,
+    Interrupted,
+    Thrown
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &str {
+        match self {
+            ErrorKind::Runtime => "RuntimeError",
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Field => "FieldError",
+            ErrorKind::Index => "IndexError",
+            ErrorKind::Argument => "ArgumentError",
+            ErrorKind::Match => "MatchError",
+            ErrorKind::Import => "ImportError",
+            ErrorKind::Recursion => "RecursionError",
+            ErrorKind::Fuel => "FuelError",
+            ErrorKind::Timeout => "TimeoutError",
+            ErrorKind::ContextLimit => "ContextLimitError",
+            ErrorKind::SizeLimit => "SizeLimitError",
+            ErrorKind::Interrupted => "InterruptedError",
+            ErrorKind::Thrown => "Error"
+        }
+    }
+
+    // Running out of steps or time, or being interrupted, ends the script; catching it would let
+    // the script run on.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self, ErrorKind::Fuel | ErrorKind::Timeout | ErrorKind::Interrupted)
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    msg: String,
+    name: String,
+    kind: ErrorKind,
+    value: Option<Box<Value>>
+}
+
 impl RuntimeError {
     pub fn new(msg: String) -> Self {
+        RuntimeError::with_kind(ErrorKind::Runtime, msg)
+    }
+
+    pub fn with_kind(kind: ErrorKind, msg: String) -> Self {
         RuntimeError {
             msg,
-            name: String::from("Runtime Error")
+            name: String::from("Runtime Error"),
+            kind,
+            value: None
+        }
+    }
+
+    // An error raised by a script's `throw`, carrying the thrown value back to the matching `catch`.
+    pub fn thrown(value: Value, msg: String) -> Self {
+        RuntimeError {
+            msg,
+            name: String::from("Uncaught Error"),
+            kind: ErrorKind::Thrown,
+            value: Some(Box::new(value))
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_deref()
+    }
+
+    // The value a `catch` clause binds: the thrown value itself, or an error value describing an interpreter error.
+    pub fn into_value(self) -> Value {
+        match self.value {
+            Some(value) => *value,
+            None => Value::Error(String::from(self.kind.name()), self.msg)
         }
     }
 }
@@ -0,0 +1,472 @@
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::node::*;
+use crate::token::{TokenType, Comment};
+use crate::error::Error;
+use crate::resolver::Lookup;
+
+pub const WIDTH: usize = 80;
+
+const INDENT: &str = "    ";
+
+// Reprints `source` in the canonical style: four-space indentation, spaces around binary
+// operators, only the parentheses precedence requires, and lists or argument lists that do not
+// fit in `WIDTH` columns broken one item per line. Comments are kept at statement granularity:
+// a comment stays on its statement's line or on its own line before the next statement.
+pub fn format(source: &str) -> Result<String, Box<dyn Error>> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize().map_err(|error| Box::new(error) as Box<dyn Error>)?;
+
+    let mut parser = Parser::with_spans(tokens, lexer.spans().to_vec());
+    let node = parser.parse().map_err(|error| Box::new(error) as Box<dyn Error>)?;
+
+    let mut formatter = Formatter {
+        spans: NodeSpans::new(&node, parser.spans()),
+        comments: lexer.comments(),
+        next_comment: 0,
+        width: WIDTH
+    };
+
+    let output = formatter.statements(&node, 0, None);
+
+    if output.is_empty() {
+        Ok(output)
+    } else {
+        Ok(output + "\n")
+    }
+}
+
+struct Formatter<'a> {
+    spans: NodeSpans,
+    comments: &'a [Comment],
+    next_comment: usize,
+    width: usize
+}
+
+impl Formatter<'_> {
+    // Formats the statements of `node` one per line at `indent`. Comments that start before
+    // `end_line` (or all remaining ones, at the top level) are interleaved by source position.
+    fn statements(&mut self, node: &Node, indent: usize, end_line: Option<usize>) -> String {
+        let padding = INDENT.repeat(indent);
+        let mut lines: Vec<String> = vec![];
+        let mut last_line = None;
+
+        if let Node::Statements(nodes, returns_last) = node {
+            for (i, statement) in nodes.iter().enumerate() {
+                if let Node::EOF | Node::Empty = **statement {
+                    continue;
+                }
+
+                let span = self.spans.get(statement);
+
+                if let Some(span) = span {
+                    self.leading_comments(&mut lines, &mut last_line, &padding, span.start.line);
+
+                    if last_line.is_some_and(|line| span.start.line > line + 1) {
+                        lines.push(String::new());
+                    }
+                }
+
+                let mut text = self.expression(statement, indent, padding.len());
+
+                if i + 1 < nodes.len() || !returns_last {
+                    text.push(';');
+                }
+
+                if let Some(span) = span {
+                    if let Some(comment) = self.comments.get(self.next_comment) {
+                        if comment.span.start.line == span.end.line {
+                            text = text + "  " + &comment.text;
+
+                            self.next_comment += 1;
+                        }
+                    }
+
+                    last_line = Some(span.end.line);
+                }
+
+                lines.push(padding.clone() + &text);
+            }
+        }
+
+        let end_line = end_line.unwrap_or(usize::MAX);
+        self.leading_comments(&mut lines, &mut last_line, &padding, end_line);
+
+        lines.join("\n")
+    }
+
+    fn leading_comments(&mut self, lines: &mut Vec<String>, last_line: &mut Option<usize>, padding: &str, before_line: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start.line >= before_line {
+                break;
+            }
+
+            if last_line.is_some_and(|line| comment.span.start.line > line + 1) {
+                lines.push(String::new());
+            }
+
+            lines.push(String::from(padding) + &comment.text);
+            *last_line = Some(comment.span.start.line);
+
+            self.next_comment += 1;
+        }
+    }
+
+    fn block(&mut self, node: &Node, indent: usize) -> String {
+        let end_line = self.spans.get(node).map(|span| span.end.line);
+        let body = self.statements(node, indent + 1, end_line);
+
+        if body.is_empty() {
+            String::from("{}")
+        } else {
+            String::from("{\n") + &body + "\n" + &INDENT.repeat(indent) + "}"
+        }
+    }
+
+    // The body of `if`, `while`, `for` and match arms: a block if it was written as one.
+    fn body(&mut self, node: &Node, indent: usize, column: usize) -> String {
+        match node {
+            Node::Statements(..) | Node::Empty => self.block(node, indent),
+            _ => self.expression(node, indent, column)
+        }
+    }
+
+    // Formats `node` starting at `column` of a line indented `indent` levels.
+    fn expression(&mut self, node: &Node, indent: usize, column: usize) -> String {
+        match node {
+            Node::Int(number) => number.to_string(),
+            Node::Float(number) => {
+                let string = number.to_string();
+
+                if string.contains('.') || !string.chars().all(|c| c.is_ascii_digit() || c == '-') {
+                    string
+                } else {
+                    string + ".0"
+                }
+            },
+            Node::Str(string) => quote(string),
+            Node::VarAcc(name) | Node::ResolvedAcc(Lookup { name, .. }) => name.clone(),
+            Node::Frame(_, body) | Node::Define(_, body) => self.expression(body, indent, column),
+            Node::BinaryOp(left, op_token, right) => {
+                let precedence = precedence(node);
+
+                let (left_min, right_min) = if *op_token == TokenType::Pow {
+                    (precedence + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+
+                let left = self.operand(left, left_min, indent, column) + " " + &op_token.to_string() + " ";
+                let right = self.operand(right, right_min, indent, after(&left, column));
+
+                left + &right
+            },
+            Node::UnaryOp(operand, op_token) => {
+                let min = match op_token {
+                    TokenType::Not | TokenType::BitwiseNot => precedence(node),
+                    _ => POSTFIX
+                };
+
+                let op = op_token.to_string();
+                let operand = self.operand(operand, min, indent, column + op.len());
+
+                op + &operand
+            },
+            Node::Range(start, end, inclusive) => {
+                let operator = if *inclusive { "..=" } else { ".." };
+
+                let start = self.operand(start, RANGE + 1, indent, column) + operator;
+                let end = self.operand(end, RANGE + 1, indent, after(&start, column));
+
+                start + &end
+            },
+            Node::Index(object, index) => {
+                let object = self.target(object, indent, column) + "[";
+                let index = self.expression(index, indent, after(&object, column));
+
+                object + &index + "]"
+            },
+            Node::FieldAcc(object, field) => self.target(object, indent, column) + "." + field,
+            Node::OptionalFieldAcc(object, field) => self.target(object, indent, column) + "?." + field,
+            Node::FuncCall(function, args) => {
+                let function = self.target(function, indent, column);
+                let args = self.list("(", args, ")", indent, after(&function, column));
+
+                function + &args
+            },
+            Node::OptionalCall(function, args) => {
+                let function = self.target(function, indent, column) + "?.";
+                let args = self.list("(", args, ")", indent, after(&function, column));
+
+                function + &args
+            },
+            Node::ListDef(nodes) => self.list("[", nodes, "]", indent, column),
+            Node::FieldAssign(object, field, value) => {
+                let head = self.target(object, indent, column) + "." + field + " = ";
+                let value = self.expression(value, indent, after(&head, column));
+
+                head + &value
+            },
+            Node::VarDef(name, annotation, value) => {
+                let head = String::from("let ") + name + &annotate(annotation) + " = ";
+                let value = self.expression(value, indent, column + head.len());
+
+                head + &value
+            },
+            Node::FuncDef(name, params, return_type, body) => {
+                let params: Vec<String> = params.iter()
+                    .map(|(param, annotation)| param.clone() + &annotate(annotation))
+                    .collect();
+
+                String::from("function ") + name + "(" + &params.join(", ") + ")" + &annotate(return_type) + " " + &self.block(body, indent)
+            },
+            Node::StructDef(name, fields, methods) => {
+                let head = String::from("struct ") + name + " {";
+
+                if methods.is_empty() {
+                    return if fields.is_empty() { head + "}" } else { head + " " + &fields.join(", ") + " }" };
+                }
+
+                let padding = INDENT.repeat(indent + 1);
+                let mut sections = vec![];
+
+                if !fields.is_empty() {
+                    sections.push(padding.clone() + &fields.join(", "));
+                }
+
+                for method in methods {
+                    sections.push(padding.clone() + &self.expression(method, indent + 1, padding.len()));
+                }
+
+                head + "\n" + &sections.join("\n\n") + "\n" + &INDENT.repeat(indent) + "}"
+            },
+            Node::EnumDef(name, variants) => {
+                let variants: Vec<String> = variants.iter()
+                    .map(|(variant, fields)| if fields.is_empty() {
+                        variant.clone()
+                    } else {
+                        variant.clone() + "(" + &fields.join(", ") + ")"
+                    })
+                    .collect();
+
+                let head = String::from("enum ") + name + " ";
+
+                let column = column + head.len();
+
+                head + &self.wrap("{", variants, "}", indent, column, " ")
+            },
+            Node::Match(subject, arms) => {
+                let head = String::from("match (") + &self.expression(subject, indent, column + 7) + ") {";
+
+                if arms.is_empty() {
+                    return head + "}";
+                }
+
+                let padding = INDENT.repeat(indent + 1);
+                let mut lines = vec![];
+
+                for (pattern, body) in arms {
+                    let head = padding.clone() + &self.pattern(pattern, indent + 1, padding.len()) + " => ";
+                    let body = self.body(body, indent + 1, after(&head, 0));
+
+                    lines.push(head + &body);
+                }
+
+                head + "\n" + &lines.join(",\n") + "\n" + &INDENT.repeat(indent) + "}"
+            },
+            Node::Throw(value) => String::from("throw ") + &self.expression(value, indent, column + 6),
+            Node::Try(body, catch, finally) => {
+                let mut text = String::from("try ") + &self.block(body, indent);
+
+                if let Some((binding, catch_body)) = catch {
+                    text += " catch ";
+
+                    if let Some(binding) = binding {
+                        text = text + "(" + binding + ") ";
+                    }
+
+                    text += &self.block(catch_body, indent);
+                }
+
+                if let Some(finally_body) = finally {
+                    text = text + " finally " + &self.block(finally_body, indent);
+                }
+
+                text
+            },
+            Node::If(condition, body, else_body) => {
+                let head = String::from("if (") + &self.expression(condition, indent, column + 4) + ") ";
+                let mut text = head.clone() + &self.body(body, indent, after(&head, column));
+
+                if let Some(else_body) = else_body {
+                    text += " else ";
+
+                    let else_body = self.body(else_body, indent, after(&text, column));
+
+                    text += &else_body;
+                }
+
+                text
+            },
+            Node::WhileLoop(condition, body) => {
+                let head = String::from("while (") + &self.expression(condition, indent, column + 7) + ") ";
+                let body = self.body(body, indent, after(&head, column));
+
+                head + &body
+            },
+            Node::ForLoop(name, iterable, body) => {
+                let head = String::from("for (") + name + " in ";
+                let head = head.clone() + &self.expression(iterable, indent, column + head.len()) + ") ";
+                let body = self.body(body, indent, after(&head, column));
+
+                head + &body
+            },
+            Node::Import(path, names) => match names {
+                Some(names) => String::from("import ") + &self.wrap("{", names.clone(), "}", indent, column + 7, " ") + " from " + &quote(path),
+                None => String::from("import ") + &quote(path)
+            },
+            Node::Export(declaration) => String::from("export ") + &self.expression(declaration, indent, column + 7),
+            Node::Statements(..) | Node::Empty => self.block(node, indent),
+            Node::EOF => String::new()
+        }
+    }
+
+    // Formats `node` as the operand of an operator, parenthesized if it binds looser than `min`.
+    fn operand(&mut self, node: &Node, min: u8, indent: usize, column: usize) -> String {
+        if precedence(node) < min {
+            return String::from("(") + &self.expression(node, indent, column + 1) + ")";
+        }
+
+        self.expression(node, indent, column)
+    }
+
+    // The object of a call, index or field access. Number literals need parentheses here too,
+    // since `1.x` would lex as the float `1.`.
+    fn target(&mut self, node: &Node, indent: usize, column: usize) -> String {
+        match node {
+            Node::Int(_) | Node::Float(_) => String::from("(") + &self.expression(node, indent, column + 1) + ")",
+            _ => self.operand(node, POSTFIX, indent, column)
+        }
+    }
+
+    fn list(&mut self, open: &str, nodes: &[Box<Node>], close: &str, indent: usize, column: usize) -> String {
+        let comment = self.next_comment;
+        let mut text = String::from(open);
+
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                text += ", ";
+            }
+
+            let item = self.expression(node, indent, after(&text, column));
+
+            text += &item;
+        }
+
+        text += close;
+
+        if self.fits(&text, column) {
+            return text;
+        }
+
+        self.next_comment = comment;
+
+        let padding = INDENT.repeat(indent + 1);
+        let items: Vec<String> = nodes.iter().map(|node| self.expression(node, indent + 1, padding.len())).collect();
+
+        self.broken(open, &items, close, indent)
+    }
+
+    // Like `list`, for items that are already plain text. `space` pads the one-line form.
+    fn wrap(&mut self, open: &str, items: Vec<String>, close: &str, indent: usize, column: usize, space: &str) -> String {
+        if items.is_empty() {
+            return String::from(open) + close;
+        }
+
+        let text = String::from(open) + space + &items.join(", ") + space + close;
+
+        if self.fits(&text, column) {
+            text
+        } else {
+            self.broken(open, &items, close, indent)
+        }
+    }
+
+    fn broken(&self, open: &str, items: &[String], close: &str, indent: usize) -> String {
+        let padding = INDENT.repeat(indent + 1);
+        let lines: Vec<String> = items.iter().map(|item| padding.clone() + item).collect();
+
+        String::from(open) + "\n" + &lines.join(",\n") + "\n" + &INDENT.repeat(indent) + close
+    }
+
+    // Whether `text`, starting at `column`, stays on one line within the width.
+    fn fits(&self, text: &str, column: usize) -> bool {
+        !text.contains('\n') && column + text.len() <= self.width
+    }
+
+    fn pattern(&mut self, pattern: &Pattern, indent: usize, column: usize) -> String {
+        match pattern {
+            Pattern::Wildcard => String::from("_"),
+            Pattern::Binding(name) => name.clone(),
+            Pattern::Literal(node) => self.expression(node, indent, column),
+            Pattern::Variant(enum_name, variant, patterns) => {
+                let text = enum_name.clone() + "." + variant;
+
+                if patterns.is_empty() {
+                    return text;
+                }
+
+                let patterns: Vec<String> = patterns.iter().map(|pattern| self.pattern(pattern, indent, column)).collect();
+
+                text + "(" + &patterns.join(", ") + ")"
+            }
+        }
+    }
+}
+
+const RANGE: u8 = 4;
+const POSTFIX: u8 = 11;
+
+// Binding strength of each node, mirroring the parser's precedence chain from `expression`
+// (0, keyword forms and assignment) down to atoms (12).
+fn precedence(node: &Node) -> u8 {
+    match node {
+        Node::BinaryOp(_, op_token, _) => match op_token {
+            TokenType::NullCoalesce => 1,
+            TokenType::BitwiseAnd | TokenType::BitwiseOr | TokenType::BitwiseXOr | TokenType::And | TokenType::Or => 2,
+            TokenType::BitwiseRightShift | TokenType::BitwiseLeftShift => 5,
+            TokenType::Plus | TokenType::Minus => 7,
+            TokenType::Mul | TokenType::Div => 8,
+            TokenType::Pow => 9,
+            _ => 3
+        },
+        Node::Range(..) => RANGE,
+        Node::UnaryOp(_, TokenType::Not) | Node::UnaryOp(_, TokenType::BitwiseNot) => 6,
+        Node::UnaryOp(..) => 10,
+        Node::Int(number) if *number < 0 => 10,
+        Node::Float(number) if *number < 0.0 => 10,
+        Node::FuncCall(..) | Node::OptionalCall(..) | Node::FieldAcc(..) | Node::OptionalFieldAcc(..) | Node::Index(..) => POSTFIX,
+        Node::Int(_) | Node::Float(_) | Node::Str(_) | Node::VarAcc(_) | Node::ResolvedAcc(_) | Node::ListDef(_) | Node::EOF => 12,
+        _ => 0
+    }
+}
+
+// The column just past `text` when it starts at `column`. Broken lines carry their own padding.
+fn after(text: &str, column: usize) -> usize {
+    match text.rfind('\n') {
+        Some(newline) => text.len() - newline - 1,
+        None => column + text.len()
+    }
+}
+
+fn annotate(annotation: &Option<Type>) -> String {
+    match annotation {
+        Some(annotation) => String::from(": ") + &annotation.to_string(),
+        None => String::new()
+    }
+}
+
+// The lexer has no escapes for quotes or backslashes, so a newline is the only character to escape.
+fn quote(string: &str) -> String {
+    String::from("\"") + &string.replace('\n', "\\n") + "\""
+}
@@ -1,23 +1,370 @@
 use crate::node::*;
 use crate::value::*;
-use crate::error::RuntimeError;
+use crate::error::{Error, ErrorKind, RuntimeError, TypeErrors};
 use crate::token::TokenType;
-use crate::ContextManager;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::checker::Checker;
+use crate::resolver::Resolver;
+use crate::optimizer::{shadowing, Optimizer};
+use crate::module::ModuleLoader;
+use crate::context::{ContextId, ContextManager};
+use crate::limits::{InterruptHandle, Limits};
+
+use std::fs;
+use std::hint;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
 
 pub type RuntimeResult = Result<Value, RuntimeError>;
 
+// How many script function calls may be active at once. In a debug build a call to a function
+// with a simple body takes about 20 KiB of the Rust stack, so this many fit in the default 8 MiB
+// main thread stack; deeper recursion needs a larger stack as well, see
+// `engine::run_with_stack_size`.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+// The size of the stack the interpreter runs on unless told otherwise, that of the main thread.
+pub const DEFAULT_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// The part of the stack nesting may not take, left for the frames between two `visit`s, natives
+// and the host's own frames. Without the check a body nesting loops, `try`s and operators could
+// overflow the stack well before the depth limit is reached.
+const STACK_RESERVE: usize = 1024 * 1024;
+
+// Reading the clock on every node would dominate simple scripts, so the deadline is checked
+// once per this many steps.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+// The address of a local, which tells how deep the stack is.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+
+    hint::black_box(&marker) as *const u8 as usize
+}
+
+// The result of evaluating a function's body, which ends either in a value or in a call in tail
+// position that the function's caller makes in its place, with the receiver of a method call.
+enum Tail {
+    Value(Value),
+    Call(Value, Vec<Value>, Option<Value>)
+}
+
+// Besides the contexts it is evaluating in, the interpreter keeps the values it is holding
+// between evaluations in `temps`, so that the collector can treat both as live.
 pub struct Interpreter<'a> {
-    manager: &'a mut ContextManager
+    manager: &'a mut ContextManager,
+    modules: &'a mut ModuleLoader,
+    scopes: Vec<ContextId>,
+    temps: Vec<Value>,
+    depth: usize,
+    max_depth: usize,
+    nesting: usize,
+    stack_size: usize,
+    stack_base: usize,
+    limits: Limits,
+    steps: u64,
+    interrupt: InterruptHandle
 }
 
 impl<'a> Interpreter<'a> {
-    pub fn new(manager: &'a mut ContextManager) -> Interpreter {
+    pub fn new(manager: &'a mut ContextManager, modules: &'a mut ModuleLoader) -> Interpreter<'a> {
         Interpreter {
-            manager
+            manager,
+            modules,
+            scopes: vec![],
+            temps: vec![],
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            nesting: 0,
+            stack_size: DEFAULT_STACK_SIZE,
+            stack_base: 0,
+            limits: Limits::new(),
+            steps: 0,
+            interrupt: InterruptHandle::new()
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // Sets the size of the stack the interpreter runs on, which bounds how deeply nodes may nest.
+    pub fn set_stack_size(&mut self, stack_size: usize) {
+        self.stack_size = stack_size;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // A handle another thread can use to stop this interpreter.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    pub fn set_interrupt_handle(&mut self, interrupt: InterruptHandle) {
+        self.interrupt = interrupt;
+    }
+
+    pub fn visit(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        self.step()?;
+
+        // The stack grows down, from wherever the outermost `visit` started.
+        let here = stack_address();
+
+        if self.nesting == 0 {
+            self.stack_base = here;
+        }
+
+        if self.stack_base.saturating_sub(here) > self.stack_size.saturating_sub(STACK_RESERVE) {
+            return Err(RuntimeError::with_kind(ErrorKind::Recursion, String::from("Maximum nesting depth exceeded")));
+        }
+
+        let (scopes, temps) = (self.scopes.len(), self.temps.len());
+
+        self.scopes.push(context_id);
+
+        if self.manager.should_collect() {
+            self.manager.collect(&self.scopes, &self.temps);
+        }
+
+        self.nesting += 1;
+
+        let result = self.dispatch(node, context_id);
+
+        self.nesting -= 1;
+        self.scopes.truncate(scopes);
+        self.temps.truncate(temps);
+
+        let value = result?;
+
+        self.check_size(&value)?;
+
+        Ok(value)
+    }
+
+    // Evaluates `node` like `visit`, except that a call in tail position is returned instead of
+    // made. Tail position is the last statement of a block and the branches of an `if` in it.
+    fn visit_tail(&mut self, node: &Node, context_id: ContextId) -> Result<Tail, RuntimeError> {
+        let (scopes, temps) = (self.scopes.len(), self.temps.len());
+
+        let result = self.descend(node, context_id);
+
+        self.scopes.truncate(scopes);
+        self.temps.truncate(temps);
+
+        result
+    }
+
+    // Walks down to the tail position in a loop rather than by recursion, so that a call takes the
+    // same few frames on the Rust stack however deeply its tail is nested.
+    fn descend(&mut self, node: &Node, context_id: ContextId) -> Result<Tail, RuntimeError> {
+        let (mut node, mut context_id) = (node, context_id);
+
+        loop {
+            while let Node::Frame(_, body) = node {
+                node = body;
+            }
+
+            self.step()?;
+            self.scopes.push(context_id);
+
+            if self.manager.should_collect() {
+                self.manager.collect(&self.scopes, &self.temps);
+            }
+
+            match node {
+                Node::Statements(nodes, true) => match nodes.split_last() {
+                    Some((last, nodes)) => {
+                        for node in nodes {
+                            self.visit(node, context_id)?;
+                        }
+
+                        node = last;
+                    },
+                    None => return Ok(Tail::Value(Value::Null))
+                },
+                Node::If(condition, body, else_body) => {
+                    let condition_value = self.visit(condition, context_id)?;
+
+                    match if condition_value.is_true() { Some(body) } else { else_body.as_ref() } {
+                        Some(branch) => {
+                            context_id = self.create_scope(context_id, branch)?;
+                            node = branch;
+                        },
+                        None => return Ok(Tail::Value(Value::Null))
+                    }
+                },
+                Node::FuncCall(func, args) if matches!(func.as_ref(), Node::FieldAcc(..) | Node::OptionalFieldAcc(..)) => {
+                    return match self.method_call(func, args, context_id)? {
+                        Some(Tail::Value(value)) => {
+                            self.check_size(&value)?;
+
+                            Ok(Tail::Value(value))
+                        },
+                        Some(call) => Ok(call),
+                        None => Ok(Tail::Value(Value::Null))
+                    };
+                },
+                Node::FuncCall(func, args) => {
+                    let function = match self.visit_object(func, context_id)? {
+                        Some(function) => function,
+                        None => return Ok(Tail::Value(Value::Null))
+                    };
+
+                    self.temps.push(function.clone());
+
+                    let values = self.visit_all(args, context_id)?;
+
+                    return Ok(Tail::Call(function, values, None));
+                },
+                _ => {
+                    let value = self.dispatch(node, context_id)?;
+
+                    self.check_size(&value)?;
+
+                    return Ok(Tail::Value(value));
+                }
+            }
+        }
+    }
+
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+
+        if let Some(fuel) = self.limits.fuel {
+            if self.steps > fuel {
+                return Err(RuntimeError::with_kind(ErrorKind::Fuel, String::from("Step budget of ") + &fuel.to_string() + " exhausted"));
+            }
+        }
+
+        if let Some(deadline) = self.limits.deadline {
+            if self.steps.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline {
+                return Err(RuntimeError::with_kind(ErrorKind::Timeout, String::from("Deadline exceeded")));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_interrupt(&self) -> Result<(), RuntimeError> {
+        if self.interrupt.is_interrupted() {
+            return Err(RuntimeError::with_kind(ErrorKind::Interrupted, String::from("Interrupted")));
+        }
+
+        Ok(())
+    }
+
+    // A string or list nothing else holds yet is a new one, which counts against the size limit.
+    fn check_size(&mut self, value: &Value) -> Result<(), RuntimeError> {
+        if self.limits.max_heap_size.is_none() {
+            return Ok(());
+        }
+
+        let size = match value {
+            Value::Str(string) if Rc::strong_count(string) == 1 => string.len(),
+            Value::List(items) if Rc::strong_count(items) == 1 => items.borrow().len(),
+            _ => return Ok(())
+        };
+
+        self.charge(size, value, 0)
+    }
+
+    // Counts `size` more of strings and lists, which `value` holds but for the `pending` about to
+    // be added to it. Once the count passes the size limit, the collector runs and only the
+    // strings and lists still reachable count.
+    fn charge(&mut self, size: usize, value: &Value, pending: usize) -> Result<(), RuntimeError> {
+        let max_size = match self.limits.max_heap_size {
+            Some(max_size) if self.manager.charge(size, max_size) => max_size,
+            _ => return Ok(())
+        };
+
+        self.temps.push(value.clone());
+        self.manager.collect(&self.scopes, &self.temps);
+
+        let size = self.manager.measure(&self.temps, pending);
+
+        self.temps.pop();
+
+        if size > max_size {
+            return Err(RuntimeError::with_kind(ErrorKind::SizeLimit, String::from("Strings and lists of total size ") + &size.to_string() + " exceed the size limit of " + &max_size.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // Callers must have rooted every value they hold, since reaching the context limit first
+    // collects the contexts that are no longer reachable.
+    fn create_context(&mut self, parent: ContextId) -> Result<ContextId, RuntimeError> {
+        self.create_scope(parent, &Node::Empty)
+    }
+
+    // Creates the context `body` runs in, with a slot for each of its variables if the resolver
+    // laid them out.
+    fn create_scope(&mut self, parent: ContextId, body: &Node) -> Result<ContextId, RuntimeError> {
+        if let Some(max_contexts) = self.limits.max_contexts {
+            if self.manager.context_count() >= max_contexts {
+                self.scopes.push(parent);
+                self.manager.collect(&self.scopes, &self.temps);
+                self.scopes.pop();
+            }
+
+            if self.manager.context_count() >= max_contexts {
+                return Err(RuntimeError::with_kind(ErrorKind::ContextLimit, String::from("Context limit of ") + &max_contexts.to_string() + " reached"));
+            }
+        }
+
+        Ok(match body {
+            Node::Frame(layout, _) => self.manager.create_scope(Some(parent), layout.clone()),
+            _ => self.manager.create_context(Some(parent))
+        })
+    }
+
+    // Stores the variable a definition makes in the slot the resolver gave it, or by name in
+    // scopes without slots.
+    fn define(&mut self, context_id: ContextId, slot: Option<u32>, name: &str, value: Value) {
+        match slot {
+            Some(slot) => self.manager.set_slot(context_id, slot as usize, value),
+            None => { self.manager.set(context_id, name, value); }
+        }
+    }
+
+    // Binds the `index`th name the scope of `body` is opened with, such as a parameter.
+    fn bind(&mut self, context_id: ContextId, body: &Node, index: usize, name: &str, value: Value) {
+        match body {
+            Node::Frame(layout, _) => self.manager.set_slot(context_id, layout.binding(index), value),
+            _ => { self.manager.set(context_id, name, value); }
+        }
+    }
+
+    // Evaluates `node` while keeping `value` alive, and returns both.
+    fn visit_holding(&mut self, value: Value, node: &Node, context_id: ContextId) -> Result<(Value, Value), RuntimeError> {
+        self.temps.push(value);
+
+        let result = self.visit(node, context_id);
+
+        let value = self.temps.pop().unwrap();
+
+        Ok((value, result?))
+    }
+
+    // Evaluates `nodes` in order, keeping the values already computed alive.
+    fn visit_all(&mut self, nodes: &[Box<Node>], context_id: ContextId) -> Result<Vec<Value>, RuntimeError> {
+        let base = self.temps.len();
+
+        for node in nodes {
+            let value = self.visit(node, context_id)?;
+
+            self.temps.push(value);
         }
+
+        Ok(self.temps.split_off(base))
     }
 
-    pub fn visit(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn dispatch(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::Statements(..) => self.visit_statements_node(node, context_id),
             Node::Int(..) => self.visit_int_node(node, context_id),
@@ -25,18 +372,39 @@ impl<'a> Interpreter<'a> {
             Node::Str(..) => self.visit_string_node(node, context_id),
             Node::UnaryOp(..) => self.visit_unary_op_node(node, context_id),
             Node::BinaryOp(..) => self.visit_binary_op_node(node, context_id),
-            Node::VarDef(..) => self.visit_var_def_node(node, context_id),
+            Node::VarDef(..) => self.visit_var_def_node(node, context_id, None),
             Node::VarAcc(..) => self.visit_var_acc_node(node, context_id),
             Node::ListDef(..) => self.visit_list_def_node(node, context_id),
-            Node::FuncDef(..) => self.visit_func_def_node(node, context_id),
-            Node::FuncCall(..) => self.visit_func_call_node(node, context_id),
+            Node::Range(..) => self.visit_range_node(node, context_id),
+            Node::Index(..) => self.visit_chain(node, context_id),
+            Node::FuncDef(..) => self.visit_func_def_node(node, context_id, None),
+            Node::FuncCall(..) => self.visit_chain(node, context_id),
+            Node::StructDef(..) => self.visit_struct_def_node(node, context_id, None),
+            Node::OptionalCall(..) | Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => self.visit_chain(node, context_id),
+            Node::FieldAssign(..) => self.visit_field_assign_node(node, context_id),
+            Node::EnumDef(..) => self.visit_enum_def_node(node, context_id, None),
+            Node::Match(..) => self.visit_match_node(node, context_id),
+            Node::Throw(..) => self.visit_throw_node(node, context_id),
+            Node::Try(..) => self.visit_try_node(node, context_id),
             Node::If(..) => self.visit_if_node(node, context_id),
             Node::WhileLoop(..) => self.visit_while_loop_node(node, context_id),
+            Node::ForLoop(..) => self.visit_for_loop_node(node, context_id),
+            Node::Import(..) => self.visit_import_node(node, context_id),
+            Node::Export(..) => self.visit_export_node(node, context_id),
+            Node::ResolvedAcc(..) => self.visit_resolved_acc_node(node, context_id),
+            Node::Frame(_, body) => self.dispatch(body, context_id),
+            Node::Define(slot, definition) => match definition.as_ref() {
+                Node::VarDef(..) => self.visit_var_def_node(definition, context_id, Some(*slot)),
+                Node::FuncDef(..) => self.visit_func_def_node(definition, context_id, Some(*slot)),
+                Node::StructDef(..) => self.visit_struct_def_node(definition, context_id, Some(*slot)),
+                Node::EnumDef(..) => self.visit_enum_def_node(definition, context_id, Some(*slot)),
+                _ => Err(RuntimeError::new(String::from("Definition expected")))
+            },
             _ => Ok(Value::Null)
         }
     }
 
-    fn visit_statements_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_statements_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::Statements(nodes, should_return_last) => {
                 let mut value = Value::Null;
@@ -55,37 +423,37 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn visit_int_node(&self, node: &Node, _context_id: i32) -> RuntimeResult {
+    fn visit_int_node(&self, node: &Node, _context_id: ContextId) -> RuntimeResult {
         match node {
             Node::Int(n) => Ok(Value::Int(*n)),
             _ => Err(RuntimeError::new(String::from("Integer expected")))
         }
     }
 
-    fn visit_float_node(&self, node: &Node, _context_id: i32) -> RuntimeResult {
+    fn visit_float_node(&self, node: &Node, _context_id: ContextId) -> RuntimeResult {
         match node {
             Node::Float(n) => Ok(Value::Float(*n)),
             _ => Err(RuntimeError::new(String::from("Float expected")))
         }
     }
 
-    fn visit_string_node(&self, node: &Node, _context_id: i32) -> RuntimeResult {
+    fn visit_string_node(&self, node: &Node, _context_id: ContextId) -> RuntimeResult {
         match node {
-            Node::Str(string) => Ok(Value::Str(string.as_str().to_string())),
+            Node::Str(string) => Ok(Value::Str(string.as_str().into())),
             _ => Err(RuntimeError::new(String::from("String expected")))
         }
     }
 
-    fn visit_unary_op_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_unary_op_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::UnaryOp(node, token) => {
                 let value = self.visit(node, context_id)?;
                 
                 let result = match token {
-                    TokenType::Plus => value.multiply(Value::Int(1), &self.manager),
-                    TokenType::Minus => value.multiply(Value::Int(-1), &self.manager),
-                    TokenType::BitwiseNot => value.bitwise_not(&self.manager),
-                    TokenType::Not => value.logical_not(&self.manager),
+                    TokenType::Plus => value.multiply(Value::Int(1)),
+                    TokenType::Minus => value.negate(),
+                    TokenType::BitwiseNot => value.bitwise_not(),
+                    TokenType::Not => value.logical_not(),
                     _ => RuntimeResult::Ok(value)
                 };
 
@@ -98,37 +466,37 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn visit_binary_op_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_binary_op_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
+            Node::BinaryOp(left_node, TokenType::NullCoalesce, right_node) => {
+                match self.visit(left_node, context_id)? {
+                    Value::Null => self.visit(right_node, context_id),
+                    left => Ok(left)
+                }
+            },
             Node::BinaryOp(left_node, token, right_node) => {
                 let left = self.visit(left_node, context_id)?;
-                let right = self.visit(right_node, context_id)?;
-                let result = match token {
-                    &TokenType::Plus => left.add(right, &self.manager),
-                    &TokenType::Minus => left.subtract(right, &self.manager),
-                    &TokenType::Mul => left.multiply(right, &self.manager),
-                    &TokenType::Div => left.divide(right, &self.manager),
-                    &TokenType::Pow => left.raise(right, &self.manager),
-                    &TokenType::EE => left.equals(right, &self.manager),
-                    &TokenType::NE => {
-                        let not_equals = left.equals(right, &self.manager)?;
-
-                        match not_equals {
-                            Value::Boolean(b) => Ok(Value::Boolean(!b)),
-                            right => Err(RuntimeError::new(String::from("Comparing '") + &left.to_string(&self.manager) + "' with '" + &right.to_string(&self.manager) + "' gave a non-boolean value."))
-                        }
-                    },
-                    &TokenType::GT => left.is_greater_than(right, &self.manager),
-                    &TokenType::GTE => left.is_greater_than_or_equal_to(right, &self.manager),
-                    &TokenType::LT => left.is_less_than(right, &self.manager),
-                    &TokenType::LTE => left.is_less_than_or_equal_to(right, &self.manager),
-                    &TokenType::BitwiseAnd => left.bitwise_and(right, &self.manager),
-                    &TokenType::BitwiseOr => left.bitwise_or(right, &self.manager),
-                    &TokenType::BitwiseXOr => left.bitwise_xor(right, &self.manager),
-                    &TokenType::BitwiseLeftShift => left.left_shift(right, &self.manager),
-                    &TokenType::BitwiseRightShift => left.right_shift(right, &self.manager),
-                    &TokenType::And => left.logical_and(right, &self.manager),
-                    &TokenType::Or => left.logical_or(right, &self.manager),
+                let (left, right) = self.visit_holding(left, right_node, context_id)?;
+                let result = match *token {
+                    TokenType::Plus => left.add(right),
+                    TokenType::Minus => left.subtract(right),
+                    TokenType::Mul => left.multiply(right),
+                    TokenType::Div => left.divide(right),
+                    TokenType::Pow => left.raise(right),
+                    TokenType::EE => left.equals(right),
+                    TokenType::NE => left.not_equals(right),
+                    TokenType::GT => left.is_greater_than(right),
+                    TokenType::GTE => left.is_greater_than_or_equal_to(right),
+                    TokenType::LT => left.is_less_than(right),
+                    TokenType::LTE => left.is_less_than_or_equal_to(right),
+                    TokenType::BitwiseAnd => left.bitwise_and(right),
+                    TokenType::BitwiseOr => left.bitwise_or(right),
+                    TokenType::BitwiseXOr => left.bitwise_xor(right),
+                    TokenType::BitwiseLeftShift => left.left_shift(right),
+                    TokenType::BitwiseRightShift => left.right_shift(right),
+                    TokenType::And => left.logical_and(right),
+                    TokenType::Or => left.logical_or(right),
+                    TokenType::Keyword(ref keyword) if keyword == "in" => right.contains(left),
                     _ => Err(RuntimeError::new(String::from("Illegal token '") + &token.to_string() + "'"))
                 };
 
@@ -141,60 +509,94 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn visit_var_def_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_var_def_node(&mut self, node: &Node, context_id: ContextId, slot: Option<u32>) -> RuntimeResult {
         match node {
-            Node::VarDef(name, value_node) => {
+            Node::VarDef(name, _, value_node) => {
                 let value = self.visit(value_node, context_id)?;
 
-                self.manager.set(context_id, name, value);
+                self.define(context_id, slot, name, value.clone());
 
-                Ok(Value::Pointer(context_id, name.to_string()))
+                Ok(value)
             }
             _ => Err(RuntimeError::new(String::from("Var definition expected")))
         }
     }
 
-    fn visit_var_acc_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_var_acc_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::VarAcc(name) => {
                 match self.manager.get(context_id, name) {
-                    Some(value) => {
-                        match value {
-                            Value::List(..) => Ok(Value::Pointer(context_id, name.to_string())),
-                            Value::Func(..) => Ok(Value::Pointer(context_id, name.to_string())),
-                            Value::Str(..) => Ok(Value::Pointer(context_id, name.to_string())),
-                            _ => Ok(value.clone())
-                        }
-                    },
-                    None => Err(RuntimeError::new(String::from(name) + " is not defined"))
+                    Some(value) => Ok(value.clone()),
+                    None => Err(RuntimeError::with_kind(ErrorKind::Name, String::from(name) + " is not defined"))
                 }
             }
             _ => Err(RuntimeError::new(String::from("Var access expected")))
         }
     }
 
-    fn visit_list_def_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_resolved_acc_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
-            Node::ListDef(nodes) => {
-                let mut values = vec![];
-
-                for node in nodes {
-                    values.push(self.visit(node, context_id)?);
+            Node::ResolvedAcc(lookup) => {
+                match self.manager.lookup(context_id, lookup) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(RuntimeError::with_kind(ErrorKind::Name, lookup.name.clone() + " is not defined"))
                 }
+            }
+            _ => Err(RuntimeError::new(String::from("Resolved access expected")))
+        }
+    }
 
-                Ok(Value::List(values))
+    fn visit_list_def_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::ListDef(nodes) => {
+                let values = self.visit_all(nodes, context_id)?;
+
+                Ok(Value::list(values))
             }
             _ => Err(RuntimeError::new(String::from("List def expected")))
         }
     }
 
-    fn visit_func_def_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_range_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Range(start_node, end_node, inclusive) => {
+                let start = self.visit(start_node, context_id)?;
+                let (start, end) = self.visit_holding(start, end_node, context_id)?;
+
+                match (start, end) {
+                    (Value::Int(start), Value::Int(end)) => Ok(Value::Range(start, end, *inclusive)),
+                    (start, end) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Range bounds must be integers, got '") + &start.to_string() + "' and '" + &end.to_string() + "'"))
+                }
+            },
+            _ => Err(RuntimeError::new(String::from("Range expected")))
+        }
+    }
+
+    fn visit_index_node(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
         match node {
-            Node::FuncDef(name, args, body) => {
+            Node::Index(object, index) => {
+                let value = match self.visit_object(object, context_id)? {
+                    Some(value) => value,
+                    None => return Ok(None)
+                };
 
-                let value = Value::Func(name.clone(), args.clone(), body.clone(), self.manager.create_context(Some(context_id)));
+                let (value, index) = self.visit_holding(value, index, context_id)?;
 
-                self.manager.set(context_id, name, value.clone());
+                value.index(index).map(Some)
+            },
+            _ => Err(RuntimeError::new(String::from("Index expected")))
+        }
+    }
+
+    fn visit_func_def_node(&mut self, node: &Node, context_id: ContextId, slot: Option<u32>) -> RuntimeResult {
+        match node {
+            Node::FuncDef(name, args, _, body) => {
+                let params = args.iter().map(|(param, _)| param.clone()).collect();
+
+                let value = Value::function(name, params, body.clone(), self.create_context(context_id)?);
+
+                self.manager.track(&value);
+                self.define(context_id, slot, name, value.clone());
 
                 Ok(value)
             }
@@ -202,69 +604,414 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn visit_func_call_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    // A field access, call or index, which ends a chain of them. A `?.` that finds null skips the
+    // rest of the chain, so `a?.b.c` is null rather than an error when `a` is.
+    fn visit_chain(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        Ok(self.visit_link(node, context_id)?.unwrap_or(Value::Null))
+    }
+
+    // Evaluates one link of a chain, or gives None if an earlier link skipped the rest.
+    fn visit_link(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
+        match node {
+            Node::Index(..) => self.visit_index_node(node, context_id),
+            Node::FuncCall(..) => self.visit_func_call_node(node, context_id),
+            Node::OptionalCall(..) => self.visit_optional_call_node(node, context_id),
+            _ => self.visit_field_acc_node(node, context_id)
+        }
+    }
+
+    // Evaluates what a link applies to, continuing the chain if it is a link itself.
+    fn visit_object(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
+        match node {
+            Node::Index(..) | Node::FuncCall(..) | Node::OptionalCall(..) | Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => self.visit_link(node, context_id),
+            _ => self.visit(node, context_id).map(Some)
+        }
+    }
+
+    fn visit_func_call_node(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
         match node {
             Node::FuncCall(func, args) => {
-                let mut function = self.visit(func, context_id)?;
+                match func.as_ref() {
+                    Node::FieldAcc(..) | Node::OptionalFieldAcc(..) => match self.method_call(func, args, context_id)? {
+                        Some(Tail::Call(function, values, receiver)) => self.invoke(function, values, receiver).map(Some),
+                        Some(Tail::Value(value)) => Ok(Some(value)),
+                        None => Ok(None)
+                    },
+                    _ => {
+                        let function = match self.visit_object(func, context_id)? {
+                            Some(function) => function,
+                            None => return Ok(None)
+                        };
 
-                loop {
-                    match function {
-                        Value::Func(_, params, body, func_context) => {
-                            let arg_context = self.manager.create_context(Some(func_context));
-                            // arg_context.set_parent(context);
-
-                            let call_context = self.manager.create_context(Some(func_context));
-                            // call_context.set_parent(func_context.get_mut());
-
-                            if params.len() > 0 {
-                                for i in 0..params.len() {
-                                    match args.get(i) {
-                                        Some(arg) => {
-                                            let arg_value = self.visit(arg, arg_context)?;
-                                            self.manager.set(call_context, &params[i], arg_value);
-                                        },
-                                        None => {
-                                            self.manager.set(call_context, &params[i], Value::Null);
-                                        }
-                                    }
-                                }
-                            }
+                        self.call_function(function, args, context_id, None).map(Some)
+                    }
+                }
+            }
+            _ => Err(RuntimeError::new(String::from("Func call expected")))
+        }
+    }
 
-                            break self.visit(&body, call_context);
-                        },
-                        Value::Pointer(_, _) => {
-                            match Value::deref(&function, self.manager) {
-                                Some(value) => {
-                                    function = value.clone()
-                                },
-                                None => {
-                                    break Err(RuntimeError::new(function.to_string(&self.manager) + " is not a function"))
-                                }
-                            };
-                        },
-                        _ => {
-                            break Err(RuntimeError::new(function.to_string(&self.manager) + " is not a function"));
+    fn visit_optional_call_node(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
+        match node {
+            Node::OptionalCall(func, args) => {
+                match self.visit_object(func, context_id)? {
+                    None | Some(Value::Null) => Ok(None),
+                    Some(function) => self.call_function(function, args, context_id, None).map(Some)
+                }
+            },
+            _ => Err(RuntimeError::new(String::from("Optional call expected")))
+        }
+    }
+
+    // Evaluates the receiver and arguments of `object.name(args)` and finds what it calls, leaving
+    // the call to the caller so that one in tail position can take the place of the current call.
+    // Methods of lists are called at once. None if a `?.` skipped the call.
+    fn method_call(&mut self, func: &Node, args: &[Box<Node>], context_id: ContextId) -> Result<Option<Tail>, RuntimeError> {
+        let (object, name) = match func {
+            Node::FieldAcc(object, name) | Node::OptionalFieldAcc(object, name) => (object, name),
+            _ => return Err(RuntimeError::new(String::from("Method call expected")))
+        };
+
+        let receiver = match self.visit_object(object, context_id)? {
+            Some(receiver) => receiver,
+            None => return Ok(None)
+        };
+
+        if let (Node::OptionalFieldAcc(..), Value::Null) = (func, &receiver) {
+            return Ok(None);
+        }
+
+        let (function, receiver) = match &receiver {
+            Value::Instance(object) => match receiver.get_field(name) {
+                Some(field) => (field, None),
+                None => {
+                    let methods_context = object.borrow().methods;
+
+                    match self.manager.get_local(methods_context, name) {
+                        Some(method) => (method.clone(), Some(receiver.clone())),
+                        None => return Err(RuntimeError::with_kind(ErrorKind::Field, receiver.to_string() + " has no method '" + name + "'"))
+                    }
+                }
+            },
+            Value::List(_) => {
+                self.temps.push(receiver.clone());
+
+                let values = self.visit_all(args, context_id)?;
+
+                // Pushing is the one way a list grows in place, so refuse it up front.
+                if name == "push" {
+                    self.charge(1, &receiver, 1)?;
+
+                    for value in &values {
+                        self.manager.track_store(&receiver, value);
+                    }
+                }
+
+                return receiver.call_method(name, values).map(|value| Some(Tail::Value(value)));
+            },
+            _ => (get_field(&receiver, name)?, None)
+        };
+
+        self.temps.push(function.clone());
+        self.temps.extend(receiver.clone());
+
+        let values = self.visit_all(args, context_id)?;
+
+        Ok(Some(Tail::Call(function, values, receiver)))
+    }
+
+    // Arguments are evaluated in the caller's context, before the callee's scope exists.
+    fn call_function(&mut self, function: Value, args: &[Box<Node>], context_id: ContextId, receiver: Option<Value>) -> RuntimeResult {
+        self.temps.push(function.clone());
+        self.temps.extend(receiver.clone());
+
+        let values = self.visit_all(args, context_id)?;
+
+        self.invoke(function, values, receiver)
+    }
+
+    // Calls `function` with arguments that are already values, as the host does through `Engine::call`.
+    pub fn call_value(&mut self, function: Value, args: Vec<Value>) -> RuntimeResult {
+        self.invoke(function, args, None)
+    }
+
+    fn invoke(&mut self, function: Value, args: Vec<Value>, receiver: Option<Value>) -> RuntimeResult {
+        match function {
+            Value::Func(function) => {
+                if self.depth >= self.max_depth {
+                    return Err(RuntimeError::with_kind(ErrorKind::Recursion, String::from("Maximum recursion depth of ") + &self.max_depth.to_string() + " exceeded"));
+                }
+
+                self.depth += 1;
+
+                let result = self.run(function, args, receiver);
+
+                self.depth -= 1;
+
+                result
+            },
+            Value::Closure(closure) => Err(RuntimeError::new(String::from("Function '") + &closure.prototype.name + "' was compiled for the virtual machine and cannot be called by the interpreter")),
+            Value::NativeFunc(native) => native.call(&args),
+            Value::Struct(struct_type) => {
+                if args.len() != struct_type.fields.len() {
+                    return Err(RuntimeError::with_kind(ErrorKind::Argument, struct_type.name.clone() + " expects " + &struct_type.fields.len().to_string() + " argument(s), got " + &args.len().to_string()));
+                }
+
+                let values = struct_type.fields.iter().cloned().zip(args).collect();
+                let instance = Value::instance(&struct_type.name, values, struct_type.methods);
+
+                self.manager.track(&instance);
+
+                Ok(instance)
+            },
+            Value::Variant(name, variant, fields) => {
+                if args.len() != fields.len() {
+                    return Err(RuntimeError::with_kind(ErrorKind::Argument, name + "." + &variant + " expects " + &fields.len().to_string() + " argument(s), got " + &args.len().to_string()));
+                }
+
+                Ok(Value::Tagged(name, variant, args))
+            },
+            _ => Err(RuntimeError::with_kind(ErrorKind::Type, function.to_string() + " is not a function"))
+        }
+    }
+
+    // Parameters are bound in a fresh child of the function's defining context, so the body sees
+    // its closure and its own parameters but nothing from the caller. A call the body makes in
+    // tail position replaces this one, so that tail recursion runs in constant space.
+    fn run(&mut self, function: Rc<Function>, args: Vec<Value>, receiver: Option<Value>) -> RuntimeResult {
+        let temps = self.temps.len();
+        let (mut function, mut args, mut receiver) = (function, args, receiver);
+
+        loop {
+            self.check_interrupt()?;
+
+            if !self.manager.is_live(function.context) {
+                return Err(RuntimeError::new(String::from("Function '") + &function.name + "' outlived the scope it was defined in"));
+            }
+
+            self.temps.truncate(temps);
+            self.temps.push(Value::Func(function.clone()));
+            self.temps.extend(args.iter().cloned());
+            self.temps.extend(receiver.clone());
+
+            let call_context = self.create_scope(function.context, &function.body)?;
+
+            if let Some(receiver) = receiver.take() {
+                self.bind(call_context, &function.body, function.params.len(), "self", receiver);
+            }
+
+            let mut values = args.into_iter();
+
+            for (i, param) in function.params.iter().enumerate() {
+                self.bind(call_context, &function.body, i, param, values.next().unwrap_or(Value::Null));
+            }
+
+            match self.visit_tail(&function.body, call_context)? {
+                Tail::Value(value) => return Ok(value),
+                Tail::Call(Value::Func(next), next_args, next_receiver) => {
+                    function = next;
+                    args = next_args;
+                    receiver = next_receiver;
+                },
+                Tail::Call(other, args, receiver) => return self.invoke(other, args, receiver)
+            }
+        }
+    }
+
+    fn visit_struct_def_node(&mut self, node: &Node, context_id: ContextId, slot: Option<u32>) -> RuntimeResult {
+        match node {
+            Node::StructDef(name, fields, methods) => {
+                let methods_context = self.create_context(context_id)?;
+
+                for method in methods {
+                    self.visit(method, methods_context)?;
+                }
+
+                let value = Value::struct_type(name, fields.clone(), methods_context);
+
+                self.manager.track(&value);
+                self.define(context_id, slot, name, value.clone());
+
+                Ok(value)
+            },
+            _ => Err(RuntimeError::new(String::from("Struct definition expected")))
+        }
+    }
+
+    fn visit_field_acc_node(&mut self, node: &Node, context_id: ContextId) -> Result<Option<Value>, RuntimeError> {
+        match node {
+            Node::FieldAcc(object, field) => {
+                match self.visit_object(object, context_id)? {
+                    Some(value) => get_field(&value, field).map(Some),
+                    None => Ok(None)
+                }
+            },
+            Node::OptionalFieldAcc(object, field) => {
+                match self.visit_object(object, context_id)? {
+                    None | Some(Value::Null) => Ok(None),
+                    Some(value) => get_field(&value, field).map(Some)
+                }
+            },
+            _ => Err(RuntimeError::new(String::from("Field access expected")))
+        }
+    }
+
+    fn visit_field_assign_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::FieldAssign(object, field, value_node) => {
+                let value = self.visit(value_node, context_id)?;
+                let (value, target) = self.visit_holding(value, object, context_id)?;
+
+                if target.set_field(field, value.clone()) {
+                    Ok(value)
+                } else {
+                    Err(RuntimeError::with_kind(ErrorKind::Field, target.to_string() + " has no field '" + field + "'"))
+                }
+            },
+            _ => Err(RuntimeError::new(String::from("Field assignment expected")))
+        }
+    }
+
+    fn visit_enum_def_node(&mut self, node: &Node, context_id: ContextId, slot: Option<u32>) -> RuntimeResult {
+        match node {
+            Node::EnumDef(name, variants) => {
+                let value = Value::Enum(name.clone(), variants.clone());
+
+                self.define(context_id, slot, name, value.clone());
+
+                Ok(value)
+            },
+            _ => Err(RuntimeError::new(String::from("Enum definition expected")))
+        }
+    }
+
+    fn visit_match_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Match(subject, arms) => {
+                let value = self.visit(subject, context_id)?;
+
+                self.temps.push(value.clone());
+
+                for (pattern, body) in arms {
+                    let mut bindings = vec![];
+
+                    if self.match_pattern(pattern, &value, context_id, &mut bindings)? {
+                        let arm_context = self.create_scope(context_id, body)?;
+
+                        for (i, (name, value)) in bindings.into_iter().enumerate() {
+                            self.bind(arm_context, body, i, &name, value);
                         }
+
+                        return self.visit(body, arm_context);
                     }
                 }
+
+                Err(RuntimeError::with_kind(ErrorKind::Match, String::from("No match arm matches '") + &value.to_string() + "'"))
+            },
+            _ => Err(RuntimeError::new(String::from("Match expected")))
+        }
+    }
+
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Value, context_id: ContextId, bindings: &mut Vec<(String, Value)>) -> Result<bool, RuntimeError> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                bindings.push((name.clone(), value.clone()));
+
+                Ok(true)
+            },
+            Pattern::Literal(node) => {
+                let literal = self.visit(node, context_id)?;
+
+                Ok(match literal.equals(value.clone()) {
+                    Ok(result) => result.is_true(),
+                    Err(_) => false
+                })
+            },
+            Pattern::Variant(enum_name, variant, patterns) => {
+                match value {
+                    Value::Tagged(name, tag, values) => {
+                        if name != enum_name || tag != variant || values.len() != patterns.len() {
+                            return Ok(false);
+                        }
+
+                        for (pattern, value) in patterns.iter().zip(values.iter()) {
+                            if !self.match_pattern(pattern, value, context_id, bindings)? {
+                                return Ok(false);
+                            }
+                        }
+
+                        Ok(true)
+                    },
+                    _ => Ok(false)
+                }
             }
-            _ => Err(RuntimeError::new(String::from("Func call expected")))
         }
     }
 
-    fn visit_if_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_throw_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Throw(value_node) => {
+                let value = self.visit(value_node, context_id)?;
+
+                let msg = value.to_string();
+
+                Err(RuntimeError::thrown(value, msg))
+            },
+            _ => Err(RuntimeError::new(String::from("Throw expected")))
+        }
+    }
+
+    fn visit_try_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Try(body, catch, finally) => {
+                let try_context = self.create_scope(context_id, body)?;
+
+                let result = match (self.visit(body, try_context), catch) {
+                    (Err(error), Some((binding, catch_body))) if error.kind().is_catchable() => {
+                        self.temps.extend(error.value().cloned());
+
+                        let catch_context = self.create_scope(context_id, catch_body)?;
+
+                        if let Some(name) = binding {
+                            self.bind(catch_context, catch_body, 0, name, error.into_value());
+                        }
+
+                        self.visit(catch_body, catch_context)
+                    },
+                    (result, _) => result
+                };
+
+                if let Some(finally_body) = finally {
+                    match &result {
+                        Ok(value) => self.temps.push(value.clone()),
+                        Err(error) => self.temps.extend(error.value().cloned())
+                    }
+
+                    let finally_context = self.create_scope(context_id, finally_body)?;
+
+                    self.visit(finally_body, finally_context)?;
+                }
+
+                result
+            },
+            _ => Err(RuntimeError::new(String::from("Try expected")))
+        }
+    }
+
+    fn visit_if_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::If(condition, body, else_body) => {
                 let condition_value = self.visit(condition, context_id)?;
 
-                if condition_value.is_true(self.manager) {
-                    let if_context = self.manager.create_context(Some(context_id));
+                if condition_value.is_true() {
+                    let if_context = self.create_scope(context_id, body)?;
 
                     self.visit(body, if_context)
                 } else {
                     match else_body {
                         Some(else_node) => {
-                            let else_context = self.manager.create_context(Some(context_id));
+                            let else_context = self.create_scope(context_id, else_node)?;
 
                             self.visit(else_node, else_context)
                         }
@@ -276,17 +1023,23 @@ impl<'a> Interpreter<'a> {
         }
     }
 
-    fn visit_while_loop_node(&mut self, node: &Node, context_id: i32) -> RuntimeResult {
+    fn visit_while_loop_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
         match node {
             Node::WhileLoop(condition, body) => {
-                let while_context = self.manager.create_context(Some(context_id));
+                let while_context = self.create_scope(context_id, body)?;
 
                 let mut result_value = Value::Null;
 
+                self.scopes.push(while_context);
+
                 loop {
-                    let condition_value = self.visit(condition, context_id)?;
+                    self.check_interrupt()?;
+
+                    let condition_value;
+
+                    (result_value, condition_value) = self.visit_holding(result_value, condition, context_id)?;
 
-                    if !condition_value.is_true(self.manager) {
+                    if !condition_value.is_true() {
                         break;
                     }
 
@@ -298,5 +1051,199 @@ impl<'a> Interpreter<'a> {
             _ => Err(RuntimeError::new(String::from("While loop expected")))
         }
     }
+
+    fn visit_for_loop_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::ForLoop(name, iterable, body) => {
+                let iterable = self.visit(iterable, context_id)?;
+
+                let for_context = self.create_scope(context_id, body)?;
+
+                let mut result_value = Value::Null;
+
+                match iterable {
+                    Value::Range(start, end, inclusive) => {
+                        let mut i = start as i64;
+                        let end = if inclusive { end as i64 + 1 } else { end as i64 };
+
+                        while i < end {
+                            self.check_interrupt()?;
+
+                            self.bind(for_context, body, 0, name, Value::Int(i as i32));
+
+                            result_value = self.visit(body, for_context)?;
+
+                            i += 1;
+                        }
+                    },
+                    Value::List(items) => {
+                        let values = items.borrow().clone();
+
+                        self.temps.push(Value::list(values.clone()));
+
+                        for value in values {
+                            self.check_interrupt()?;
+
+                            self.bind(for_context, body, 0, name, value);
+
+                            result_value = self.visit(body, for_context)?;
+                        }
+                    },
+                    Value::Str(string) => {
+                        for c in string.chars() {
+                            self.check_interrupt()?;
+
+                            self.bind(for_context, body, 0, name, Value::Str(c.to_string().into()));
+
+                            result_value = self.visit(body, for_context)?;
+                        }
+                    },
+                    value => return Err(RuntimeError::with_kind(ErrorKind::Type, value.to_string() + " is not iterable"))
+                }
+
+                Ok(result_value)
+            },
+            _ => Err(RuntimeError::new(String::from("For loop expected")))
+        }
+    }
+
+    fn visit_import_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Import(name, names) => {
+                let path = match self.modules.resolve(name) {
+                    Some(path) => path,
+                    None => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Cannot find module '") + name + "'"))
+                };
+
+                let module_context = self.load_module(&path)?;
+
+                let exports = match self.modules.get(&path) {
+                    Some(module) => module.exports.clone(),
+                    None => vec![]
+                };
+
+                let imported = match names {
+                    Some(names) => names.clone(),
+                    None => exports.clone()
+                };
+
+                for import in imported {
+                    if !exports.contains(&import) {
+                        return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Module '") + name + "' has no export named '" + &import + "'"));
+                    }
+
+                    let value = match self.manager.get(module_context, &import) {
+                        Some(value) => value.clone(),
+                        None => Value::Null
+                    };
+
+                    self.manager.set(context_id, &import, value);
+                }
+
+                Ok(Value::Null)
+            },
+            _ => Err(RuntimeError::new(String::from("Import expected")))
+        }
+    }
+
+    fn visit_export_node(&mut self, node: &Node, context_id: ContextId) -> RuntimeResult {
+        match node {
+            Node::Export(declaration) => {
+                let value = self.visit(declaration, context_id)?;
+
+                match declaration.as_ref() {
+                    Node::VarDef(name, _, _) | Node::FuncDef(name, _, _, _) | Node::StructDef(name, _, _) | Node::EnumDef(name, _) => self.modules.export(name),
+                    _ => return Err(RuntimeError::new(String::from("Only declarations can be exported")))
+                }
+
+                Ok(value)
+            },
+            _ => Err(RuntimeError::new(String::from("Export expected")))
+        }
+    }
+
+    // Evaluates the module at `path` in a fresh root context the first time it is imported and returns that context.
+    fn load_module(&mut self, path: &Path) -> Result<ContextId, RuntimeError> {
+        if let Some(module) = self.modules.get(path) {
+            return Ok(module.context_id);
+        }
+
+        if self.modules.is_loading(path) {
+            let chain: Vec<String> = self.modules.cycle(path).iter().map(|path| path.display().to_string()).collect();
+
+            return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Import cycle detected: ") + &chain.join(" -> ")));
+        }
+
+        let display = path.display().to_string();
+
+        let code = match fs::read_to_string(path) {
+            Ok(code) => code,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Cannot read module '") + &display + "': " + &error.to_string()))
+        };
+
+        let tokens = match Lexer::new(&code).tokenize() {
+            Ok(tokens) => tokens,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let node = match Parser::new(tokens).parse() {
+            Ok(node) => node,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let prelude = self.manager.prelude();
+        let mut checker = Checker::new();
+
+        checker.set_globals(self.manager, prelude);
+
+        if let Err(errors) = checker.check(&node) {
+            return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&TypeErrors::new(errors))));
+        }
+
+        let mut optimizer = Optimizer::new();
+        let mut resolver = Resolver::new();
+
+        optimizer.set_globals(shadowing(self.manager, prelude));
+        resolver.set_globals(self.manager.names(prelude));
+
+        let node = match resolver.resolve(node) {
+            Ok(node) => optimizer.optimize(node),
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let module_context = self.manager.create_root_context();
+
+        self.modules.begin(path);
+
+        match self.visit(&node, module_context) {
+            Ok(_) => {
+                self.modules.end(module_context);
+
+                Ok(module_context)
+            },
+            Err(error) => {
+                self.modules.abort();
+
+                Err(error)
+            }
+        }
+    }
 }
 
+// Reads `field` of an instance, a variant of an enum, or the kind or message of a caught error.
+pub(crate) fn get_field(value: &Value, field: &str) -> RuntimeResult {
+    let field_value = match value {
+        Value::Enum(..) => value.get_variant(field),
+        Value::Error(kind, msg) => match field {
+            "kind" => Some(Value::Str(kind.as_str().into())),
+            "message" => Some(Value::Str(msg.as_str().into())),
+            _ => None
+        },
+        value => value.get_field(field)
+    };
+
+    match field_value {
+        Some(field_value) => Ok(field_value),
+        None => Err(RuntimeError::with_kind(ErrorKind::Field, value.to_string() + " has no field '" + field + "'"))
+    }
+}
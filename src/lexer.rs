@@ -1,4 +1,4 @@
-use crate::token::TokenType;
+use crate::token::{TokenType, Position, Span, Comment};
 use crate::characters::*;
 use crate::error::*;
 
@@ -8,7 +8,11 @@ pub type LexResult = Result<TokenType, LexError>;
 pub struct Lexer {
     source: String,    
     current_char: Option<char>,
-    char_index: i32
+    char_index: i32,
+    line: usize,
+    column: usize,
+    spans: Vec<Span>,
+    comments: Vec<Comment>
 }
 
 impl Lexer {
@@ -16,7 +20,11 @@ impl Lexer {
         let mut lexer = Lexer {
             source: String::from(source),
             current_char: None,
-            char_index: -1
+            char_index: -1,
+            line: 1,
+            column: 1,
+            spans: vec![],
+            comments: vec![]
         };
         lexer.next();
         lexer
@@ -26,6 +34,7 @@ impl Lexer {
         let mut tokens = vec![];
         loop {
             let token_type;
+            let start = self.position();
 
             match self.current_char {
                 Some(current_char) => {
@@ -83,6 +92,11 @@ impl Lexer {
                                 self.next();
                                 TokenType::Comma
                             },
+                            ':' => {
+                                self.next();
+                                TokenType::Colon
+                            },
+                            '.' => self.make_dot()?,
                             '~' => {
                                 self.next();
                                 TokenType::BitwiseNot
@@ -95,24 +109,25 @@ impl Lexer {
                             '|' => self.make_or()?,
                             '&' => self.make_and()?,
                             '^' => self.make_pow()?,
+                            '?' => self.make_question()?,
                             ' ' => {
                                 self.next();
                                 continue;
                             },
                             '#' => {
-                                loop {
-                                    match self.current_char {
-                                        Some(current_char) => {
-                                            if ['\n', '\r'].contains(&current_char) {
-                                                break;
-                                            } else {
-                                                self.next();
-                                            }
-                                        },
-                                        None => break
+                                let mut text = String::new();
+
+                                while let Some(current_char) = self.current_char {
+                                    if ['\n', '\r'].contains(&current_char) {
+                                        break;
+                                    } else {
+                                        text.push(current_char);
+                                        self.next();
                                     }
                                 };
 
+                                self.comments.push(Comment { text, span: Span::new(start, self.position()) });
+
                                 continue;
                             },
                             '\t' => {
@@ -141,6 +156,8 @@ impl Lexer {
                 }
             };
 
+            self.spans.push(Span::new(start, self.position()));
+
             match token_type {
                 TokenType::EOF => {
                     tokens.push(token_type);
@@ -155,27 +172,23 @@ impl Lexer {
     fn make_number(&mut self) -> LexResult {
         let mut number_string = String::new();
         let mut has_point = false;
-        loop {
-            if let Some(current_char) = self.current_char {
-                if !DIGITS.contains(current_char) && current_char != '.' {
+        while let Some(current_char) = self.current_char {
+            if !DIGITS.contains(current_char) && current_char != '.' {
+                break;
+            }
+            if current_char == '.' {
+                if has_point || self.peek() == Some('.') {
                     break;
                 }
-                if current_char == '.' {
-                    if has_point {
-                        break;
-                    }
-                    has_point = true;
-                }
-                number_string.push(current_char);
-                self.next();
-            } else {
-                break;
+                has_point = true;
             }
+            number_string.push(current_char);
+            self.next();
         }
         if has_point {
             Ok(TokenType::Float(number_string.parse::<f32>().unwrap()))
         } else {
-            Ok(TokenType::Int(number_string.parse::<i32>().unwrap()))
+            number_string.parse::<i32>().map(TokenType::Int).map_err(|_| LexError::new(String::from("Integer '") + &number_string + "' does not fit in an int"))
         }
     }
 
@@ -224,16 +237,12 @@ impl Lexer {
     fn make_identifier(&mut self) -> LexResult {
         let mut identifier_string = String::new();
 
-        loop {
-            if let Some(current_char) = self.current_char {
-                if !LETTERS_AND_DIGITS.contains(current_char) {
-                    break;
-                }
-                identifier_string.push(current_char);
-                self.next();
-            } else {
+        while let Some(current_char) = self.current_char {
+            if !LETTERS_AND_DIGITS.contains(current_char) {
                 break;
             }
+            identifier_string.push(current_char);
+            self.next();
         }
 
         if KEYWORDS.contains(&identifier_string.as_str()) {
@@ -250,9 +259,12 @@ impl Lexer {
             Some(current_char) => {
                 if current_char == '=' {
                     self.next();
-                    return Ok(TokenType::EE);
+                    Ok(TokenType::EE)
+                } else if current_char == '>' {
+                    self.next();
+                    Ok(TokenType::Arrow)
                 } else {
-                    return Ok(TokenType::Eq);
+                    Ok(TokenType::Eq)
                 }
             },
             None => Err(LexError::new(String::from("Expected '='")))
@@ -266,9 +278,9 @@ impl Lexer {
             Some(current_char) => {
                 if current_char == '=' {
                     self.next();
-                    return Ok(TokenType::NE);
+                    Ok(TokenType::NE)
                 } else {
-                    return Ok(TokenType::Not);
+                    Ok(TokenType::Not)
                 }
             },
             None => Err(LexError::new(String::from("Expected '='")))
@@ -320,9 +332,9 @@ impl Lexer {
             Some(current_char) => {
                 if current_char == '|' {
                     self.next();
-                    return Ok(TokenType::Or);
+                    Ok(TokenType::Or)
                 } else {
-                    return Ok(TokenType::BitwiseOr);
+                    Ok(TokenType::BitwiseOr)
                 }
             },
             None => Err(LexError::new(String::from("Expected '|'")))
@@ -336,9 +348,9 @@ impl Lexer {
             Some(current_char) => {
                 if current_char == '&' {
                     self.next();
-                    return Ok(TokenType::And);
+                    Ok(TokenType::And)
                 } else {
-                    return Ok(TokenType::BitwiseAnd);
+                    Ok(TokenType::BitwiseAnd)
                 }
             },
             None => Err(LexError::new(String::from("Expected '&'")))
@@ -352,16 +364,75 @@ impl Lexer {
             Some(current_char) => {
                 if current_char == '^' {
                     self.next();
-                    return Ok(TokenType::BitwiseXOr);
+                    Ok(TokenType::BitwiseXOr)
                 } else {
-                    return Ok(TokenType::Pow);
+                    Ok(TokenType::Pow)
                 }
             },
             None => Err(LexError::new(String::from("Expected '^'")))
         }
     }
 
+    fn make_question(&mut self) -> LexResult {
+        self.next();
+
+        match self.current_char {
+            Some('?') => {
+                self.next();
+                Ok(TokenType::NullCoalesce)
+            },
+            Some('.') => {
+                self.next();
+                Ok(TokenType::QuestionDot)
+            },
+            _ => Err(LexError::new(String::from("Expected '?' or '.'")))
+        }
+    }
+
+    fn make_dot(&mut self) -> LexResult {
+        self.next();
+
+        if self.current_char != Some('.') {
+            return Ok(TokenType::Dot);
+        }
+
+        self.next();
+
+        if self.current_char == Some('=') {
+            self.next();
+            Ok(TokenType::DotDotEq)
+        } else {
+            Ok(TokenType::DotDot)
+        }
+    }
+
+    // The span of each token returned by `tokenize`, by index.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    fn position(&self) -> Position {
+        Position { line: self.line, column: self.column }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source.chars().nth((self.char_index + 1) as usize)
+    }
+
     fn next(&mut self) {
+        match self.current_char {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            },
+            Some(_) => self.column += 1,
+            None => {}
+        }
+
         self.char_index += 1;
         self.current_char = self.source.chars().nth(self.char_index as usize);
     }
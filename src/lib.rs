@@ -0,0 +1,25 @@
+pub mod lexer;
+pub mod token;
+pub mod characters;
+pub mod error;
+pub mod parser;
+pub mod node;
+pub mod interpreter;
+pub mod value;
+pub mod context;
+pub mod module;
+pub mod checker;
+pub mod visitor;
+pub mod formatter;
+pub mod dump;
+pub mod native;
+pub mod engine;
+pub mod convert;
+pub mod limits;
+pub mod bytecode;
+pub mod compiler;
+pub mod vm;
+pub mod resolver;
+pub mod optimizer;
+
+pub use crate::engine::Engine;
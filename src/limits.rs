@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Budgets for running untrusted scripts. Every limit is off by default; exceeding one stops the
+// script with a runtime error of its own kind.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    // The number of nodes the interpreter may visit.
+    pub fuel: Option<u64>,
+    pub deadline: Option<Instant>,
+    // The number of contexts that may be alive at once, after unreachable ones are collected.
+    pub max_contexts: Option<usize>,
+    // The total length of the strings, in bytes, and lists, in items, that may be alive at once,
+    // the engine's globals included. Garbage does not count: once scripts have built more than
+    // this since the last measurement, the collector runs and the ones still reachable are measured.
+    pub max_heap_size: Option<usize>
+}
+
+impl Limits {
+    pub fn new() -> Limits {
+        Limits::default()
+    }
+}
+
+// Stops a running script from another thread, such as a UI's Stop button. The interpreter checks
+// the handle at every loop iteration and call. Once interrupted, every later check in the same run
+// fails, even in a `finally` block; the engine clears the handle when the run ends.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>
+}
+
+impl InterruptHandle {
+    pub fn new() -> InterruptHandle {
+        InterruptHandle::default()
+    }
+
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+    }
+}
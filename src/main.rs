@@ -1,75 +1,154 @@
-pub mod lexer;
-pub mod token;
-pub mod characters;
-pub mod error;
-pub mod parser;
-pub mod node;
-pub mod interpreter;
-pub mod value;
-pub mod context;
-
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::interpreter::*;
-use crate::error::*;
-use crate::value::Value;
-use crate::context::*;
-
-// use std::io::{self, stdin, Write};
-use std::fs;
+use rust_parser::Engine;
+use rust_parser::engine::{self, Backend};
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+use rust_parser::error::Error;
+use rust_parser::formatter;
+use rust_parser::dump::{self, Format};
+use rust_parser::node::NodeSpans;
 
-const NAME: &str = "C:\\Users\\carri\\Desktop\\Projects\\rust-parser\\src\\main.txt";
+use std::env;
+use std::fs;
+use std::path::PathBuf;
 
 fn main() {
-    // print!("> ");
-    // io::stdout().flush().unwrap();
+    let mut file = None;
+
+    let mut args = env::args().skip(1).peekable();
+
+    let format = args.peek().is_some_and(|arg| arg == "fmt");
+
+    if format {
+        args.next();
+    }
+
+    let mut dump_tokens = None;
+    let mut dump_ast = None;
+    let mut search_paths = vec![];
+    let mut max_depth = None;
+    let mut stack_size = None;
+    let mut backend = Backend::Interpreter;
+
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--dump-tokens") || arg.starts_with("--dump-ast") {
+            let (flag, format) = match arg.split_once('=') {
+                Some((flag, name)) => (flag, Format::from_name(name)),
+                None => (arg.as_str(), Some(Format::Json))
+            };
+
+            let format = match format {
+                Some(format) => format,
+                None => {
+                    eprintln!("Unknown dump format in '{}', expected 'json' or 'sexp'", arg);
+                    return;
+                }
+            };
 
-    // let mut context = Context::new(None);
-    // context.symbol_table.set("true", Value::Boolean(true));
-    // context.symbol_table.set("false", Value::Boolean(false));
-    // context.symbol_table.set("null", Value::Null);
+            if flag == "--dump-tokens" {
+                dump_tokens = Some(format);
+            } else {
+                dump_ast = Some(format);
+            }
+        } else if arg == "--vm" {
+            backend = Backend::Vm;
+        } else if arg == "-I" || arg == "--path" {
+            match args.next() {
+                Some(path) => search_paths.push(PathBuf::from(path)),
+                None => {
+                    eprintln!("Expected a directory after '{}'", arg);
+                    return;
+                }
+            }
+        } else if arg == "--max-depth" || arg == "--stack-size" {
+            let number = match args.next().and_then(|number| number.parse::<usize>().ok()) {
+                Some(number) => number,
+                None => {
+                    eprintln!("Expected a number after '{}'", arg);
+                    return;
+                }
+            };
 
-    let mut manager = ContextManager::new();
+            if arg == "--max-depth" {
+                max_depth = Some(number);
+            } else {
+                stack_size = Some(number);
+            }
+        } else {
+            file = Some(PathBuf::from(arg));
+        }
+    }
 
-    let id = manager.create_context(None);
+    let file = match file {
+        Some(file) => file,
+        None => {
+            eprintln!("Usage: rust-parser [fmt] [options] <file>");
+            return;
+        }
+    };
 
-    manager.set(id, "true", Value::Boolean(true));
-    manager.set(id, "false", Value::Boolean(false));
-    manager.set(id, "null", Value::Null);
+    if format {
+        let code = fs::read_to_string(&file).expect("Something went wrong reading the file");
 
-    // let mut name = String::new();
+        match formatter::format(&code) {
+            Ok(formatted) => print!("{}", formatted),
+            Err(error) => eprintln!("{}", error.to_string())
+        }
 
-    // stdin().read_line(&mut name).unwrap();
+        return;
+    }
 
-    let code = fs::read_to_string(NAME).expect("Something went wrong reading the file");
+    if dump_tokens.is_some() || dump_ast.is_some() {
+        let code = fs::read_to_string(&file).expect("Something went wrong reading the file");
 
-    run(&code, &mut manager, id);
+        dump(&code, dump_tokens, dump_ast);
+
+        return;
+    }
+
+    let run = || {
+        let mut engine = Engine::new();
+
+        engine.set_backend(backend);
+
+        for path in &search_paths {
+            engine.add_search_path(path);
+        }
+
+        if let Some(max_depth) = max_depth {
+            engine.set_max_depth(max_depth);
+        }
+
+        match engine.eval_file(&file) {
+            Ok(value) => println!("{}", value),
+            Err(error) => eprintln!("{}", error.to_string())
+        }
+    };
+
+    // The stack size is given in MiB.
+    match stack_size {
+        Some(size) => engine::run_with_stack_size(size * 1024 * 1024, run),
+        None => run()
+    }
 }
 
-fn run(code: &str, manager: &mut ContextManager, context_id: i32) {
-    
-    let mut lexer = Lexer::new(&code);
-    let result = lexer.tokenize();
-
-    match result {
-        Err(error) => eprintln!("{}", error.to_string()),
-        Ok(tokens) => {
-            println!("{:?}", tokens);
-            let mut parser = Parser::new(tokens);
-            let result = parser.parse();
-            
-            match result {
-                Err(error) => eprintln!("{}", error.to_string()),
-                Ok(node) => {
-                    println!("{:?}", node);
-                    let mut interpreter = Interpreter::new(manager);
-
-                    match interpreter.visit(&node, context_id) {
-                        Err(error) => eprintln!("{}", error.to_string()),
-                        Ok(value) => println!("{}", value.to_string(&manager))
-                    }
-                }
-            }
+fn dump(code: &str, dump_tokens: Option<Format>, dump_ast: Option<Format>) {
+    let mut lexer = Lexer::new(code);
+
+    let tokens = match lexer.tokenize() {
+        Ok(tokens) => tokens,
+        Err(error) => return eprintln!("{}", error.to_string())
+    };
+
+    if let Some(format) = dump_tokens {
+        println!("{}", dump::dump_tokens(&tokens, lexer.spans(), format));
+    }
+
+    if let Some(format) = dump_ast {
+        let mut parser = Parser::with_spans(tokens, lexer.spans().to_vec());
+
+        match parser.parse() {
+            Ok(node) => println!("{}", dump::dump_node(&node, &NodeSpans::new(&node, parser.spans()), format)),
+            Err(error) => eprintln!("{}", error.to_string())
         }
     }
 }
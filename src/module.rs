@@ -0,0 +1,134 @@
+use crate::context::ContextId;
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+pub const MODULE_EXTENSION: &str = "txt";
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub path: PathBuf,
+    pub context_id: ContextId,
+    pub exports: Vec<String>
+}
+
+struct LoadingModule {
+    path: PathBuf,
+    exports: Vec<String>
+}
+
+pub struct ModuleLoader {
+    search_paths: Vec<PathBuf>,
+    modules: HashMap<PathBuf, Module>,
+    loading: Vec<LoadingModule>
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleLoader {
+    pub fn new() -> ModuleLoader {
+        ModuleLoader {
+            search_paths: vec![],
+            modules: HashMap::new(),
+            loading: vec![]
+        }
+    }
+
+    pub fn add_search_path(&mut self, path: &Path) {
+        self.search_paths.push(path.to_path_buf());
+    }
+
+    // Looks the module up next to the importing file first, then in each search path in order.
+    // A path without an extension also matches a file with the default module extension.
+    pub fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let base = match self.loading.last() {
+            Some(module) => module.path.parent().map(Path::to_path_buf),
+            None => env::current_dir().ok()
+        };
+
+        let mut directories = vec![];
+
+        if let Some(base) = base {
+            directories.push(base);
+        }
+
+        directories.extend(self.search_paths.iter().cloned());
+
+        for directory in directories {
+            let candidate = directory.join(name);
+
+            if candidate.is_file() {
+                return candidate.canonicalize().ok();
+            }
+
+            if candidate.extension().is_none() {
+                let candidate = candidate.with_extension(MODULE_EXTENSION);
+
+                if candidate.is_file() {
+                    return candidate.canonicalize().ok();
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    pub fn is_loading(&self, path: &Path) -> bool {
+        self.loading.iter().any(|module| module.path == path)
+    }
+
+    // The chain of modules currently being evaluated, from the entry file to `path`.
+    pub fn cycle(&self, path: &Path) -> Vec<PathBuf> {
+        let mut chain: Vec<PathBuf> = self.loading.iter()
+            .skip_while(|module| module.path != path)
+            .map(|module| module.path.clone())
+            .collect();
+
+        chain.push(path.to_path_buf());
+
+        chain
+    }
+
+    pub fn begin(&mut self, path: &Path) {
+        self.loading.push(LoadingModule {
+            path: path.to_path_buf(),
+            exports: vec![]
+        });
+    }
+
+    pub fn end(&mut self, context_id: ContextId) -> Option<&Module> {
+        let loaded = self.loading.pop()?;
+
+        let module = Module {
+            path: loaded.path.clone(),
+            context_id,
+            exports: loaded.exports
+        };
+
+        self.modules.insert(loaded.path.clone(), module);
+
+        self.modules.get(&loaded.path)
+    }
+
+    // Drops the module on top of the loading stack without caching it, used when evaluation fails.
+    pub fn abort(&mut self) {
+        self.loading.pop();
+    }
+
+    pub fn export(&mut self, name: &str) {
+        if let Some(module) = self.loading.last_mut() {
+            if !module.exports.iter().any(|export| export == name) {
+                module.exports.push(String::from(name));
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::value::Value;
+use crate::context::{ContextId, ContextManager};
+use crate::interpreter::RuntimeResult;
+use crate::error::{ErrorKind, RuntimeError};
+use crate::convert::HostFunction;
+
+use std::convert::TryFrom;
+use std::fmt::{self, Debug};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+pub type NativeFn = Rc<dyn Fn(&[Value]) -> RuntimeResult>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    Range(usize, usize),
+    Variadic
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == *n,
+            Arity::Range(min, max) => count >= *min && count <= *max,
+            Arity::Variadic => true
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Arity::Exact(n) => n.to_string(),
+            Arity::Range(min, max) => min.to_string() + " to " + &max.to_string(),
+            Arity::Variadic => String::from("any number of")
+        }
+    }
+}
+
+// A function implemented in Rust. Its arguments are evaluated in the caller's context.
+#[derive(Clone)]
+pub struct NativeFunc {
+    pub name: String,
+    pub arity: Arity,
+    pub function: NativeFn
+}
+
+impl NativeFunc {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> NativeFunc
+    where
+        F: Fn(&[Value]) -> RuntimeResult + 'static
+    {
+        NativeFunc {
+            name: String::from(name),
+            arity,
+            function: Rc::new(function)
+        }
+    }
+
+    pub fn typed<Args, F>(name: &str, function: F) -> NativeFunc
+    where
+        F: HostFunction<Args> + 'static
+    {
+        let arity = Arity::Exact(function.arity());
+        let owned_name = String::from(name);
+
+        NativeFunc::new(name, arity, move |args| function.invoke(&owned_name, args))
+    }
+
+    pub fn call(&self, args: &[Value]) -> RuntimeResult {
+        if !self.arity.accepts(args.len()) {
+            return Err(RuntimeError::with_kind(ErrorKind::Argument, self.name.clone() + " expects " + &self.arity.describe() + " argument(s), got " + &args.len().to_string()));
+        }
+
+        (self.function)(args)
+    }
+}
+
+impl Debug for NativeFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunc({})", self.name)
+    }
+}
+
+pub struct Registry {
+    functions: Vec<NativeFunc>
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            functions: vec![]
+        }
+    }
+
+    // The core built-ins every program can use.
+    pub fn prelude() -> Registry {
+        let mut registry = Registry::new();
+
+        registry.register("print", Arity::Variadic, |args| {
+            print!("{}", join(args));
+            io::stdout().flush().ok();
+
+            Ok(Value::Null)
+        });
+
+        registry.register("println", Arity::Variadic, |args| {
+            println!("{}", join(args));
+
+            Ok(Value::Null)
+        });
+
+        registry.register("input", Arity::Range(0, 1), |args| {
+            if let Some(prompt) = args.first() {
+                print!("{}", prompt);
+                io::stdout().flush().ok();
+            }
+
+            let mut line = String::new();
+
+            io::stdin().read_line(&mut line).map_err(|error| RuntimeError::new(String::from("Could not read input: ") + &error.to_string()))?;
+
+            Ok(Value::Str(line.trim_end_matches(&['\n', '\r'][..]).into()))
+        });
+
+        registry.register("len", Arity::Exact(1), |args| {
+            match &args[0] {
+                Value::Str(string) => Ok(Value::Int(string.chars().count() as i32)),
+                Value::List(items) => Ok(Value::Int(items.borrow().len() as i32)),
+                Value::Range(start, end, inclusive) => {
+                    let len = Value::range_len(*start, *end, *inclusive);
+
+                    i32::try_from(len).map(Value::Int).map_err(|_| RuntimeError::with_kind(ErrorKind::Runtime, String::from("Length ") + &len.to_string() + " of '" + &args[0].to_string() + "' does not fit in an int"))
+                },
+                other => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("len() cannot be applied to '") + &other.to_string() + "'"))
+            }
+        });
+
+        registry.register("type", Arity::Exact(1), |args| Ok(Value::Str(args[0].type_name().into())));
+
+        registry.register("str", Arity::Exact(1), |args| Ok(Value::Str(args[0].to_string().into())));
+
+        registry.register("int", Arity::Exact(1), |args| {
+            match &args[0] {
+                Value::Int(n) => Ok(Value::Int(*n)),
+                Value::Float(n) => Ok(Value::Int(*n as i32)),
+                Value::Boolean(b) => Ok(Value::Int(*b as i32)),
+                Value::Str(string) => match string.trim().parse::<i32>() {
+                    Ok(n) => Ok(Value::Int(n)),
+                    Err(_) => Err(conversion_error(&args[0], "int"))
+                },
+                other => Err(conversion_error(other, "int"))
+            }
+        });
+
+        registry.register("float", Arity::Exact(1), |args| {
+            match &args[0] {
+                Value::Int(n) => Ok(Value::Float(*n as f32)),
+                Value::Float(n) => Ok(Value::Float(*n)),
+                Value::Boolean(b) => Ok(Value::Float(*b as i32 as f32)),
+                Value::Str(string) => match string.trim().parse::<f32>() {
+                    Ok(n) => Ok(Value::Float(n)),
+                    Err(_) => Err(conversion_error(&args[0], "float"))
+                },
+                other => Err(conversion_error(other, "float"))
+            }
+        });
+
+        registry.register("bool", Arity::Exact(1), |args| Ok(Value::Boolean(args[0].is_true())));
+
+        registry
+    }
+
+    pub fn register<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Value]) -> RuntimeResult + 'static
+    {
+        self.functions.push(NativeFunc::new(name, arity, function));
+    }
+
+    // Registers a Rust function with typed parameters, such as `fn(i32, String) -> Vec<f32>`.
+    pub fn register_fn<Args, F>(&mut self, name: &str, function: F)
+    where
+        F: HostFunction<Args> + 'static
+    {
+        self.functions.push(NativeFunc::typed(name, function));
+    }
+
+    pub fn install(&self, manager: &mut ContextManager, context_id: ContextId) {
+        for function in &self.functions {
+            manager.set(context_id, &function.name, Value::NativeFunc(function.clone()));
+        }
+    }
+}
+
+fn join(args: &[Value]) -> String {
+    let strings: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+    strings.join(" ")
+}
+
+fn conversion_error(value: &Value, target: &str) -> RuntimeError {
+    RuntimeError::with_kind(ErrorKind::Type, String::from("Cannot convert '") + &value.to_string() + "' to " + target)
+}
@@ -1,4 +1,9 @@
-use crate::token::TokenType;
+use crate::token::{TokenType, Span};
+use crate::visitor::{Visitor, walk};
+use crate::resolver::{Layout, Lookup};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::fmt::{self, Display};
 
 #[derive(Debug, Clone)]
 pub enum Node {
@@ -7,17 +12,140 @@ pub enum Node {
     Str(String),
     BinaryOp(Box<Node>, TokenType, Box<Node>),
     UnaryOp(Box<Node>, TokenType),
-    VarDef(String, Box<Node>),
+    VarDef(String, Option<Type>, Box<Node>),
     VarAcc(String),
     ListDef(Vec<Box<Node>>),
-    FuncDef(String, Vec<String>, Box<Node>),
+    Range(Box<Node>, Box<Node>, bool),
+    Index(Box<Node>, Box<Node>),
+    FuncDef(String, Vec<(String, Option<Type>)>, Option<Type>, Box<Node>),
     FuncCall(Box<Node>, Vec<Box<Node>>),
+    OptionalCall(Box<Node>, Vec<Box<Node>>),
+    StructDef(String, Vec<String>, Vec<Box<Node>>),
+    FieldAcc(Box<Node>, String),
+    OptionalFieldAcc(Box<Node>, String),
+    FieldAssign(Box<Node>, String, Box<Node>),
+    EnumDef(String, Vec<(String, Vec<String>)>),
+    Match(Box<Node>, Vec<(Pattern, Box<Node>)>),
+    Throw(Box<Node>),
+    Try(Box<Node>, Option<(Option<String>, Box<Node>)>, Option<Box<Node>>),
     Statements(Vec<Box<Node>>, bool),
 
     If(Box<Node>, Box<Node>, Option<Box<Node>>),
 
     WhileLoop(Box<Node>, Box<Node>),
+    ForLoop(String, Box<Node>, Box<Node>),
+
+    Import(String, Option<Vec<String>>),
+    Export(Box<Node>),
+
+    // Only produced by `resolver::Resolver`: a read of a variable that knows where to find it, the
+    // body of a scope whose variables are kept in slots, and a definition in such a scope with the
+    // slot it stores its variable in.
+    ResolvedAcc(Lookup),
+    Frame(Rc<Layout>, Box<Node>),
+    Define(u32, Box<Node>),
 
     Empty,
     EOF
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Literal(Box<Node>),
+    Variant(String, String, Vec<Pattern>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Number,
+    Str,
+    Bool,
+    List,
+    Range,
+    Func,
+    Null,
+    Any,
+    Named(String)
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "number" => Type::Number,
+            "str" => Type::Str,
+            "bool" => Type::Bool,
+            "list" => Type::List,
+            "range" => Type::Range,
+            "func" => Type::Func,
+            "null" => Type::Null,
+            "any" => Type::Any,
+            _ => Type::Named(String::from(name))
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Number => write!(f, "number"),
+            Type::Str => write!(f, "str"),
+            Type::Bool => write!(f, "bool"),
+            Type::List => write!(f, "list"),
+            Type::Range => write!(f, "range"),
+            Type::Func => write!(f, "func"),
+            Type::Null => write!(f, "null"),
+            Type::Any => write!(f, "any"),
+            Type::Named(name) => write!(f, "{}", name)
+        }
+    }
+}
+
+// Looks up the span of each node in a parsed tree, pairing `Parser::spans` (which lists them
+// in post-order) back up with the nodes. Lookups are by address, so the tree must not move.
+pub struct NodeSpans {
+    spans: HashMap<*const Node, Span>
+}
+
+impl NodeSpans {
+    pub fn new(root: &Node, spans: &[Span]) -> NodeSpans {
+        let mut collector = SpanCollector {
+            spans,
+            index: 0,
+            found: HashMap::new()
+        };
+
+        collector.visit(root);
+
+        NodeSpans { spans: collector.found }
+    }
+
+    pub fn get(&self, node: &Node) -> Option<Span> {
+        self.spans.get(&(node as *const Node)).copied()
+    }
+}
+
+struct SpanCollector<'a> {
+    spans: &'a [Span],
+    index: usize,
+    found: HashMap<*const Node, Span>
+}
+
+impl Visitor for SpanCollector<'_> {
+    fn visit(&mut self, node: &Node) {
+        walk(self, node);
+
+        if let Some(span) = self.spans.get(self.index) {
+            self.found.insert(node as *const Node, *span);
+        }
+
+        self.index += 1;
+    }
+}
@@ -0,0 +1,405 @@
+use crate::node::{Node, Pattern};
+use crate::resolver::Lookup;
+use crate::token::TokenType;
+use crate::value::Value;
+use crate::context::{ContextId, ContextManager};
+use crate::visitor::{Visitor, Folder, walk, walk_pattern, fold_children};
+
+use std::collections::{HashMap, HashSet};
+
+// The rewrites `Optimizer` can make. None of them changes what a program evaluates to, prints
+// or raises; they only remove work that does not depend on anything the program does at run time.
+//
+// - `ConstantFolding` evaluates unary and binary operators whose operands are literals, using the
+//   same `Value` operations the interpreter does, so `7 / 2` folds to `3.5` and `4 / 2` to `2`.
+//   An operation that would fail or overflow is left for the interpreter to report. `??` with a
+//   literal on its left folds to the side it picks.
+// - `DeadBranches` drops the branch an `if` whose condition is a literal does not take. The one
+//   it takes still runs in a scope of its own, which its variables live in and which the
+//   resolver counted when it bound the variables it reads.
+// - `DeadLoops` removes `while` loops whose condition is a false literal.
+// - `Algebraic` drops operations that leave their operand unchanged, and merges chains of
+//   constants, when the operand's type is known from its shape: `x * 1` and `x ^ 1` for numbers,
+//   `x + 0` and `x - 0` for integers (a float's sign of zero would change), and `x / 1` only
+//   for floats, since dividing an integer goes through a float and loses precision.
+//   `(x + 1) + 2` becomes `x + 3` for integers when both constants have the same sign, and
+//   `(x * 2) * 3` becomes `x * 6` when neither constant is zero, so that the merged form
+//   overflows exactly when the original would.
+//
+// `true`, `false` and `null` are literals unless the program, or a global it runs with,
+// defines a variable of the same name. The optimizer runs on trees the resolver has bound, so
+// that variables are checked in code it removes, as well as on trees it has not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    ConstantFolding,
+    DeadBranches,
+    DeadLoops,
+    Algebraic
+}
+
+pub const PASSES: [Pass; 4] = [Pass::ConstantFolding, Pass::DeadBranches, Pass::DeadLoops, Pass::Algebraic];
+
+// What is known about the value of an expression from its shape alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Float,
+    Number,
+    Unknown
+}
+
+impl Kind {
+    fn is_number(self) -> bool {
+        self != Kind::Unknown
+    }
+}
+
+pub struct Optimizer {
+    passes: Vec<Pass>,
+    shadowed: HashSet<String>,
+    // The variables the program declares exactly once, which always hold the value of that
+    // declaration since variables cannot be reassigned, and the kinds of those found so far.
+    unique: HashSet<String>,
+    kinds: HashMap<String, Kind>
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Optimizer {
+    pub fn new() -> Optimizer {
+        Optimizer {
+            passes: PASSES.to_vec(),
+            shadowed: HashSet::new(),
+            unique: HashSet::new(),
+            kinds: HashMap::new()
+        }
+    }
+
+    pub fn set_passes(&mut self, passes: Vec<Pass>) {
+        self.passes = passes;
+    }
+
+    // Sets the names defined outside the program, which shadow `true`, `false` and `null` and
+    // which a read may find before the program's own declaration of the name runs.
+    pub fn set_globals(&mut self, globals: Vec<String>) {
+        self.shadowed.extend(globals);
+    }
+
+    pub fn optimize(mut self, node: Node) -> Node {
+        let mut declared = Declared { names: HashMap::new(), open: false };
+
+        declared.visit(&node);
+
+        if declared.open {
+            self.shadowed.extend(["true", "false", "null"].map(String::from));
+        } else {
+            self.unique = declared.names.iter()
+                .filter(|(name, count)| **count == 1 && !self.shadowed.contains(*name) && !matches!(name.as_str(), "true" | "false" | "null"))
+                .map(|(name, _)| name.clone())
+                .collect();
+        }
+
+        self.shadowed.extend(declared.names.into_keys());
+
+        self.fold(node)
+    }
+
+    fn runs(&self, pass: Pass) -> bool {
+        self.passes.contains(&pass)
+    }
+
+    // The value of `node` if it is a literal.
+    fn constant(&self, node: &Node) -> Option<Value> {
+        match node {
+            Node::Int(n) => Some(Value::Int(*n)),
+            Node::Float(n) => Some(Value::Float(*n)),
+            Node::Str(string) => Some(Value::Str(string.as_str().into())),
+            Node::VarAcc(name) | Node::ResolvedAcc(Lookup { name, .. }) if !self.shadowed.contains(name) => match name.as_str() {
+                "true" => Some(Value::Boolean(true)),
+                "false" => Some(Value::Boolean(false)),
+                "null" => Some(Value::Null),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    // The literal for `value`, if it has one.
+    fn literal(&self, value: Value) -> Option<Node> {
+        let node = match value {
+            Value::Int(n) => Node::Int(n),
+            Value::Float(n) => Node::Float(n),
+            Value::Str(string) => Node::Str(string.to_string()),
+            Value::Boolean(true) => Node::VarAcc(String::from("true")),
+            Value::Boolean(false) => Node::VarAcc(String::from("false")),
+            Value::Null => Node::VarAcc(String::from("null")),
+            _ => return None
+        };
+
+        self.constant(&node).map(|_| node)
+    }
+
+    fn kind(&self, node: &Node) -> Kind {
+        match node {
+            Node::Int(_) => Kind::Int,
+            Node::Float(_) => Kind::Float,
+            Node::VarAcc(name) | Node::ResolvedAcc(Lookup { name, .. }) => self.kinds.get(name).copied().unwrap_or(Kind::Unknown),
+            Node::UnaryOp(value, TokenType::Minus | TokenType::Plus) => match self.kind(value) {
+                Kind::Unknown => Kind::Number,
+                kind => kind
+            },
+            Node::BinaryOp(left, token, right) => {
+                let (left, right) = (self.kind(left), self.kind(right));
+                let float = left == Kind::Float || right == Kind::Float;
+
+                match token {
+                    TokenType::Minus | TokenType::Mul | TokenType::Plus if left == Kind::Int && right == Kind::Int => Kind::Int,
+                    TokenType::Plus if !left.is_number() || !right.is_number() => Kind::Unknown,
+                    TokenType::Minus | TokenType::Mul | TokenType::Div | TokenType::Plus if float => Kind::Float,
+                    TokenType::Minus | TokenType::Mul | TokenType::Div | TokenType::Plus | TokenType::Pow => Kind::Number,
+                    _ => Kind::Unknown
+                }
+            },
+            _ => Kind::Unknown
+        }
+    }
+
+    fn fold_binary(&self, left: &Value, token: &TokenType, right: Value) -> Option<Value> {
+        let result = match token {
+            TokenType::Plus => left.add(right),
+            TokenType::Minus => left.subtract(right),
+            TokenType::Mul => left.multiply(right),
+            TokenType::Div => left.divide(right),
+            TokenType::Pow => left.raise(right),
+            TokenType::EE => left.equals(right),
+            TokenType::NE => left.not_equals(right),
+            TokenType::GT => left.is_greater_than(right),
+            TokenType::GTE => left.is_greater_than_or_equal_to(right),
+            TokenType::LT => left.is_less_than(right),
+            TokenType::LTE => left.is_less_than_or_equal_to(right),
+            TokenType::BitwiseAnd => left.bitwise_and(right),
+            TokenType::BitwiseOr => left.bitwise_or(right),
+            TokenType::BitwiseXOr => left.bitwise_xor(right),
+            TokenType::BitwiseLeftShift => left.left_shift(right),
+            TokenType::BitwiseRightShift => left.right_shift(right),
+            TokenType::And => left.logical_and(right),
+            TokenType::Or => left.logical_or(right),
+            TokenType::Keyword(keyword) if keyword == "in" => right.contains(left.clone()),
+            _ => return None
+        };
+
+        result.ok()
+    }
+
+    fn fold_unary(&self, value: &Value, token: &TokenType) -> Option<Value> {
+        let result = match (value, token) {
+            (_, TokenType::Minus) => value.negate(),
+            (_, TokenType::Plus) => value.multiply(Value::Int(1)),
+            (_, TokenType::BitwiseNot) => value.bitwise_not(),
+            (_, TokenType::Not) => value.logical_not(),
+            _ => return None
+        };
+
+        result.ok()
+    }
+
+    fn simplify(&self, left: Box<Node>, token: TokenType, right: Box<Node>) -> Node {
+        let is_int = |node: &Node, n: i32| matches!(node, Node::Int(m) if *m == n);
+        let (left_kind, right_kind) = (self.kind(&left), self.kind(&right));
+
+        match token {
+            TokenType::Mul | TokenType::Pow if is_int(&right, 1) && left_kind.is_number() => return *left,
+            TokenType::Mul if is_int(&left, 1) && right_kind.is_number() => return *right,
+            TokenType::Plus | TokenType::Minus if is_int(&right, 0) && left_kind == Kind::Int => return *left,
+            TokenType::Plus if is_int(&left, 0) && right_kind == Kind::Int => return *right,
+            TokenType::Div if is_int(&right, 1) && left_kind == Kind::Float => return *left,
+            _ => {}
+        }
+
+        // Merging the constants must not let the merged form fit where the original overflows,
+        // which `x * -1` could when `x * a` is the one value that negating overflows.
+        let merged = match (left.as_ref(), right.as_ref()) {
+            (Node::BinaryOp(inner, inner_token, a), Node::Int(b)) if *inner_token == token && self.kind(inner) == Kind::Int => {
+                match (a.as_ref(), &token) {
+                    (Node::Int(a), TokenType::Plus) if (*a >= 0) == (*b >= 0) => a.checked_add(*b),
+                    (Node::Int(a), TokenType::Mul) if *a != 0 && *b != 0 && *b != -1 => a.checked_mul(*b),
+                    _ => None
+                }
+            },
+            _ => None
+        };
+
+        match (*left, merged) {
+            (Node::BinaryOp(inner, _, _), Some(merged)) => Node::BinaryOp(inner, token, Box::new(Node::Int(merged))),
+            (left, _) => Node::BinaryOp(Box::new(left), token, right)
+        }
+    }
+}
+
+impl Folder for Optimizer {
+    fn fold_var_def_node(&mut self, node: Node) -> Node {
+        let node = fold_children(self, node);
+
+        if let Node::VarDef(name, _, value) = &node {
+            if self.unique.contains(name) {
+                self.kinds.insert(name.clone(), self.kind(value));
+            }
+        }
+
+        node
+    }
+
+    fn fold_binary_op_node(&mut self, node: Node) -> Node {
+        match fold_children(self, node) {
+            Node::BinaryOp(left, token, right) => {
+                if self.runs(Pass::ConstantFolding) {
+                    if let Some(left_value) = self.constant(&left) {
+                        if let TokenType::NullCoalesce = token {
+                            return match left_value {
+                                Value::Null => *right,
+                                _ => *left
+                            };
+                        }
+
+                        let folded = self.constant(&right)
+                            .and_then(|right_value| self.fold_binary(&left_value, &token, right_value))
+                            .and_then(|value| self.literal(value));
+
+                        if let Some(folded) = folded {
+                            return folded;
+                        }
+                    }
+                }
+
+                if self.runs(Pass::Algebraic) {
+                    return self.simplify(left, token, right);
+                }
+
+                Node::BinaryOp(left, token, right)
+            },
+            node => node
+        }
+    }
+
+    fn fold_unary_op_node(&mut self, node: Node) -> Node {
+        match fold_children(self, node) {
+            Node::UnaryOp(value, token) => {
+                if self.runs(Pass::ConstantFolding) {
+                    let folded = self.constant(&value)
+                        .and_then(|constant| self.fold_unary(&constant, &token))
+                        .and_then(|constant| self.literal(constant));
+
+                    if let Some(folded) = folded {
+                        return folded;
+                    }
+                }
+
+                if let (true, TokenType::Minus, Node::UnaryOp(inner, TokenType::Minus)) = (self.runs(Pass::Algebraic), &token, value.as_ref()) {
+                    if self.kind(inner) == Kind::Float {
+                        if let Node::UnaryOp(inner, _) = *value {
+                            return *inner;
+                        }
+                    }
+                }
+
+                Node::UnaryOp(value, token)
+            },
+            node => node
+        }
+    }
+
+    fn fold_if_node(&mut self, node: Node) -> Node {
+        match fold_children(self, node) {
+            Node::If(condition, body, else_body) => {
+                match self.constant(&condition) {
+                    Some(value) if self.runs(Pass::DeadBranches) => {
+                        match (value.is_true(), else_body) {
+                            (true, _) => Node::If(Box::new(Node::Int(1)), body, None),
+                            (false, Some(else_body)) => Node::If(Box::new(Node::Int(1)), else_body, None),
+                            (false, None) => Node::Empty
+                        }
+                    },
+                    _ => Node::If(condition, body, else_body)
+                }
+            },
+            node => node
+        }
+    }
+
+    fn fold_while_loop_node(&mut self, node: Node) -> Node {
+        match fold_children(self, node) {
+            Node::WhileLoop(condition, body) => {
+                match self.constant(&condition) {
+                    Some(value) if self.runs(Pass::DeadLoops) && !value.is_true() => Node::Empty,
+                    _ => Node::WhileLoop(condition, body)
+                }
+            },
+            node => node
+        }
+    }
+}
+
+// The names visible from `context_id` that shadow a literal: every name but `true`, `false` and
+// `null` while they still hold their own values.
+pub fn shadowing(manager: &ContextManager, context_id: ContextId) -> Vec<String> {
+    manager.names(context_id).into_iter()
+        .filter(|name| !matches!((name.as_str(), manager.get(context_id, name)),
+            ("true", Some(Value::Boolean(true))) | ("false", Some(Value::Boolean(false))) | ("null", Some(Value::Null))))
+        .collect()
+}
+
+// Counts the declarations of every name the program defines anywhere.
+struct Declared {
+    names: HashMap<String, usize>,
+    open: bool
+}
+
+impl Declared {
+    fn declare(&mut self, name: &str) {
+        *self.names.entry(String::from(name)).or_insert(0) += 1;
+    }
+}
+
+impl Visitor for Declared {
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::VarDef(name, _, _) | Node::EnumDef(name, _) | Node::ForLoop(name, _, _) => self.declare(name),
+            Node::StructDef(name, _, methods) => {
+                self.declare(name);
+
+                for _ in methods {
+                    self.declare("self");
+                }
+            },
+            Node::FuncDef(name, params, _, _) => {
+                self.declare(name);
+
+                for (param, _) in params {
+                    self.declare(param);
+                }
+            },
+            Node::Try(_, Some((Some(binding), _)), _) => self.declare(binding),
+            Node::Import(_, Some(names)) => {
+                for name in names {
+                    self.declare(name);
+                }
+            },
+            Node::Import(_, None) => self.open = true,
+            _ => {}
+        }
+
+        walk(self, node);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let Pattern::Binding(name) = pattern {
+            self.declare(name);
+        }
+
+        walk_pattern(self, pattern);
+    }
+}
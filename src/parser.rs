@@ -1,4 +1,4 @@
-use crate::token::TokenType;
+use crate::token::{TokenType, Span};
 use crate::node::*;
 use crate::error::ParseError;
 
@@ -6,17 +6,36 @@ type ParseResult = Result<Node, ParseError>;
 
 pub struct Parser {
     tokens: Vec<TokenType>,
-    token_index: usize
+    token_index: usize,
+    token_spans: Vec<Span>,
+    node_spans: Vec<Span>
 }
 
 impl Parser {
     pub fn new(tokens: Vec<TokenType>) -> Parser {
         Parser {
             tokens,
-            token_index: 0
+            token_index: 0,
+            token_spans: vec![],
+            node_spans: vec![]
         }
     }
 
+    // Like `new`, but also records the span of every node it builds, given the lexer's token spans.
+    pub fn with_spans(tokens: Vec<TokenType>, token_spans: Vec<Span>) -> Parser {
+        Parser {
+            tokens,
+            token_index: 0,
+            token_spans,
+            node_spans: vec![]
+        }
+    }
+
+    // One span per node of the parsed tree, in post-order (children before their parent, in source order).
+    pub fn spans(&self) -> &[Span] {
+        &self.node_spans
+    }
+
     pub fn parse(&mut self) -> ParseResult {
         let result = self.statements(false)?;
 
@@ -27,13 +46,16 @@ impl Parser {
         }
     }
 
+    // Parses `;`-separated statements. A trailing `;` makes the sequence evaluate to null,
+    // otherwise it evaluates to its last statement. A block with no statements is `Node::Empty`.
     fn statements(&mut self, contained: bool) -> ParseResult {
+        let start = self.token_index;
         let mut nodes = vec![];
 
         loop {
 
             if self.current_token() == TokenType::RightBracket && contained {
-                return Ok(Node::Empty);
+                return Ok(self.mark(self.token_index, Node::Empty));
             }
 
             nodes.push(Box::new(self.expression()?));
@@ -44,11 +66,11 @@ impl Parser {
                     self.next();
                 }
 
-                if self.current_token() == TokenType::EOF/* || (self.current_token() == TokenType::RightBracket && contained)*/ {
-                    return Ok(Node::Statements(nodes, false));
+                if self.current_token() == TokenType::EOF || (self.current_token() == TokenType::RightBracket && contained) {
+                    return Ok(self.mark(start, Node::Statements(nodes, false)));
                 }
             } else {
-                return Ok(Node::Statements(nodes, true));
+                return Ok(self.mark(start, Node::Statements(nodes, true)));
             }
         }
     }
@@ -64,20 +86,79 @@ impl Parser {
                     self.if_expression()
                 } else if string == "while" {
                     self.while_expression()
+                } else if string == "for" {
+                    self.for_expression()
+                } else if string == "struct" {
+                    self.struct_def()
+                } else if string == "enum" {
+                    self.enum_def()
+                } else if string == "match" {
+                    self.match_expression()
+                } else if string == "throw" {
+                    self.throw_statement()
+                } else if string == "try" {
+                    self.try_expression()
+                } else if string == "import" {
+                    self.import_statement()
+                } else if string == "export" {
+                    self.export_statement()
                 } else {
-                    self.logical_bitwise_comparison()
+                    self.assignment()
                 }
             }
-            _ => self.logical_bitwise_comparison()
+            _ => self.assignment()
         }
     }
 
+    fn assignment(&mut self) -> ParseResult {
+        let start = self.token_index;
+        let node = self.null_coalescing()?;
+
+        if self.current_token() == TokenType::Eq {
+            return match node {
+                Node::FieldAcc(object, field) => {
+                    self.node_spans.pop();
+
+                    self.next();
+
+                    let value_node = self.expression()?;
+
+                    Ok(self.mark(start, Node::FieldAssign(object, field, Box::new(value_node))))
+                },
+                _ => Err(ParseError::new(String::from("Invalid assignment target")))
+            }
+        }
+
+        Ok(node)
+    }
+
+    fn null_coalescing(&mut self) -> ParseResult {
+        self.binary_operation(&mut |this: &mut Self| this.logical_bitwise_comparison(), &[TokenType::NullCoalesce], false)
+    }
+
     fn logical_bitwise_comparison(&mut self) -> ParseResult {
         self.binary_operation(&mut |this: &mut Self| this.numeric_comparison(), &[TokenType::BitwiseAnd, TokenType::BitwiseOr, TokenType::BitwiseXOr, TokenType::And, TokenType::Or], false)
     }
 
     fn numeric_comparison(&mut self) -> ParseResult {
-        self.binary_operation(&mut |this: &mut Self| this.bitwise_shifting(), &[TokenType::EE, TokenType::NE, TokenType::GT, TokenType::GTE, TokenType::LT, TokenType::LTE], false)
+        self.binary_operation(&mut |this: &mut Self| this.range(), &[TokenType::EE, TokenType::NE, TokenType::GT, TokenType::GTE, TokenType::LT, TokenType::LTE, TokenType::Keyword(String::from("in"))], false)
+    }
+
+    fn range(&mut self) -> ParseResult {
+        let start_index = self.token_index;
+        let start = self.bitwise_shifting()?;
+
+        let inclusive = match self.current_token() {
+            TokenType::DotDot => false,
+            TokenType::DotDotEq => true,
+            _ => return Ok(start)
+        };
+
+        self.next();
+
+        let end = self.bitwise_shifting()?;
+
+        Ok(self.mark(start_index, Node::Range(Box::new(start), Box::new(end), inclusive)))
     }
 
     fn bitwise_shifting(&mut self) -> ParseResult {
@@ -86,6 +167,7 @@ impl Parser {
     
     fn not(&mut self) -> ParseResult {
         if self.current_token() == TokenType::Not || self.current_token() == TokenType::BitwiseNot {
+            let start = self.token_index;
             let op_token = self.current_token();
             
             self.next();
@@ -94,7 +176,7 @@ impl Parser {
 
             return match node {
                 Node::Empty => Err(ParseError::new(String::from("Unexpected end of file."))),
-                _ => Ok(Node::UnaryOp(Box::new(node), op_token))
+                _ => Ok(self.mark(start, Node::UnaryOp(Box::new(node), op_token)))
             }
         }
 
@@ -114,6 +196,7 @@ impl Parser {
     }
 
     fn unary(&mut self) -> ParseResult {
+        let start = self.token_index;
         let current_token = self.current_token();
         if current_token == TokenType::Plus || current_token == TokenType::Minus {
             self.next();
@@ -122,7 +205,7 @@ impl Parser {
 
             return match node {
                 Node::Empty => Err(ParseError::new(String::from("Unexpected end of file."))),
-                _ => Ok(Node::UnaryOp(Box::new(node), current_token))
+                _ => Ok(self.mark(start, Node::UnaryOp(Box::new(node), current_token)))
             }
         }
 
@@ -130,35 +213,85 @@ impl Parser {
     }
 
     fn call(&mut self) -> ParseResult {
-        let node = self.listing()?;
+        let start = self.token_index;
+        let mut node = self.listing()?;
 
-        if self.current_token() == TokenType::LeftParen {
-            self.next();
+        loop {
+            if self.current_token() == TokenType::LeftParen {
+                let args = self.arguments()?;
+
+                node = self.mark(start, Node::FuncCall(Box::new(node), args));
+            } else if self.current_token() == TokenType::QuestionDot {
+                self.next();
 
-            let mut args = vec![];
+                match self.current_token() {
+                    TokenType::LeftParen => {
+                        let args = self.arguments()?;
 
-            while self.current_token() != TokenType::RightParen {
-                args.push(Box::new(self.expression()?));
+                        node = self.mark(start, Node::OptionalCall(Box::new(node), args));
+                    },
+                    TokenType::Identifier(field) => {
+                        self.next();
 
-                if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightParen {
-                    return Err(ParseError::new(String::from("Expected ',' or ')'")));
+                        node = self.mark(start, Node::OptionalFieldAcc(Box::new(node), field));
+                    },
+                    _ => return Err(ParseError::new(String::from("Expected field name or '(' after '?.'")))
                 }
+            } else if self.current_token() == TokenType::LeftSquare {
+                self.next();
 
-                if self.current_token() != TokenType::RightParen {
-                    self.next();
+                let index = self.expression()?;
+
+                if self.current_token() != TokenType::RightSquare {
+                    return Err(ParseError::new(String::from("Expected ']'")));
+                }
+
+                self.next();
+
+                node = self.mark(start, Node::Index(Box::new(node), Box::new(index)));
+            } else if self.current_token() == TokenType::Dot {
+                self.next();
+
+                match self.current_token() {
+                    TokenType::Identifier(field) => {
+                        self.next();
+
+                        node = self.mark(start, Node::FieldAcc(Box::new(node), field));
+                    },
+                    _ => return Err(ParseError::new(String::from("Expected field name")))
                 }
+            } else {
+                return Ok(node);
             }
+        }
+    }
 
-            self.next();
+    #[allow(clippy::vec_box)]
+    fn arguments(&mut self) -> Result<Vec<Box<Node>>, ParseError> {
+        self.next();
+
+        let mut args = vec![];
+
+        while self.current_token() != TokenType::RightParen {
+            args.push(Box::new(self.expression()?));
 
-            return Ok(Node::FuncCall(Box::new(node), args));
+            if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightParen {
+                return Err(ParseError::new(String::from("Expected ',' or ')'")));
+            }
+
+            if self.current_token() != TokenType::RightParen {
+                self.next();
+            }
         }
 
-        Ok(node)
+        self.next();
+
+        Ok(args)
     }
 
     fn listing(&mut self) -> ParseResult {
         if self.current_token() == TokenType::LeftSquare {
+            let start = self.token_index;
             let mut list_nodes = vec![];
             self.next();
 
@@ -176,7 +309,7 @@ impl Parser {
 
             self.next();
 
-            return Ok(Node::ListDef(list_nodes));
+            return Ok(self.mark(start, Node::ListDef(list_nodes)));
         }
 
         self.grouping()
@@ -201,6 +334,7 @@ impl Parser {
     }
 
     fn atom(&mut self) -> ParseResult {
+        let start = self.token_index;
         let result = match self.current_token() {
             TokenType::Int(number) => Ok(Node::Int(number)),
             TokenType::Float(number) => Ok(Node::Float(number)),
@@ -210,10 +344,11 @@ impl Parser {
             _ => Err(ParseError::new(String::from("Unexpected token '") + &self.current_token().to_string() + "'"))
         };
         self.next();
-        result
+        result.map(|node| self.mark(start, node))
     }
 
     pub fn binary_operation<T: FnMut(&mut Self) -> ParseResult>(&mut self, func: &mut T, token_types: &[TokenType], right_to_left: bool) -> ParseResult {
+        let start = self.token_index;
         let mut left = func(self)?;
 
         while token_types.contains(&self.current_token()) {
@@ -221,27 +356,29 @@ impl Parser {
 
             self.next();
 
-            let right;
-
-            if right_to_left {
-                right = self.binary_operation(func, token_types, right_to_left)?;
-            } else {          
-                right = func(self)?;
-            }
+            let right = if right_to_left {
+                self.binary_operation(func, token_types, right_to_left)?
+            } else {
+                func(self)?
+            };
 
-            left = Node::BinaryOp(Box::new(left), op_token, Box::new(right));
+            left = self.mark(start, Node::BinaryOp(Box::new(left), op_token, Box::new(right)));
         }
 
         Ok(left)
     }
 
     fn var_def(&mut self) -> ParseResult {
+        let start = self.token_index;
+
         self.next();
                     
         match self.current_token() {
             TokenType::Identifier(name) => {
                 self.next();
 
+                let annotation = self.type_annotation()?;
+
                 if self.current_token() != TokenType::Eq {
                     return Err(ParseError::new(String::from("Expected '='")))
                 }
@@ -250,13 +387,15 @@ impl Parser {
 
                 let value_node = self.expression()?;
 
-                Ok(Node::VarDef(name, Box::new(value_node)))
+                Ok(self.mark(start, Node::VarDef(name, annotation, Box::new(value_node))))
             },
             _ => Err(ParseError::new(String::from("Expected identifier")))
         }
     }
 
     fn function_def(&mut self) -> ParseResult {
+        let start = self.token_index;
+
         self.next();
 
         match self.current_token() {
@@ -274,10 +413,10 @@ impl Parser {
                 while self.current_token() != TokenType::RightParen {
                     match self.current_token() {
                         TokenType::Identifier(arg) => {
-                            args.push(arg);
-
                             self.next();
 
+                            args.push((arg, self.type_annotation()?));
+
                             if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightParen {
                                 return Err(ParseError::new(String::from("Expected ',' or ')'")));
                             }
@@ -292,15 +431,335 @@ impl Parser {
 
                 self.next();
 
+                let return_type = self.type_annotation()?;
+
                 let statements = self.block()?;
 
-                Ok(Node::FuncDef(function_name, args, Box::new(statements)))
+                Ok(self.mark(start, Node::FuncDef(function_name, args, return_type, Box::new(statements))))
             },
             _ => Err(ParseError::new(String::from("Expected identifier")))
         }
     }
 
+    fn type_annotation(&mut self) -> Result<Option<Type>, ParseError> {
+        if self.current_token() != TokenType::Colon {
+            return Ok(None);
+        }
+
+        self.next();
+
+        match self.current_token() {
+            TokenType::Identifier(name) => {
+                self.next();
+
+                Ok(Some(Type::from_name(&name)))
+            },
+            _ => Err(ParseError::new(String::from("Expected type name")))
+        }
+    }
+
+    fn struct_def(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        let name = match self.current_token() {
+            TokenType::Identifier(name) => name,
+            _ => return Err(ParseError::new(String::from("Expected identifier")))
+        };
+
+        self.next();
+
+        if self.current_token() != TokenType::LeftBracket {
+            return Err(ParseError::new(String::from("Expected '{'")));
+        }
+
+        self.next();
+
+        let mut fields = vec![];
+
+        // Fields are separated by commas, and the last may be followed by one.
+        while let TokenType::Identifier(field) = self.current_token() {
+            fields.push(field);
+
+            self.next();
+
+            match self.current_token() {
+                TokenType::Comma => { self.next(); },
+                TokenType::Identifier(_) => return Err(ParseError::new(String::from("Expected ',' between fields"))),
+                _ => break
+            }
+        }
+
+        let mut methods = vec![];
+
+        loop {
+            match self.current_token() {
+                TokenType::Semicolon => {
+                    self.next();
+                },
+                TokenType::Keyword(string) if string == "function" => {
+                    methods.push(Box::new(self.function_def()?));
+                },
+                TokenType::RightBracket => break,
+                _ => return Err(ParseError::new(String::from("Expected field, method or '}'")))
+            }
+        }
+
+        self.next();
+
+        Ok(self.mark(start, Node::StructDef(name, fields, methods)))
+    }
+
+    fn enum_def(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        let name = match self.current_token() {
+            TokenType::Identifier(name) => name,
+            _ => return Err(ParseError::new(String::from("Expected identifier")))
+        };
+
+        self.next();
+
+        if self.current_token() != TokenType::LeftBracket {
+            return Err(ParseError::new(String::from("Expected '{'")));
+        }
+
+        self.next();
+
+        let mut variants = vec![];
+
+        while self.current_token() != TokenType::RightBracket {
+            let variant = match self.current_token() {
+                TokenType::Identifier(variant) => variant,
+                _ => return Err(ParseError::new(String::from("Expected variant name")))
+            };
+
+            self.next();
+
+            let mut fields = vec![];
+
+            if self.current_token() == TokenType::LeftParen {
+                self.next();
+
+                while self.current_token() != TokenType::RightParen {
+                    match self.current_token() {
+                        TokenType::Identifier(field) => {
+                            fields.push(field);
+
+                            self.next();
+
+                            if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightParen {
+                                return Err(ParseError::new(String::from("Expected ',' or ')'")));
+                            }
+
+                            if self.current_token() != TokenType::RightParen {
+                                self.next();
+                            }
+                        },
+                        _ => return Err(ParseError::new(String::from("Identifier expected")))
+                    }
+                }
+
+                self.next();
+            }
+
+            variants.push((variant, fields));
+
+            if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightBracket {
+                return Err(ParseError::new(String::from("Expected ',' or '}'")));
+            }
+
+            if self.current_token() != TokenType::RightBracket {
+                self.next();
+            }
+        }
+
+        self.next();
+
+        Ok(self.mark(start, Node::EnumDef(name, variants)))
+    }
+
+    fn match_expression(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        if self.current_token() != TokenType::LeftParen {
+            return Err(ParseError::new(String::from("Expected '('")));
+        }
+
+        let subject = self.grouping()?;
+
+        if self.current_token() != TokenType::LeftBracket {
+            return Err(ParseError::new(String::from("Expected '{'")));
+        }
+
+        self.next();
+
+        let mut arms = vec![];
+
+        while self.current_token() != TokenType::RightBracket {
+            let pattern = self.pattern()?;
+
+            if self.current_token() != TokenType::Arrow {
+                return Err(ParseError::new(String::from("Expected '=>'")));
+            }
+
+            self.next();
+
+            let body = if self.current_token() == TokenType::LeftBracket {
+                self.block()?
+            } else {
+                self.expression()?
+            };
+
+            arms.push((pattern, Box::new(body)));
+
+            if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightBracket {
+                return Err(ParseError::new(String::from("Expected ',' or '}'")));
+            }
+
+            if self.current_token() != TokenType::RightBracket {
+                self.next();
+            }
+        }
+
+        self.next();
+
+        Ok(self.mark(start, Node::Match(Box::new(subject), arms)))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        match self.current_token() {
+            TokenType::Int(_) | TokenType::Float(_) | TokenType::Str(_) => Ok(Pattern::Literal(Box::new(self.atom()?))),
+            TokenType::Minus => {
+                let start = self.token_index;
+
+                self.next();
+
+                match self.current_token() {
+                    TokenType::Int(_) | TokenType::Float(_) => {
+                        let number = self.atom()?;
+
+                        Ok(Pattern::Literal(Box::new(self.mark(start, Node::UnaryOp(Box::new(number), TokenType::Minus)))))
+                    },
+                    _ => Err(ParseError::new(String::from("Expected number after '-'")))
+                }
+            },
+            TokenType::Identifier(name) => {
+                self.next();
+
+                if self.current_token() != TokenType::Dot {
+                    return Ok(match name.as_str() {
+                        "_" => Pattern::Wildcard,
+                        "true" | "false" | "null" => Pattern::Literal(Box::new(self.mark(self.token_index - 1, Node::VarAcc(name)))),
+                        _ => Pattern::Binding(name)
+                    });
+                }
+
+                self.next();
+
+                let variant = match self.current_token() {
+                    TokenType::Identifier(variant) => variant,
+                    _ => return Err(ParseError::new(String::from("Expected variant name")))
+                };
+
+                self.next();
+
+                let mut patterns = vec![];
+
+                if self.current_token() == TokenType::LeftParen {
+                    self.next();
+
+                    while self.current_token() != TokenType::RightParen {
+                        patterns.push(self.pattern()?);
+
+                        if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightParen {
+                            return Err(ParseError::new(String::from("Expected ',' or ')'")));
+                        }
+
+                        if self.current_token() != TokenType::RightParen {
+                            self.next();
+                        }
+                    }
+
+                    self.next();
+                }
+
+                Ok(Pattern::Variant(name, variant, patterns))
+            },
+            _ => Err(ParseError::new(String::from("Unexpected token '") + &self.current_token().to_string() + "' in pattern"))
+        }
+    }
+
+    fn throw_statement(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        let value = self.expression()?;
+
+        if let Node::EOF = value {
+            return Err(ParseError::new(String::from("Unexpected end of file")))
+        }
+
+        Ok(self.mark(start, Node::Throw(Box::new(value))))
+    }
+
+    fn try_expression(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        let body = self.block()?;
+
+        let mut catch = None;
+        let mut finally = None;
+
+        if self.current_token() == TokenType::Keyword(String::from("catch")) {
+            self.next();
+
+            let mut binding = None;
+
+            if self.current_token() == TokenType::LeftParen {
+                self.next();
+
+                match self.current_token() {
+                    TokenType::Identifier(name) => binding = Some(name),
+                    _ => return Err(ParseError::new(String::from("Expected identifier")))
+                }
+
+                self.next();
+
+                if self.current_token() != TokenType::RightParen {
+                    return Err(ParseError::new(String::from("Expected ')'")));
+                }
+
+                self.next();
+            }
+
+            catch = Some((binding, Box::new(self.block()?)));
+        }
+
+        if self.current_token() == TokenType::Keyword(String::from("finally")) {
+            self.next();
+
+            finally = Some(Box::new(self.block()?));
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError::new(String::from("Expected 'catch' or 'finally'")));
+        }
+
+        Ok(self.mark(start, Node::Try(Box::new(body), catch, finally)))
+    }
+
     fn if_expression(&mut self) -> ParseResult {
+        let start = self.token_index;
+
         self.next();
 
         if self.current_token() != TokenType::LeftParen {
@@ -316,11 +775,8 @@ impl Parser {
         } else {
             body = self.expression()?;
 
-            match body {
-                Node::EOF => {
-                    return Err(ParseError::new(String::from("Unexpected end of file")))
-                },
-                _ => {}
+            if let Node::EOF = body {
+                return Err(ParseError::new(String::from("Unexpected end of file")))
             }
         }
 
@@ -336,24 +792,23 @@ impl Parser {
                     } else {
                         else_body = self.expression()?;
             
-                        match else_body {
-                            Node::EOF => {
-                                return Err(ParseError::new(String::from("Unexpected end of file")))
-                            },
-                            _ => {}
+                        if let Node::EOF = else_body {
+                            return Err(ParseError::new(String::from("Unexpected end of file")))
                         }
                     }
 
-                    Ok(Node::If(Box::new(condition), Box::new(body), Some(Box::new(else_body))))
+                    Ok(self.mark(start, Node::If(Box::new(condition), Box::new(body), Some(Box::new(else_body)))))
                 } else {
-                    Ok(Node::If(Box::new(condition), Box::new(body), None))
+                    Ok(self.mark(start, Node::If(Box::new(condition), Box::new(body), None)))
                 }
             },
-            _ => Ok(Node::If(Box::new(condition), Box::new(body), None))
+            _ => Ok(self.mark(start, Node::If(Box::new(condition), Box::new(body), None)))
         }
     }
 
     fn while_expression(&mut self) -> ParseResult {
+        let start = self.token_index;
+
         self.next();
 
         if self.current_token() != TokenType::LeftParen {
@@ -369,18 +824,131 @@ impl Parser {
         } else {
             body = self.expression()?;
 
-            match body {
-                Node::EOF => {
-                    return Err(ParseError::new(String::from("Unexpected end of file")))
-                },
-                _ => {}
+            if let Node::EOF = body {
+                return Err(ParseError::new(String::from("Unexpected end of file")))
             }
         }
 
-        Ok(Node::WhileLoop(Box::new(condition), Box::new(body)))
+        Ok(self.mark(start, Node::WhileLoop(Box::new(condition), Box::new(body))))
+    }
+
+    fn import_statement(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        let mut names = None;
+
+        if self.current_token() == TokenType::LeftBracket {
+            self.next();
+
+            let mut imported = vec![];
+
+            while self.current_token() != TokenType::RightBracket {
+                match self.current_token() {
+                    TokenType::Identifier(name) => {
+                        imported.push(name);
+
+                        self.next();
+
+                        if self.current_token() != TokenType::Comma && self.current_token() != TokenType::RightBracket {
+                            return Err(ParseError::new(String::from("Expected ',' or '}'")));
+                        }
+
+                        if self.current_token() != TokenType::RightBracket {
+                            self.next();
+                        }
+                    },
+                    _ => return Err(ParseError::new(String::from("Identifier expected")))
+                }
+            }
+
+            self.next();
+
+            if self.current_token() != TokenType::Keyword(String::from("from")) {
+                return Err(ParseError::new(String::from("Expected 'from'")));
+            }
+
+            self.next();
+
+            names = Some(imported);
+        }
+
+        match self.current_token() {
+            TokenType::Str(path) => {
+                self.next();
+
+                Ok(self.mark(start, Node::Import(path, names)))
+            },
+            _ => Err(ParseError::new(String::from("Expected module path")))
+        }
+    }
+
+    fn export_statement(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        match self.current_token() {
+            TokenType::Keyword(string) if ["let", "function", "struct", "enum"].contains(&string.as_str()) => {
+                let declaration = self.expression()?;
+
+                Ok(self.mark(start, Node::Export(Box::new(declaration))))
+            },
+            _ => Err(ParseError::new(String::from("Expected 'let', 'function', 'struct' or 'enum' after 'export'")))
+        }
+    }
+
+    fn for_expression(&mut self) -> ParseResult {
+        let start = self.token_index;
+
+        self.next();
+
+        if self.current_token() != TokenType::LeftParen {
+            return Err(ParseError::new(String::from("Expected '('")));
+        }
+
+        self.next();
+
+        let name = match self.current_token() {
+            TokenType::Identifier(name) => name,
+            _ => return Err(ParseError::new(String::from("Expected identifier")))
+        };
+
+        self.next();
+
+        if self.current_token() != TokenType::Keyword(String::from("in")) {
+            return Err(ParseError::new(String::from("Expected 'in'")));
+        }
+
+        self.next();
+
+        let iterable = self.expression()?;
+
+        if self.current_token() != TokenType::RightParen {
+            return Err(ParseError::new(String::from("Expected ')'")));
+        }
+
+        self.next();
+
+        let body;
+
+        if self.current_token() == TokenType::LeftBracket {
+            body = self.block()?;
+        } else {
+            body = self.expression()?;
+
+            if let Node::EOF = body {
+                return Err(ParseError::new(String::from("Unexpected end of file")))
+            }
+        }
+
+        Ok(self.mark(start, Node::ForLoop(name, Box::new(iterable), Box::new(body))))
     }
 
     fn block(&mut self) -> ParseResult {
+        let start = self.token_index;
+
         if self.current_token() != TokenType::LeftBracket {
             return Err(ParseError::new(String::from("Expected '{'")));
         }
@@ -395,9 +963,25 @@ impl Parser {
 
         self.next();
 
+        // A block's span covers its braces, so comments before the closing brace fall inside it.
+        if self.node_spans.pop().is_some() {
+            self.node_spans.push(Span::new(self.token_spans[start].start, self.token_spans[self.token_index - 1].end));
+        }
+
         Ok(statements)
     }
 
+    // Records the span of `node`, from the token at `start` to the last token consumed.
+    fn mark(&mut self, start: usize, node: Node) -> Node {
+        let end = if self.token_index > start { self.token_index - 1 } else { start };
+
+        if let (Some(first), Some(last)) = (self.token_spans.get(start), self.token_spans.get(end)) {
+            self.node_spans.push(Span::new(first.start, last.end));
+        }
+
+        node
+    }
+
     fn next(&mut self) -> TokenType {
         if self.token_index + 1 < self.tokens.len() {
             self.token_index += 1;
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
@@ -10,6 +10,7 @@ pub enum TokenType {
     Div,
     Pow,
     Eq,
+    Arrow,
     EE,
     NE,
     GT,
@@ -19,6 +20,8 @@ pub enum TokenType {
     Not,
     And,
     Or,
+    NullCoalesce,
+    QuestionDot,
     BitwiseNot,
     BitwiseAnd,
     BitwiseOr,
@@ -30,6 +33,10 @@ pub enum TokenType {
     Str(String),
     Semicolon,
     Comma,
+    Colon,
+    Dot,
+    DotDot,
+    DotDotEq,
     LeftParen,
     RightParen,
     LeftBracket,
@@ -39,11 +46,36 @@ pub enum TokenType {
     EOF
 }
 
-impl TokenType {
-    pub fn to_string(&self) -> String {
-        let mut string = format!("{:?}", self);
-        
-        string = match self {
+// A 1-based line and column in the source text.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize
+}
+
+// The source range of a token or node. `end` points just past its last character.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+}
+
+// A `#` comment, which the lexer keeps aside from the token stream. `text` includes the `#`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span
+}
+
+impl Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string = match self {
             TokenType::Int(n) => n.to_string(),
             TokenType::Float(n) => n.to_string(),
             TokenType::Plus => String::from("+"),
@@ -52,6 +84,7 @@ impl TokenType {
             TokenType::Div => String::from("/"),
             TokenType::Pow => String::from("^"),
             TokenType::Eq => String::from("="),
+            TokenType::Arrow => String::from("=>"),
             TokenType::EE => String::from("=="),
             TokenType::NE => String::from("!="),
             TokenType::GT => String::from(">"),
@@ -67,8 +100,14 @@ impl TokenType {
             TokenType::Not => String::from("!"),
             TokenType::And => String::from("&&"),
             TokenType::Or => String::from("||"),
+            TokenType::NullCoalesce => String::from("??"),
+            TokenType::QuestionDot => String::from("?."),
             TokenType::Semicolon => String::from(";"),
             TokenType::Comma => String::from(","),
+            TokenType::Colon => String::from(":"),
+            TokenType::Dot => String::from("."),
+            TokenType::DotDot => String::from(".."),
+            TokenType::DotDotEq => String::from("..="),
             TokenType::LeftParen => String::from("("),
             TokenType::RightParen => String::from(")"),
             TokenType::LeftSquare => String::from("["),
@@ -78,9 +117,9 @@ impl TokenType {
             TokenType::Keyword(string) => string.clone(),
             TokenType::Identifier(string) => string.clone(),
             TokenType::Str(string) => String::from("\"") + string + "\"",
-            _ => string
+            _ => format!("{:?}", self)
         };
 
-        string
+        write!(f, "{}", string)
     }
 }
\ No newline at end of file
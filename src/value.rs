@@ -1,73 +1,215 @@
 use crate::interpreter::RuntimeResult;
-use crate::error::RuntimeError;
+use crate::error::{ErrorKind, RuntimeError};
 use crate::node::Node;
-use crate::ContextManager;
+use crate::native::NativeFunc;
+use crate::context::ContextId;
+use crate::bytecode::Closure;
 
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+// Strings, lists, functions and instances live on the heap behind reference-counted handles,
+// so copying a value shares the object: mutating a list or an instance through one variable is
+// visible through every other variable holding it.
 #[derive(Debug, Clone)]
 pub enum Value {
     Int(i32),
     Float(f32),
-    Str(String),
+    Str(Rc<str>),
     Boolean(bool),
-    Func(String, Vec<String>, Box<Node>, i32),
-    List(Vec<Value>),
-    Pointer(i32, String),
+    Func(Rc<Function>),
+    Closure(Rc<Closure>),
+    NativeFunc(NativeFunc),
+    List(Rc<RefCell<Vec<Value>>>),
+    Range(i32, i32, bool),
+    Struct(Rc<StructType>),
+    Instance(Rc<RefCell<Object>>),
+    Enum(String, Vec<(String, Vec<String>)>),
+    Variant(String, String, Vec<String>),
+    Tagged(String, String, Vec<Value>),
+    Error(String, String),
     Null
 }
 
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Box<Node>,
+    pub context: ContextId
+}
+
+// A struct type, with its fields in declaration order and the context its methods are defined in.
+#[derive(Debug)]
+pub struct StructType {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub methods: ContextId
+}
+
+// An instance of a struct, with its fields in declaration order.
+#[derive(Debug)]
+pub struct Object {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+    pub methods: ContextId
+}
+
 use self::Value::*;
 
 impl Value {
 
-    pub fn deref<'a>(value: &'a Value, manager: &'a ContextManager) -> Option<&'a Value> {
-        match value {
-            Value::Pointer(id, name) => {
-                manager.get(*id, name)
+    pub fn list(values: Vec<Value>) -> Value {
+        List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn function(name: &str, params: Vec<String>, body: Box<Node>, context: ContextId) -> Value {
+        Func(Rc::new(Function { name: String::from(name), params, body, context }))
+    }
+
+    pub fn struct_type(name: &str, fields: Vec<String>, methods: ContextId) -> Value {
+        Struct(Rc::new(StructType { name: String::from(name), fields, methods }))
+    }
+
+    pub fn instance(name: &str, fields: Vec<(String, Value)>, methods: ContextId) -> Value {
+        Instance(Rc::new(RefCell::new(Object { name: String::from(name), fields, methods })))
+    }
+
+    pub fn get_field(&self, field: &str) -> Option<Value> {
+        match self {
+            Instance(object) => object.borrow().fields.iter().find(|(name, _)| name == field).map(|(_, value)| value.clone()),
+            _ => None
+        }
+    }
+
+    // Looks up a variant of an enum type, returning its constructor, or the tagged value itself for variants without fields.
+    pub fn get_variant(&self, variant: &str) -> Option<Value> {
+        match self {
+            Enum(name, variants) => {
+                let (variant, fields) = variants.iter().find(|(name, _)| name == variant)?;
+
+                if fields.is_empty() {
+                    Some(Tagged(name.clone(), variant.clone(), vec![]))
+                } else {
+                    Some(Variant(name.clone(), variant.clone(), fields.clone()))
+                }
+            },
+            _ => None
+        }
+    }
+
+    // The name `type()` reports: the primitive type names used in annotations, or the struct or enum name.
+    pub fn type_name(&self) -> String {
+        match self {
+            Int(_) => String::from("int"),
+            Float(_) => String::from("float"),
+            Str(_) => String::from("str"),
+            Boolean(_) => String::from("bool"),
+            Func(..) | Closure(..) | NativeFunc(..) | Variant(..) => String::from("func"),
+            List(_) => String::from("list"),
+            Range(..) => String::from("range"),
+            Struct(..) => String::from("struct"),
+            Instance(object) => object.borrow().name.clone(),
+            Enum(..) => String::from("enum"),
+            Tagged(name, _, _) => name.clone(),
+            Error(..) => String::from("error"),
+            Null => String::from("null")
+        }
+    }
+
+    pub fn set_field(&self, field: &str, value: Value) -> bool {
+        match self {
+            Instance(object) => match object.borrow_mut().fields.iter_mut().find(|(name, _)| name == field) {
+                Some(slot) => {
+                    slot.1 = value;
+                    true
+                },
+                None => false
             },
-            _ => Some(value)
+            _ => false
         }
     }
 
-    pub fn add(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    // The built-in methods of lists, which change the list in place.
+    pub fn call_method(&self, method: &str, args: Vec<Value>) -> RuntimeResult {
+        match (self, method) {
+            (List(vec), "push") => {
+                Value::check_arity(method, 1, &args)?;
+
+                vec.borrow_mut().extend(args);
+
+                Ok(Null)
+            },
+            (List(vec), "pop") => {
+                Value::check_arity(method, 0, &args)?;
+
+                match vec.borrow_mut().pop() {
+                    Some(value) => Ok(value),
+                    None => Err(RuntimeError::with_kind(ErrorKind::Index, String::from("Cannot pop from an empty list")))
+                }
+            },
+            _ => Err(RuntimeError::with_kind(ErrorKind::Field, self.to_string() + " has no method '" + method + "'"))
+        }
+    }
+
+    fn check_arity(method: &str, expected: usize, args: &[Value]) -> Result<(), RuntimeError> {
+        if args.len() == expected {
+            Ok(())
+        } else {
+            Err(RuntimeError::with_kind(ErrorKind::Argument, String::from(method) + " expects " + &expected.to_string() + " argument(s), got " + &args.len().to_string()))
+        }
+    }
+
+    pub fn add(&self, other: Value) -> RuntimeResult {
         match (self, other) {
-            (Int(n1), Int(n2)) => Ok(Int(n1 + n2)),
+            (Int(n1), Int(n2)) => n1.checked_add(n2).map(Int).ok_or_else(|| Value::overflow("+", *n1, n2)),
             (Int(n1), Float(n2)) => Ok(Float(*n1 as f32 + n2)),
             (Float(n1), Int(n2)) => Ok(Float(n1 + n2 as f32)),
             (Float(n1), Float(n2)) => Ok(Float(n1 + n2)),
-            (Str(s1), Str(s2)) => Ok(Str(String::from(s1) + &s2)),
-            (Str(s), other) => Ok(Str(String::from(s) + &other.to_string(manager))),
-            (_, Str(s)) => Ok(Str(self.to_string(manager) + &s)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().add(other, manager),
-            (_, Pointer(id, name)) => self.add(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '+' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (Str(s1), Str(s2)) => Ok(Str((String::from(&**s1) + &s2).into())),
+            (Str(s), other) => Ok(Str((String::from(&**s) + &other.to_string()).into())),
+            (_, Str(s)) => Ok(Str((self.to_string() + &s).into())),
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '+' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
+        }
+    }
+
+    // Integer results that do not fit in an i32, and shifts by 32 or more or by a negative
+    // amount, are errors rather than wrapping around.
+    fn overflow(operator: &str, n1: i32, n2: i32) -> RuntimeError {
+        RuntimeError::with_kind(ErrorKind::Runtime, String::from("Operator '") + operator + "' overflows on '" + &n1.to_string() + "', '" + &n2.to_string() + "'.")
+    }
+
+    pub fn negate(&self) -> RuntimeResult {
+        match self {
+            Int(n) => n.checked_neg().map(Int).ok_or_else(|| RuntimeError::with_kind(ErrorKind::Runtime, String::from("Operator '-' overflows on '") + &n.to_string() + "'.")),
+            _ => self.multiply(Int(-1))
         }
     }
 
-    pub fn subtract(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn subtract(&self, other: Value) -> RuntimeResult {
         match (self, other) {
-            (Int(n1), Int(n2)) => Ok(Int(n1 - n2)),
+            (Int(n1), Int(n2)) => n1.checked_sub(n2).map(Int).ok_or_else(|| Value::overflow("-", *n1, n2)),
             (Int(n1), Float(n2)) => Ok(Float(*n1 as f32 - n2)),
             (Float(n1), Int(n2)) => Ok(Float(n1 - n2 as f32)),
             (Float(n1), Float(n2)) => Ok(Float(n1 - n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().subtract(other, manager),
-            (_, Pointer(id, name)) => self.subtract(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '-' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '-' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn multiply(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn multiply(&self, other: Value) -> RuntimeResult {
         match (self, other) {
-            (Int(n1), Int(n2)) => Ok(Int(n1 * n2)),
+            (Int(n1), Int(n2)) => n1.checked_mul(n2).map(Int).ok_or_else(|| Value::overflow("*", *n1, n2)),
             (Int(n1), Float(n2)) => Ok(Float(*n1 as f32 * n2)),
             (Float(n1), Int(n2)) => Ok(Float(n1 * n2 as f32)),
             (Float(n1), Float(n2)) => Ok(Float(n1 * n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().multiply(other, manager),
-            (_, Pointer(id, name)) => self.multiply(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '*' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '*' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn divide(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn divide(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => {
                 let r = *n1 as f32 / n2 as f32;
@@ -80,17 +222,15 @@ impl Value {
             (Int(n1), Float(n2)) => Ok(Float(*n1 as f32 / n2)),
             (Float(n1), Int(n2)) => Ok(Float(n1 / n2 as f32)),
             (Float(n1), Float(n2)) => Ok(Float(n1 / n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().divide(other, manager),
-            (_, Pointer(id, name)) => self.divide(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '/' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '/' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn raise(&self, power_of: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn raise(&self, power_of: Value) -> RuntimeResult {
         match (self, power_of) {
             (Int(n1), Int(n2)) => {
                 if n2 >= 0 {
-                    Ok(Int(n1.pow(n2 as u32)))
+                    n1.checked_pow(n2 as u32).map(Int).ok_or_else(|| Value::overflow("^", *n1, n2))
                 } else {
                     let n1 = *n1 as f32;
                     Ok(Float(n1.powi(n2)))
@@ -99,61 +239,57 @@ impl Value {
             (Int(n1), Float(n2)) => Ok(Float((*n1 as f32).powf(n2))),
             (Float(n1), Int(n2)) => Ok(Float(n1.powi(n2))),
             (Float(n1), Float(n2)) => Ok(Float(n1.powf(n2))),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().raise(other, manager),
-            (_, Pointer(id, name)) => self.raise(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '^' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '^' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn is_greater_than(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn is_greater_than(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Boolean(n1 > &n2)),
             (Int(n1), Float(n2)) => Ok(Boolean(*n1 as f32 > n2)),
             (Float(n1), Int(n2)) => Ok(Boolean(n1 > &(n2 as f32))),
             (Float(n1), Float(n2)) => Ok(Boolean(n1 > &n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().is_greater_than(other, manager),
-            (_, Pointer(id, name)) => self.is_greater_than(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '>' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '>' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn is_greater_than_or_equal_to(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn is_greater_than_or_equal_to(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Boolean(n1 >= &n2)),
             (Int(n1), Float(n2)) => Ok(Boolean(*n1 as f32 >= n2)),
             (Float(n1), Int(n2)) => Ok(Boolean(n1 >= &(n2 as f32))),
             (Float(n1), Float(n2)) => Ok(Boolean(n1 >= &n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().is_greater_than_or_equal_to(other, manager),
-            (_, Pointer(id, name)) => self.is_greater_than_or_equal_to(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '>=' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '>=' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn is_less_than(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn is_less_than(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Boolean(n1 < &n2)),
             (Int(n1), Float(n2)) => Ok(Boolean((*n1 as f32) < n2)),
             (Float(n1), Int(n2)) => Ok(Boolean(n1 < &(n2 as f32))),
             (Float(n1), Float(n2)) => Ok(Boolean(n1 < &n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().is_less_than(other, manager),
-            (_, Pointer(id, name)) => self.is_less_than(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '<' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '<' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn is_less_than_or_equal_to(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn is_less_than_or_equal_to(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Boolean(n1 <= &n2)),
             (Int(n1), Float(n2)) => Ok(Boolean((*n1 as f32) <= n2)),
             (Float(n1), Int(n2)) => Ok(Boolean(n1 <= &(n2 as f32))),
             (Float(n1), Float(n2)) => Ok(Boolean(n1 <= &n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().is_less_than_or_equal_to(other, manager),
-            (_, Pointer(id, name)) => self.is_less_than_or_equal_to(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '<=' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '<=' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn equals(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn equals(&self, other: Value) -> RuntimeResult {
+        self.equals_in(other, &mut HashSet::new())
+    }
+
+    // Instances can contain themselves, so a pair of instances already being compared further up
+    // is taken to be equal; if they differ anywhere, that comparison finds it.
+    fn equals_in(&self, other: Value, pairs: &mut HashSet<(usize, usize)>) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Boolean(n1 == &n2)),
             (Int(n1), Float(n2)) => Ok(Boolean((*n1 as f32) == n2)),
@@ -162,129 +298,293 @@ impl Value {
             (Boolean(b1), Boolean(b2)) => Ok(Boolean(b1 == &b2)),
             (Str(s1), Str(s2)) => Ok(Boolean(s1 == &s2)),
             (Null, Null) => Ok(Boolean(true)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().equals(other, manager),
-            (_, Pointer(id, name)) => self.equals(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Cannot compare '") + &self.to_string(manager) + "' with '" + &other.to_string(manager) + "'."))
+            (Instance(object1), Instance(object2)) => {
+                let pair = (Rc::as_ptr(object1) as *const () as usize, Rc::as_ptr(&object2) as *const () as usize);
+
+                if !pairs.insert(pair) {
+                    return Ok(Boolean(true));
+                }
+
+                let (object1, object2) = (object1.borrow(), object2.borrow());
+
+                if object1.name != object2.name || object1.fields.len() != object2.fields.len() {
+                    return Ok(Boolean(false));
+                }
+
+                for ((_, value1), (_, value2)) in object1.fields.iter().zip(object2.fields.iter()) {
+                    if !value1.equals_in(value2.clone(), pairs)?.is_true() {
+                        return Ok(Boolean(false));
+                    }
+                }
+
+                Ok(Boolean(true))
+            },
+            (Tagged(enum1, variant1, values1), Tagged(enum2, variant2, values2)) => {
+                if enum1 != &enum2 || variant1 != &variant2 || values1.len() != values2.len() {
+                    return Ok(Boolean(false));
+                }
+
+                for (value1, value2) in values1.iter().zip(values2) {
+                    if !value1.equals_in(value2, pairs)?.is_true() {
+                        return Ok(Boolean(false));
+                    }
+                }
+
+                Ok(Boolean(true))
+            },
+            (Range(start1, end1, inclusive1), Range(start2, end2, inclusive2)) => Ok(Boolean(Value::range_len(*start1, *end1, *inclusive1) == Value::range_len(start2, end2, inclusive2) && (start1 == &start2 || Value::range_len(start2, end2, inclusive2) == 0))),
+            (Error(kind1, msg1), Error(kind2, msg2)) => Ok(Boolean(kind1 == &kind2 && msg1 == &msg2)),
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Cannot compare '") + &self.to_string() + "' with '" + &other.to_string() + "'."))
+        }
+    }
+
+    pub fn not_equals(&self, other: Value) -> RuntimeResult {
+        match self.equals(other)? {
+            Boolean(b) => Ok(Boolean(!b)),
+            result => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Comparing '") + &self.to_string() + "' with '" + &result.to_string() + "' gave a non-boolean value."))
+        }
+    }
+
+    pub fn contains(&self, item: Value) -> RuntimeResult {
+        match (self, item) {
+            (Range(start, end, inclusive), Int(n)) => Ok(Boolean(n >= *start && (n < *end || (*inclusive && n == *end)))),
+            (Range(start, end, inclusive), Float(n)) => Ok(Boolean(n.fract() == 0.0 && Value::range_contains(*start, *end, *inclusive, n as f64))),
+            (List(vec), item) => {
+                for value in vec.borrow().iter() {
+                    if let Ok(Boolean(true)) = value.equals(item.clone()) {
+                        return Ok(Boolean(true));
+                    }
+                }
+
+                Ok(Boolean(false))
+            },
+            (Str(s), Str(sub)) => Ok(Boolean(s.contains(&*sub))),
+            (_, item) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator 'in' cannot be applied to '") + &item.to_string() + "', '" + &self.to_string() + "'."))
+        }
+    }
+
+    pub fn index(&self, index: Value) -> RuntimeResult {
+        match (self, index) {
+            (List(vec), Int(i)) => match usize::try_from(i).ok().and_then(|i| vec.borrow().get(i).cloned()) {
+                Some(value) => Ok(value),
+                None => Err(Value::out_of_range(i, vec.borrow().len()))
+            },
+            (Str(s), Int(i)) => match usize::try_from(i).ok().and_then(|i| s.chars().nth(i)) {
+                Some(c) => Ok(Str(c.to_string().into())),
+                None => Err(Value::out_of_range(i, s.chars().count()))
+            },
+            (Range(start, end, inclusive), Int(i)) => {
+                let len = Value::range_len(*start, *end, *inclusive);
+
+                if i >= 0 && (i as i64) < len {
+                    Ok(Int(start + i))
+                } else {
+                    Err(Value::out_of_range(i, len as usize))
+                }
+            },
+            (List(vec), Range(start, end, inclusive)) => {
+                let vec = vec.borrow();
+                let (from, to) = Value::slice_bounds(start, end, inclusive, vec.len());
+
+                Ok(Value::list(vec[from..to].to_vec()))
+            },
+            (Str(s), Range(start, end, inclusive)) => {
+                let (from, to) = Value::slice_bounds(start, end, inclusive, s.chars().count());
+
+                Ok(Str(s.chars().skip(from).take(to - from).collect::<String>().into()))
+            },
+            (Range(outer_start, outer_end, outer_inclusive), Range(start, end, inclusive)) => {
+                let (from, to) = Value::slice_bounds(start, end, inclusive, Value::range_len(*outer_start, *outer_end, *outer_inclusive) as usize);
+
+                let from = *outer_start as i64 + from as i64;
+                let to = *outer_start as i64 + to as i64;
+
+                // A slice reaching past i32::MAX can only be written inclusively.
+                if to <= i32::MAX as i64 {
+                    Ok(Range(from as i32, to as i32, false))
+                } else if from < to {
+                    Ok(Range(from as i32, (to - 1) as i32, true))
+                } else {
+                    Ok(Range(i32::MAX, i32::MAX, false))
+                }
+            },
+            (_, index) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Cannot index '") + &self.to_string() + "' with '" + &index.to_string() + "'."))
         }
     }
 
-    pub fn is_true(&self, manager: &ContextManager) -> bool {
+    pub fn range_len(start: i32, end: i32, inclusive: bool) -> i64 {
+        let end = if inclusive { end as i64 + 1 } else { end as i64 };
+
+        (end - start as i64).max(0)
+    }
+
+    // Bounds are widened to f64, which holds every i32 exactly.
+    fn range_contains(start: i32, end: i32, inclusive: bool, n: f64) -> bool {
+        n >= start as f64 && (n < end as f64 || (inclusive && n == end as f64))
+    }
+
+    // Clamps a range to the valid positions of a sequence of length `len`, like slicing in most languages.
+    fn slice_bounds(start: i32, end: i32, inclusive: bool, len: usize) -> (usize, usize) {
+        let end = if inclusive { end as i64 + 1 } else { end as i64 };
+
+        let from = (start as i64).clamp(0, len as i64) as usize;
+        let to = end.clamp(from as i64, len as i64) as usize;
+
+        (from, to)
+    }
+
+    fn out_of_range(index: i32, len: usize) -> RuntimeError {
+        RuntimeError::with_kind(ErrorKind::Index, String::from("Index ") + &index.to_string() + " is out of range for length " + &len.to_string())
+    }
+
+    pub fn is_true(&self) -> bool {
         match self {
             Int(n) => *n != 0,
             Float(n) => *n != 0.0,
             Boolean(b) => *b,
-            Str(s) => s != "",
+            Str(s) => !s.is_empty(),
             Func(..) => true,
-            List(vec) => !vec.is_empty(),
-            Pointer(_, _) => Value::deref(self, manager).unwrap().is_true(manager),
+            Closure(..) => true,
+            NativeFunc(..) => true,
+            List(vec) => !vec.borrow().is_empty(),
+            Range(start, end, inclusive) => Value::range_len(*start, *end, *inclusive) > 0,
+            Struct(..) => true,
+            Instance(..) => true,
+            Enum(..) => true,
+            Variant(..) => true,
+            Tagged(..) => true,
+            Error(..) => true,
             Null => false
         }
     }
 
-    pub fn bitwise_and(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn bitwise_and(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Int(n1 & n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().bitwise_and(other, manager),
-            (_, Pointer(id, name)) => self.bitwise_and(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '&' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '&' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn bitwise_or(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn bitwise_or(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Int(n1 | n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().bitwise_or(other, manager),
-            (_, Pointer(id, name)) => self.bitwise_or(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '|' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '|' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn bitwise_xor(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn bitwise_xor(&self, other: Value) -> RuntimeResult {
         match (self, other) {
             (Int(n1), Int(n2)) => Ok(Int(n1 ^ n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().bitwise_xor(other, manager),
-            (_, Pointer(id, name)) => self.bitwise_xor(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '^^' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '^^' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn bitwise_not(&self, manager: &ContextManager) -> RuntimeResult {
+    pub fn bitwise_not(&self) -> RuntimeResult {
         match self {
             Int(n) => Ok(Int(!n)),
-            Pointer(_, _) => Value::deref(self, manager).unwrap().bitwise_not(manager),
-            _ => Err(RuntimeError::new(String::from("Operator '~' cannot be applied to '") + &self.to_string(manager) + "'."))
+            _ => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '~' cannot be applied to '") + &self.to_string() + "'."))
         }
     }
 
-    pub fn left_shift(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn left_shift(&self, other: Value) -> RuntimeResult {
         match (self, other) {
-            (Int(n1), Int(n2)) => Ok(Int(n1 << n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().left_shift(other, manager),
-            (_, Pointer(id, name)) => self.left_shift(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '<<' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (Int(n1), Int(n2)) => u32::try_from(n2).ok().and_then(|n| n1.checked_shl(n)).map(Int).ok_or_else(|| Value::overflow("<<", *n1, n2)),
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '<<' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn right_shift(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
+    pub fn right_shift(&self, other: Value) -> RuntimeResult {
         match (self, other) {
-            (Int(n1), Int(n2)) => Ok(Int(n1 >> n2)),
-            (Pointer(_, _), other) => Value::deref(self, manager).unwrap().right_shift(other, manager),
-            (_, Pointer(id, name)) => self.right_shift(Value::deref(&Value::Pointer(id, name), manager).unwrap().clone(), manager),
-            (_, other) => Err(RuntimeError::new(String::from("Operator '>>' cannot be applied to '") + &self.to_string(manager) + "', '" + &other.to_string(manager) + "'."))
+            (Int(n1), Int(n2)) => u32::try_from(n2).ok().and_then(|n| n1.checked_shr(n)).map(Int).ok_or_else(|| Value::overflow(">>", *n1, n2)),
+            (_, other) => Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Operator '>>' cannot be applied to '") + &self.to_string() + "', '" + &other.to_string() + "'."))
         }
     }
 
-    pub fn logical_or(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
-        if self.is_true(manager) {
+    pub fn logical_or(&self, other: Value) -> RuntimeResult {
+        if self.is_true() {
             Ok(self.clone())
         } else {
             Ok(other)
         }
     }
 
-    pub fn logical_and(&self, other: Value, manager: &ContextManager) -> RuntimeResult {
-        if self.is_true(manager) {
+    pub fn logical_and(&self, other: Value) -> RuntimeResult {
+        if self.is_true() {
             Ok(other)
         } else {
             Ok(self.clone())
         }
     }
 
-    pub fn logical_not(&self, manager: &ContextManager) -> RuntimeResult {
-        Ok(Boolean(!self.is_true(manager)))
+    pub fn logical_not(&self) -> RuntimeResult {
+        Ok(Boolean(!self.is_true()))
     }
+}
 
-    pub fn to_string(&self, manager: &ContextManager) -> String {
-        match self {
-            Int(n) => n.to_string(),
-            Float(n) => n.to_string(),
-            Boolean(b) => b.to_string(),
-            Str(s) => String::from(s),
-            Func(name, args, _, _) => name.clone() + "(" + &args.join(", ") + ")",
-            List(vec) => {
-                let mut string = String::from("[");
-
-                for item in 0..vec.len() {
-                    match &vec.get(item) {
-                        Some(val) => {
-                            string += &val.to_string(manager);
-                            if item < vec.len() - 1 {
-                                string += ", "
-                            }
-                        },
-                        None => break
-                    }
-                }
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", display(self, &mut HashSet::new()))
+    }
+}
 
-                string += "]";
+// Lists and instances in `objects` are being printed further up, so they are shown as `[...]` and
+// `Name {...}` instead of printing themselves again.
+fn display(value: &Value, objects: &mut HashSet<usize>) -> String {
+    match value {
+        Int(n) => n.to_string(),
+        Float(n) => n.to_string(),
+        Boolean(b) => b.to_string(),
+        Str(s) => String::from(&**s),
+        Func(function) => function.name.clone() + "(" + &function.params.join(", ") + ")",
+        Closure(closure) => closure.prototype.name.clone() + "(" + &closure.prototype.params.join(", ") + ")",
+        NativeFunc(function) => String::from("<native ") + &function.name + ">",
+        List(vec) => {
+            let pointer = Rc::as_ptr(vec) as *const () as usize;
 
-                string
-            },
-            Pointer(context_id, name) => match manager.get(*context_id, name) {
-                Some(value) => value.to_string(manager),
-                None => String::from("null")
-            },
-            Null => String::from("null")
-        }
+            if !objects.insert(pointer) {
+                return String::from("[...]");
+            }
+
+            let items: Vec<String> = vec.borrow().iter().map(|item| display(item, objects)).collect();
+
+            objects.remove(&pointer);
+
+            String::from("[") + &items.join(", ") + "]"
+        },
+        Range(start, end, inclusive) => start.to_string() + if *inclusive { "..=" } else { ".." } + &end.to_string(),
+        Struct(struct_type) => String::from("struct ") + &struct_type.name,
+        Instance(object) => {
+            let pointer = Rc::as_ptr(object) as *const () as usize;
+            let object = object.borrow();
+
+            if !objects.insert(pointer) {
+                return object.name.clone() + " {...}";
+            }
+
+            let string = if object.fields.is_empty() {
+                object.name.clone() + " {}"
+            } else {
+                let fields: Vec<String> = object.fields.iter().map(|(field, value)| field.clone() + ": " + &display(value, objects)).collect();
+
+                object.name.clone() + " { " + &fields.join(", ") + " }"
+            };
+
+            objects.remove(&pointer);
+
+            string
+        },
+        Enum(name, _) => String::from("enum ") + name,
+        Variant(name, variant, fields) => String::from("<variant ") + name + "." + variant + "(" + &fields.join(", ") + ")>",
+        Tagged(name, variant, values) => {
+            if values.is_empty() {
+                name.clone() + "." + variant
+            } else {
+                let values: Vec<String> = values.iter().map(|value| display(value, objects)).collect();
+
+                name.clone() + "." + variant + "(" + &values.join(", ") + ")"
+            }
+        },
+        Error(kind, msg) => kind.clone() + ": " + msg,
+        Null => String::from("null")
     }
-}
\ No newline at end of file
+}
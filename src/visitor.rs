@@ -0,0 +1,286 @@
+use crate::node::*;
+
+// Read-only traversal of a `Node` tree. `visit` dispatches on the node kind to the matching
+// `visit_*_node` method, and every method defaults to `walk`, which visits the node's children.
+// Implementors override only the node kinds they care about and call `walk` to keep descending.
+pub trait Visitor {
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::Statements(..) => self.visit_statements_node(node),
+            Node::Int(..) => self.visit_int_node(node),
+            Node::Float(..) => self.visit_float_node(node),
+            Node::Str(..) => self.visit_string_node(node),
+            Node::UnaryOp(..) => self.visit_unary_op_node(node),
+            Node::BinaryOp(..) => self.visit_binary_op_node(node),
+            Node::VarDef(..) => self.visit_var_def_node(node),
+            Node::VarAcc(..) => self.visit_var_acc_node(node),
+            Node::ListDef(..) => self.visit_list_def_node(node),
+            Node::Range(..) => self.visit_range_node(node),
+            Node::Index(..) => self.visit_index_node(node),
+            Node::FuncDef(..) => self.visit_func_def_node(node),
+            Node::FuncCall(..) => self.visit_func_call_node(node),
+            Node::OptionalCall(..) => self.visit_optional_call_node(node),
+            Node::StructDef(..) => self.visit_struct_def_node(node),
+            Node::FieldAcc(..) => self.visit_field_acc_node(node),
+            Node::OptionalFieldAcc(..) => self.visit_optional_field_acc_node(node),
+            Node::FieldAssign(..) => self.visit_field_assign_node(node),
+            Node::EnumDef(..) => self.visit_enum_def_node(node),
+            Node::Match(..) => self.visit_match_node(node),
+            Node::Throw(..) => self.visit_throw_node(node),
+            Node::Try(..) => self.visit_try_node(node),
+            Node::If(..) => self.visit_if_node(node),
+            Node::WhileLoop(..) => self.visit_while_loop_node(node),
+            Node::ForLoop(..) => self.visit_for_loop_node(node),
+            Node::Import(..) => self.visit_import_node(node),
+            Node::Export(..) => self.visit_export_node(node),
+            Node::ResolvedAcc(..) => self.visit_resolved_acc_node(node),
+            Node::Frame(..) => self.visit_frame_node(node),
+            Node::Define(..) => self.visit_define_node(node),
+            Node::Empty | Node::EOF => {}
+        }
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_statements_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_int_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_float_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_string_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_unary_op_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_binary_op_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_var_def_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_var_acc_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_list_def_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_range_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_index_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_func_def_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_func_call_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_optional_call_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_struct_def_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_field_acc_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_optional_field_acc_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_field_assign_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_enum_def_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_match_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_throw_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_try_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_if_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_while_loop_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_for_loop_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_import_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_export_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_resolved_acc_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_frame_node(&mut self, node: &Node) { walk(self, node) }
+    fn visit_define_node(&mut self, node: &Node) { walk(self, node) }
+}
+
+// Visits each direct child of `node`, in source order.
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, node: &Node) {
+    match node {
+        Node::Statements(nodes, _) | Node::ListDef(nodes) | Node::StructDef(_, _, nodes) => {
+            for node in nodes {
+                visitor.visit(node);
+            }
+        },
+        Node::UnaryOp(node, _) | Node::VarDef(_, _, node) | Node::FuncDef(_, _, _, node) | Node::FieldAcc(node, _) |
+        Node::OptionalFieldAcc(node, _) | Node::Throw(node) | Node::Export(node) | Node::Frame(_, node) |
+        Node::Define(_, node) => visitor.visit(node),
+        Node::BinaryOp(left, _, right) | Node::Range(left, right, _) | Node::Index(left, right) | Node::FieldAssign(left, _, right) |
+        Node::WhileLoop(left, right) | Node::ForLoop(_, left, right) => {
+            visitor.visit(left);
+            visitor.visit(right);
+        },
+        Node::FuncCall(func, args) | Node::OptionalCall(func, args) => {
+            visitor.visit(func);
+
+            for arg in args {
+                visitor.visit(arg);
+            }
+        },
+        Node::Match(subject, arms) => {
+            visitor.visit(subject);
+
+            for (pattern, body) in arms {
+                visitor.visit_pattern(pattern);
+                visitor.visit(body);
+            }
+        },
+        Node::Try(body, catch, finally) => {
+            visitor.visit(body);
+
+            if let Some((_, catch_body)) = catch {
+                visitor.visit(catch_body);
+            }
+
+            if let Some(finally_body) = finally {
+                visitor.visit(finally_body);
+            }
+        },
+        Node::If(condition, body, else_body) => {
+            visitor.visit(condition);
+            visitor.visit(body);
+
+            if let Some(else_body) = else_body {
+                visitor.visit(else_body);
+            }
+        },
+        Node::Int(..) | Node::Float(..) | Node::Str(..) | Node::VarAcc(..) | Node::ResolvedAcc(..) | Node::EnumDef(..) |
+        Node::Import(..) | Node::Empty | Node::EOF => {}
+    }
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Literal(node) => visitor.visit(node),
+        Pattern::Variant(_, _, patterns) => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        },
+        Pattern::Wildcard | Pattern::Binding(_) => {}
+    }
+}
+
+// Owning transformation of a `Node` tree. `fold` dispatches on the node kind to the matching
+// `fold_*_node` method, and every method defaults to `fold_children`, which rebuilds the node
+// from its folded children. Implementors override the node kinds they rewrite.
+pub trait Folder {
+    fn fold(&mut self, node: Node) -> Node {
+        match node {
+            Node::Statements(..) => self.fold_statements_node(node),
+            Node::Int(..) => self.fold_int_node(node),
+            Node::Float(..) => self.fold_float_node(node),
+            Node::Str(..) => self.fold_string_node(node),
+            Node::UnaryOp(..) => self.fold_unary_op_node(node),
+            Node::BinaryOp(..) => self.fold_binary_op_node(node),
+            Node::VarDef(..) => self.fold_var_def_node(node),
+            Node::VarAcc(..) => self.fold_var_acc_node(node),
+            Node::ListDef(..) => self.fold_list_def_node(node),
+            Node::Range(..) => self.fold_range_node(node),
+            Node::Index(..) => self.fold_index_node(node),
+            Node::FuncDef(..) => self.fold_func_def_node(node),
+            Node::FuncCall(..) => self.fold_func_call_node(node),
+            Node::OptionalCall(..) => self.fold_optional_call_node(node),
+            Node::StructDef(..) => self.fold_struct_def_node(node),
+            Node::FieldAcc(..) => self.fold_field_acc_node(node),
+            Node::OptionalFieldAcc(..) => self.fold_optional_field_acc_node(node),
+            Node::FieldAssign(..) => self.fold_field_assign_node(node),
+            Node::EnumDef(..) => self.fold_enum_def_node(node),
+            Node::Match(..) => self.fold_match_node(node),
+            Node::Throw(..) => self.fold_throw_node(node),
+            Node::Try(..) => self.fold_try_node(node),
+            Node::If(..) => self.fold_if_node(node),
+            Node::WhileLoop(..) => self.fold_while_loop_node(node),
+            Node::ForLoop(..) => self.fold_for_loop_node(node),
+            Node::Import(..) => self.fold_import_node(node),
+            Node::Export(..) => self.fold_export_node(node),
+            Node::ResolvedAcc(..) => self.fold_resolved_acc_node(node),
+            Node::Frame(..) => self.fold_frame_node(node),
+            Node::Define(..) => self.fold_define_node(node),
+            Node::Empty | Node::EOF => node
+        }
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        fold_pattern_children(self, pattern)
+    }
+
+    fn fold_statements_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_int_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_float_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_string_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_unary_op_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_binary_op_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_var_def_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_var_acc_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_list_def_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_range_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_index_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_func_def_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_func_call_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_optional_call_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_struct_def_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_field_acc_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_optional_field_acc_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_field_assign_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_enum_def_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_match_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_throw_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_try_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_if_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_while_loop_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_for_loop_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_import_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_export_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_resolved_acc_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_frame_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+    fn fold_define_node(&mut self, node: Node) -> Node { fold_children(self, node) }
+}
+
+// Rebuilds `node` with each direct child replaced by the result of folding it.
+pub fn fold_children<F: Folder + ?Sized>(folder: &mut F, node: Node) -> Node {
+    let mut fold_box = |node: Box<Node>| Box::new(folder.fold(*node));
+
+    match node {
+        Node::Statements(nodes, should_return_last) => Node::Statements(fold_all(folder, nodes), should_return_last),
+        Node::ListDef(nodes) => Node::ListDef(fold_all(folder, nodes)),
+        Node::StructDef(name, fields, methods) => Node::StructDef(name, fields, fold_all(folder, methods)),
+        Node::UnaryOp(node, token) => Node::UnaryOp(fold_box(node), token),
+        Node::BinaryOp(left, token, right) => Node::BinaryOp(fold_box(left), token, fold_box(right)),
+        Node::VarDef(name, annotation, value) => Node::VarDef(name, annotation, fold_box(value)),
+        Node::Range(start, end, inclusive) => Node::Range(fold_box(start), fold_box(end), inclusive),
+        Node::Index(object, index) => Node::Index(fold_box(object), fold_box(index)),
+        Node::FuncDef(name, params, return_type, body) => Node::FuncDef(name, params, return_type, fold_box(body)),
+        Node::FuncCall(func, args) => {
+            let func = Box::new(folder.fold(*func));
+
+            Node::FuncCall(func, fold_all(folder, args))
+        },
+        Node::OptionalCall(func, args) => {
+            let func = Box::new(folder.fold(*func));
+
+            Node::OptionalCall(func, fold_all(folder, args))
+        },
+        Node::FieldAcc(object, field) => Node::FieldAcc(fold_box(object), field),
+        Node::OptionalFieldAcc(object, field) => Node::OptionalFieldAcc(fold_box(object), field),
+        Node::FieldAssign(object, field, value) => Node::FieldAssign(fold_box(object), field, fold_box(value)),
+        Node::Match(subject, arms) => {
+            let subject = Box::new(folder.fold(*subject));
+
+            let arms = arms.into_iter().map(|(pattern, body)| (folder.fold_pattern(pattern), Box::new(folder.fold(*body)))).collect();
+
+            Node::Match(subject, arms)
+        },
+        Node::Throw(value) => Node::Throw(fold_box(value)),
+        Node::Try(body, catch, finally) => Node::Try(
+            fold_box(body),
+            catch.map(|(binding, catch_body)| (binding, fold_box(catch_body))),
+            finally.map(&mut fold_box)
+        ),
+        Node::If(condition, body, else_body) => Node::If(fold_box(condition), fold_box(body), else_body.map(&mut fold_box)),
+        Node::WhileLoop(condition, body) => Node::WhileLoop(fold_box(condition), fold_box(body)),
+        Node::ForLoop(name, iterable, body) => Node::ForLoop(name, fold_box(iterable), fold_box(body)),
+        Node::Export(declaration) => Node::Export(fold_box(declaration)),
+        Node::Frame(layout, body) => Node::Frame(layout, fold_box(body)),
+        Node::Define(slot, definition) => Node::Define(slot, fold_box(definition)),
+        node @ (Node::Int(..) | Node::Float(..) | Node::Str(..) | Node::VarAcc(..) | Node::ResolvedAcc(..) | Node::EnumDef(..) |
+                Node::Import(..) | Node::Empty | Node::EOF) => node
+    }
+}
+
+pub fn fold_pattern_children<F: Folder + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    match pattern {
+        Pattern::Literal(node) => Pattern::Literal(Box::new(folder.fold(*node))),
+        Pattern::Variant(enum_name, variant, patterns) => {
+            Pattern::Variant(enum_name, variant, patterns.into_iter().map(|pattern| folder.fold_pattern(pattern)).collect())
+        },
+        pattern => pattern
+    }
+}
+
+#[allow(clippy::vec_box)]
+fn fold_all<F: Folder + ?Sized>(folder: &mut F, nodes: Vec<Box<Node>>) -> Vec<Box<Node>> {
+    nodes.into_iter().map(|node| Box::new(folder.fold(*node))).collect()
+}
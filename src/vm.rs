@@ -0,0 +1,920 @@
+use crate::bytecode::*;
+use crate::compiler::Compiler;
+use crate::value::Value;
+use crate::error::{Error, ErrorKind, RuntimeError, TypeErrors};
+use crate::interpreter::{get_field, RuntimeResult, DEFAULT_MAX_DEPTH};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::checker::Checker;
+use crate::resolver::Resolver;
+use crate::optimizer::{shadowing, Optimizer};
+use crate::module::ModuleLoader;
+use crate::context::{ContextId, ContextManager};
+use crate::limits::{InterruptHandle, Limits};
+
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+// Reading the clock on every instruction would dominate simple scripts, so the deadline is
+// checked once per this many steps.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+// A function being run, and the length of the scope stack when it was called.
+struct Frame {
+    prototype: Rc<Prototype>,
+    ip: usize,
+    scopes: usize,
+    call: bool
+}
+
+// An active `try`, with the state to return to when an error reaches it.
+struct Guard {
+    handler: Handler,
+    target: usize,
+    calls: usize,
+    stack: usize,
+    scopes: usize,
+    scope: ContextId,
+    errors: usize,
+    depth: usize
+}
+
+// The state to restore when an error leaves a run of the virtual machine.
+#[derive(Clone, Copy)]
+struct Base {
+    calls: usize,
+    stack: usize,
+    scopes: usize,
+    depth: usize
+}
+
+// Runs bytecode from `compiler::Compiler`. Calls do not recurse on the Rust stack, so the depth
+// limit is the only bound on recursion. Frames are contexts in the `ContextManager`, collected
+// like the interpreter's, with the scope stack, the value stack and the errors being handled as
+// the roots.
+pub struct Vm<'a> {
+    manager: &'a mut ContextManager,
+    modules: &'a mut ModuleLoader,
+    stack: Vec<Value>,
+    scopes: Vec<ContextId>,
+    calls: Vec<Frame>,
+    guards: Vec<Guard>,
+    errors: Vec<RuntimeError>,
+    bindings: Vec<Value>,
+    depth: usize,
+    max_depth: usize,
+    limits: Limits,
+    steps: u64,
+    interrupt: InterruptHandle
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(manager: &'a mut ContextManager, modules: &'a mut ModuleLoader) -> Vm<'a> {
+        Vm {
+            manager,
+            modules,
+            stack: vec![],
+            scopes: vec![],
+            calls: vec![],
+            guards: vec![],
+            errors: vec![],
+            bindings: vec![],
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            limits: Limits::new(),
+            steps: 0,
+            interrupt: InterruptHandle::new()
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // A handle another thread can use to stop this virtual machine.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    pub fn set_interrupt_handle(&mut self, interrupt: InterruptHandle) {
+        self.interrupt = interrupt;
+    }
+
+    // Runs a compiled script or module in `context_id`.
+    pub fn run(&mut self, prototype: Rc<Prototype>, context_id: ContextId) -> RuntimeResult {
+        let base = self.base();
+
+        self.calls.push(Frame { prototype, ip: 0, scopes: self.scopes.len(), call: false });
+        self.scopes.push(context_id);
+
+        self.execute(base)
+    }
+
+    // Calls `function` with arguments that are already values, as the host does through `Engine::call`.
+    pub fn call_value(&mut self, function: Value, args: Vec<Value>) -> RuntimeResult {
+        let base = self.base();
+
+        self.stack.push(function);
+        self.stack.extend(args);
+
+        if let Err(error) = self.call(base.stack, base.stack + 1, None) {
+            self.restore(base);
+
+            return Err(error);
+        }
+
+        if self.calls.len() > base.calls {
+            self.execute(base)
+        } else {
+            Ok(self.stack.pop().unwrap_or(Value::Null))
+        }
+    }
+
+    fn base(&self) -> Base {
+        Base {
+            calls: self.calls.len(),
+            stack: self.stack.len(),
+            scopes: self.scopes.len(),
+            depth: self.depth
+        }
+    }
+
+    fn restore(&mut self, base: Base) {
+        self.calls.truncate(base.calls);
+        self.stack.truncate(base.stack);
+        self.scopes.truncate(base.scopes);
+        self.depth = base.depth;
+    }
+
+    // Runs until the frame pushed after `base` returns, handling errors with the guards pushed since.
+    fn execute(&mut self, base: Base) -> RuntimeResult {
+        loop {
+            let error = match self.dispatch(base) {
+                Ok(value) => return Ok(value),
+                Err(error) => error
+            };
+
+            if let Err(error) = self.unwind(error, base) {
+                self.restore(base);
+
+                return Err(error);
+            }
+        }
+    }
+
+    // Transfers control to the innermost guard that handles `error`, or returns it if there is none.
+    fn unwind(&mut self, error: RuntimeError, base: Base) -> Result<(), RuntimeError> {
+        while self.guards.last().is_some_and(|guard| guard.calls > base.calls) {
+            let guard = self.guards.pop().unwrap();
+
+            if guard.handler == Handler::Catch && !error.kind().is_catchable() {
+                continue;
+            }
+
+            self.calls.truncate(guard.calls);
+            self.stack.truncate(guard.stack);
+            self.scopes.truncate(guard.scopes - 1);
+            self.scopes.push(guard.scope);
+            self.errors.truncate(guard.errors);
+            self.depth = guard.depth;
+
+            if let Some(frame) = self.calls.last_mut() {
+                frame.ip = guard.target;
+            }
+
+            match guard.handler {
+                Handler::Catch => self.stack.push(error.into_value()),
+                Handler::Finally => self.errors.push(error)
+            }
+
+            return Ok(());
+        }
+
+        Err(error)
+    }
+
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+
+        if let Some(fuel) = self.limits.fuel {
+            if self.steps > fuel {
+                return Err(RuntimeError::with_kind(ErrorKind::Fuel, String::from("Step budget of ") + &fuel.to_string() + " exhausted"));
+            }
+        }
+
+        if let Some(deadline) = self.limits.deadline {
+            if self.steps.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline {
+                return Err(RuntimeError::with_kind(ErrorKind::Timeout, String::from("Deadline exceeded")));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_interrupt(&self) -> Result<(), RuntimeError> {
+        if self.interrupt.is_interrupted() {
+            return Err(RuntimeError::with_kind(ErrorKind::Interrupted, String::from("Interrupted")));
+        }
+
+        Ok(())
+    }
+
+    // A string or list on top of the stack that nothing else holds is a new one, which counts
+    // against the size limit.
+    fn check_size(&mut self) -> Result<(), RuntimeError> {
+        if self.limits.max_heap_size.is_none() {
+            return Ok(());
+        }
+
+        let size = match self.top() {
+            Value::Str(string) if Rc::strong_count(string) == 1 => string.len(),
+            Value::List(items) if Rc::strong_count(items) == 1 => items.borrow().len(),
+            _ => return Ok(())
+        };
+
+        self.charge(size, 0)
+    }
+
+    // Counts `size` more of strings and lists, all on the stack but for the `pending` about to be
+    // added to one. Once the count passes the size limit, the collector runs and only the strings
+    // and lists still reachable count.
+    fn charge(&mut self, size: usize, pending: usize) -> Result<(), RuntimeError> {
+        let max_size = match self.limits.max_heap_size {
+            Some(max_size) if self.manager.charge(size, max_size) => max_size,
+            _ => return Ok(())
+        };
+
+        let (contexts, values) = self.roots();
+
+        self.manager.collect(&contexts, &values);
+
+        let size = self.manager.measure(&values, pending);
+
+        if size > max_size {
+            return Err(RuntimeError::with_kind(ErrorKind::SizeLimit, String::from("Strings and lists of total size ") + &size.to_string() + " exceed the size limit of " + &max_size.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn roots(&self) -> (Vec<ContextId>, Vec<Value>) {
+        let contexts = self.scopes.iter().copied().chain(self.guards.iter().map(|guard| guard.scope)).collect();
+
+        let values = self.stack.iter()
+            .chain(self.bindings.iter())
+            .chain(self.errors.iter().filter_map(|error| error.value()))
+            .cloned()
+            .collect();
+
+        (contexts, values)
+    }
+
+    fn collect(&mut self) {
+        let (contexts, values) = self.roots();
+
+        self.manager.collect(&contexts, &values);
+    }
+
+    // Tracked objects can fill the heap without a context being created, so making or storing one
+    // is a reason to collect of its own.
+    fn collect_if_due(&mut self) {
+        if self.manager.should_collect() {
+            self.collect();
+        }
+    }
+
+    // Everything the machine holds is rooted, so contexts can be collected whenever one is created.
+    fn create_context(&mut self, parent: ContextId, size: u32) -> Result<ContextId, RuntimeError> {
+        let at_limit = self.limits.max_contexts.is_some_and(|max_contexts| self.manager.context_count() >= max_contexts);
+
+        if self.manager.should_collect() || at_limit {
+            self.scopes.push(parent);
+            self.collect();
+            self.scopes.pop();
+        }
+
+        if let Some(max_contexts) = self.limits.max_contexts {
+            if self.manager.context_count() >= max_contexts {
+                return Err(RuntimeError::with_kind(ErrorKind::ContextLimit, String::from("Context limit of ") + &max_contexts.to_string() + " reached"));
+            }
+        }
+
+        Ok(self.manager.create_frame(Some(parent), size as usize))
+    }
+
+    fn scope(&self) -> ContextId {
+        *self.scopes.last().expect("The virtual machine has no scope")
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("The virtual machine's stack is empty")
+    }
+
+    fn top(&self) -> &Value {
+        self.stack.last().expect("The virtual machine's stack is empty")
+    }
+
+    fn load(&self, lookup: &Lookup) -> RuntimeResult {
+        match self.manager.lookup(self.scope(), lookup) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::with_kind(ErrorKind::Name, lookup.name.clone() + " is not defined"))
+        }
+    }
+
+    fn store(manager: &mut ContextManager, context_id: ContextId, target: Target, value: Value, names: &[String]) {
+        match target {
+            Target::Slot(slot) => manager.set_slot(context_id, slot as usize, value),
+            Target::Name(name) => { manager.set(context_id, &names[name as usize], value); }
+        }
+    }
+
+    // Calls the function at `callee` in the stack with the arguments from `args` up. A script
+    // function gets a frame and runs from the dispatch loop; anything else is called at once.
+    fn call(&mut self, callee: usize, args: usize, receiver: Option<Value>) -> Result<(), RuntimeError> {
+        let function = self.stack[callee].clone();
+
+        match function {
+            Value::Closure(closure) => {
+                self.check_interrupt()?;
+
+                if !self.manager.is_live(closure.context) {
+                    return Err(RuntimeError::new(String::from("Function '") + &closure.prototype.name + "' outlived the scope it was defined in"));
+                }
+
+                if self.depth >= self.max_depth {
+                    return Err(RuntimeError::with_kind(ErrorKind::Recursion, String::from("Maximum recursion depth of ") + &self.max_depth.to_string() + " exceeded"));
+                }
+
+                let prototype = closure.prototype.clone();
+                let context = self.create_context(closure.context, prototype.size)?;
+                let names = &prototype.chunk.names;
+
+                if let (Some(receiver), Some(target)) = (receiver, prototype.receiver) {
+                    Vm::store(self.manager, context, target, receiver, names);
+                }
+
+                let mut values = self.stack.split_off(args).into_iter();
+
+                for target in &prototype.targets {
+                    Vm::store(self.manager, context, *target, values.next().unwrap_or(Value::Null), names);
+                }
+
+                self.stack.truncate(callee);
+
+                self.depth += 1;
+
+                self.calls.push(Frame { prototype, ip: 0, scopes: self.scopes.len(), call: true });
+                self.scopes.push(context);
+
+                Ok(())
+            },
+            function => {
+                let values = self.stack.split_off(args);
+
+                self.stack.truncate(callee);
+
+                let value = match function {
+                    Value::NativeFunc(native) => {
+                        native.call(&values)?
+                    },
+                    Value::Struct(struct_type) => {
+                        if values.len() != struct_type.fields.len() {
+                            return Err(RuntimeError::with_kind(ErrorKind::Argument, struct_type.name.clone() + " expects " + &struct_type.fields.len().to_string() + " argument(s), got " + &values.len().to_string()));
+                        }
+
+                        let fields = struct_type.fields.iter().cloned().zip(values).collect();
+                        let instance = Value::instance(&struct_type.name, fields, struct_type.methods);
+
+                        self.manager.track(&instance);
+
+                        instance
+                    },
+                    Value::Variant(name, variant, fields) => {
+                        if values.len() != fields.len() {
+                            return Err(RuntimeError::with_kind(ErrorKind::Argument, name + "." + &variant + " expects " + &fields.len().to_string() + " argument(s), got " + &values.len().to_string()));
+                        }
+
+                        Value::Tagged(name, variant, values)
+                    },
+                    Value::Func(function) => return Err(RuntimeError::new(String::from("Function '") + &function.name + "' was defined by the interpreter and cannot be called by the virtual machine")),
+                    function => return Err(RuntimeError::with_kind(ErrorKind::Type, function.to_string() + " is not a function"))
+                };
+
+                self.stack.push(value);
+                self.check_size()?;
+                self.collect_if_due();
+
+                Ok(())
+            }
+        }
+    }
+
+    // Returns from the current frame before a tail call of the function at `callee`, so that the
+    // call takes its place. Only bodies of script functions make tail calls, and never inside a
+    // `try`. Other callees return at once, so the frame stays to receive their value.
+    fn leave_for(&mut self, callee: usize) {
+        if let Value::Closure(_) = self.stack[callee] {
+            let frame = self.calls.pop().expect("The virtual machine has no frame");
+
+            self.scopes.truncate(frame.scopes);
+            self.depth -= 1;
+        }
+    }
+
+    fn binary(&mut self, operation: fn(&Value, Value) -> RuntimeResult) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+
+        let value = operation(&left, right)?;
+
+        self.stack.push(value);
+
+        Ok(())
+    }
+
+    fn unary(&mut self, operation: fn(&Value) -> RuntimeResult) -> Result<(), RuntimeError> {
+        let value = self.pop();
+
+        self.stack.push(operation(&value)?);
+
+        Ok(())
+    }
+
+    fn dispatch(&mut self, base: Base) -> RuntimeResult {
+        loop {
+            self.step()?;
+
+            let frame = self.calls.last_mut().expect("The virtual machine has no frame");
+            let prototype = frame.prototype.clone();
+            let op = prototype.chunk.code[frame.ip];
+            let chunk = &prototype.chunk;
+
+            frame.ip += 1;
+
+            match op {
+                Op::Constant(index) => self.stack.push(chunk.constants[index as usize].clone()),
+                Op::Null => self.stack.push(Value::Null),
+                Op::Pop => { self.pop(); },
+                Op::Stash(depth) => {
+                    let value = self.pop();
+                    let index = self.stack.len() - 1 - depth as usize;
+
+                    self.stack[index] = value;
+                },
+                Op::Load(index) => {
+                    let value = self.load(&chunk.lookups[index as usize])?;
+
+                    self.stack.push(value);
+                },
+                Op::Define(target) => {
+                    let value = self.top().clone();
+                    let scope = self.scope();
+
+                    Vm::store(self.manager, scope, target, value, &chunk.names);
+                },
+                Op::Closure(index) => {
+                    let closure = Closure { prototype: chunk.functions[index as usize].clone(), context: self.scope() };
+
+                    let value = Value::Closure(Rc::new(closure));
+
+                    self.manager.track(&value);
+                    self.stack.push(value);
+                    self.collect_if_due();
+                },
+                Op::Add => {
+                    self.binary(Value::add)?;
+
+                    self.check_size()?;
+                },
+                Op::Subtract => self.binary(Value::subtract)?,
+                Op::Multiply => self.binary(Value::multiply)?,
+                Op::Divide => self.binary(Value::divide)?,
+                Op::Power => self.binary(Value::raise)?,
+                Op::Equal => self.binary(Value::equals)?,
+                Op::NotEqual => self.binary(Value::not_equals)?,
+                Op::Greater => self.binary(Value::is_greater_than)?,
+                Op::GreaterEqual => self.binary(Value::is_greater_than_or_equal_to)?,
+                Op::Less => self.binary(Value::is_less_than)?,
+                Op::LessEqual => self.binary(Value::is_less_than_or_equal_to)?,
+                Op::BitwiseAnd => self.binary(Value::bitwise_and)?,
+                Op::BitwiseOr => self.binary(Value::bitwise_or)?,
+                Op::BitwiseXOr => self.binary(Value::bitwise_xor)?,
+                Op::LeftShift => self.binary(Value::left_shift)?,
+                Op::RightShift => self.binary(Value::right_shift)?,
+                Op::And => self.binary(Value::logical_and)?,
+                Op::Or => self.binary(Value::logical_or)?,
+                Op::In => self.binary(|item, collection| collection.contains(item.clone()))?,
+                Op::Negate => self.unary(|value| value.negate())?,
+                Op::Plus => self.unary(|value| value.multiply(Value::Int(1)))?,
+                Op::BitwiseNot => self.unary(Value::bitwise_not)?,
+                Op::Not => self.unary(Value::logical_not)?,
+                Op::MakeList(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count as usize);
+
+                    self.stack.push(Value::list(values));
+                    self.check_size()?;
+                },
+                Op::MakeRange(inclusive) => {
+                    let end = self.pop();
+                    let start = self.pop();
+
+                    match (start, end) {
+                        (Value::Int(start), Value::Int(end)) => self.stack.push(Value::Range(start, end, inclusive)),
+                        (start, end) => return Err(RuntimeError::with_kind(ErrorKind::Type, String::from("Range bounds must be integers, got '") + &start.to_string() + "' and '" + &end.to_string() + "'"))
+                    }
+                },
+                Op::Index => {
+                    self.binary(Value::index)?;
+
+                    self.check_size()?;
+                },
+                Op::GetField(name) => {
+                    let value = self.pop();
+
+                    self.stack.push(get_field(&value, &chunk.names[name as usize])?);
+                },
+                Op::SetField(name) => {
+                    let target = self.pop();
+                    let value = self.pop();
+                    let field = &chunk.names[name as usize];
+
+                    if !target.set_field(field, value.clone()) {
+                        return Err(RuntimeError::with_kind(ErrorKind::Field, target.to_string() + " has no field '" + field + "'"));
+                    }
+
+                    self.stack.push(value);
+                },
+                Op::Method(name) => {
+                    let receiver = self.pop();
+                    let name = &chunk.names[name as usize];
+
+                    let (function, receiver) = match &receiver {
+                        Value::Instance(object) => match receiver.get_field(name) {
+                            Some(field) => (field, Value::Null),
+                            None => {
+                                let methods_context = object.borrow().methods;
+
+                                match self.manager.get_local(methods_context, name) {
+                                    Some(method) => (method.clone(), receiver),
+                                    None => return Err(RuntimeError::with_kind(ErrorKind::Field, receiver.to_string() + " has no method '" + name + "'"))
+                                }
+                            }
+                        },
+                        Value::List(_) => (receiver.clone(), receiver),
+                        _ => (get_field(&receiver, name)?, Value::Null)
+                    };
+
+                    self.stack.push(function);
+                    self.stack.push(receiver);
+                },
+                Op::CallMethod(name, count) | Op::TailCallMethod(name, count) => {
+                    let args = self.stack.len() - count as usize;
+                    let callee = args - 2;
+
+                    match (&self.stack[callee], &self.stack[args - 1]) {
+                        (Value::List(_), Value::List(_)) => {
+                            let name = &chunk.names[name as usize];
+
+                            // Pushing is the one way a list grows in place, so refuse it up front.
+                            if name == "push" {
+                                self.charge(1, 1)?;
+                            }
+
+                            let values = self.stack.split_off(args);
+                            let receiver = self.pop();
+
+                            self.pop();
+
+                            if name == "push" {
+                                for value in &values {
+                                    self.manager.track_store(&receiver, value);
+                                }
+                            }
+
+                            self.stack.push(receiver.call_method(name, values)?);
+                            self.collect_if_due();
+                        },
+                        (_, receiver) => {
+                            let receiver = match receiver {
+                                Value::Null => None,
+                                receiver => Some(receiver.clone())
+                            };
+
+                            if let Op::TailCallMethod(..) = op {
+                                self.leave_for(callee);
+                            }
+
+                            self.call(callee, args, receiver)?;
+                        }
+                    }
+                },
+                Op::Call(count) => {
+                    let args = self.stack.len() - count as usize;
+
+                    self.call(args - 1, args, None)?;
+                },
+                Op::TailCall(count) => {
+                    let args = self.stack.len() - count as usize;
+
+                    self.leave_for(args - 1);
+                    self.call(args - 1, args, None)?;
+                },
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.calls.pop().expect("The virtual machine has no frame");
+
+                    self.scopes.truncate(frame.scopes);
+
+                    if frame.call {
+                        self.depth -= 1;
+                    }
+
+                    if self.calls.len() == base.calls {
+                        return Ok(value);
+                    }
+
+                    self.stack.push(value);
+                },
+                Op::Jump(target) => self.jump(target),
+                Op::Loop(target) => {
+                    self.check_interrupt()?;
+
+                    self.jump(target);
+                },
+                Op::JumpIfFalse(target) => {
+                    if !self.pop().is_true() {
+                        self.jump(target);
+                    }
+                },
+                Op::JumpIfNull(target) => {
+                    if let Value::Null = self.top() {
+                        self.jump(target);
+                    }
+                },
+                Op::JumpIfNotNull(target) => {
+                    if !matches!(self.top(), Value::Null) {
+                        self.jump(target);
+                    }
+                },
+                Op::EnterScope(size) => {
+                    let context = self.create_context(self.scope(), size)?;
+
+                    self.scopes.push(context);
+                },
+                Op::ExitScope => { self.scopes.pop(); },
+                Op::SwapScope => {
+                    let len = self.scopes.len();
+
+                    self.scopes.swap(len - 1, len - 2);
+                },
+                Op::Iterate => {
+                    let snapshot = match self.pop() {
+                        range @ Value::Range(..) => range,
+                        Value::List(items) => Value::list(items.borrow().clone()),
+                        Value::Str(string) => Value::list(string.chars().map(|c| Value::Str(c.to_string().into())).collect()),
+                        value => return Err(RuntimeError::with_kind(ErrorKind::Type, value.to_string() + " is not iterable"))
+                    };
+
+                    self.stack.push(snapshot);
+                    self.stack.push(Value::Int(0));
+                },
+                Op::Next(target) => {
+                    let len = self.stack.len();
+
+                    let index = match self.stack[len - 1] {
+                        Value::Int(index) => index as i64,
+                        _ => 0
+                    };
+
+                    let item = match &self.stack[len - 2] {
+                        Value::Range(start, end, inclusive) if index < Value::range_len(*start, *end, *inclusive) => Some(Value::Int((*start as i64 + index) as i32)),
+                        Value::List(items) => items.borrow().get(index as usize).cloned(),
+                        _ => None
+                    };
+
+                    match item {
+                        Some(item) => {
+                            self.stack[len - 1] = Value::Int((index + 1) as i32);
+                            self.stack.push(item);
+                        },
+                        None => self.jump(target)
+                    }
+                },
+                Op::MakeStruct(index) => {
+                    let info = &chunk.structs[index as usize];
+
+                    let value = Value::struct_type(&info.name, info.fields.clone(), self.scope());
+
+                    self.manager.track(&value);
+                    self.stack.push(value);
+                },
+                Op::Match(arm, target) => {
+                    let mut bindings = vec![];
+
+                    if self.test(&chunk.arms[arm as usize].pattern, self.top(), &mut bindings, chunk)? {
+                        self.bindings = bindings;
+                    } else {
+                        self.jump(target);
+                    }
+                },
+                Op::Bind(arm) => {
+                    let scope = self.scope();
+                    let bindings = mem::take(&mut self.bindings);
+
+                    for (target, value) in chunk.arms[arm as usize].targets.iter().zip(bindings) {
+                        Vm::store(self.manager, scope, *target, value, &chunk.names);
+                    }
+                },
+                Op::NoMatch => return Err(RuntimeError::with_kind(ErrorKind::Match, String::from("No match arm matches '") + &self.top().to_string() + "'")),
+                Op::PushHandler(handler, target) => {
+                    self.guards.push(Guard {
+                        handler,
+                        target: target as usize,
+                        calls: self.calls.len(),
+                        stack: self.stack.len(),
+                        scopes: self.scopes.len(),
+                        scope: self.scope(),
+                        errors: self.errors.len(),
+                        depth: self.depth
+                    });
+                },
+                Op::PopHandler => { self.guards.pop(); },
+                Op::Rethrow => {
+                    if let Some(error) = self.errors.pop() {
+                        return Err(error);
+                    }
+                },
+                Op::Throw => {
+                    let value = self.pop();
+                    let msg = value.to_string();
+
+                    return Err(RuntimeError::thrown(value, msg));
+                },
+                Op::Import(index) => self.import(&chunk.imports[index as usize])?,
+                Op::Export(name) => self.modules.export(&chunk.names[name as usize]),
+                Op::Fail(message) => return Err(RuntimeError::new(chunk.constants[message as usize].to_string()))
+            }
+        }
+    }
+
+    fn jump(&mut self, target: u32) {
+        if let Some(frame) = self.calls.last_mut() {
+            frame.ip = target as usize;
+        }
+    }
+
+    fn test(&self, pattern: &Pattern, value: &Value, bindings: &mut Vec<Value>, chunk: &Chunk) -> Result<bool, RuntimeError> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding => {
+                bindings.push(value.clone());
+
+                Ok(true)
+            },
+            Pattern::Constant(_) | Pattern::Load(_) => {
+                let literal = match pattern {
+                    Pattern::Load(lookup) => self.load(&chunk.lookups[*lookup as usize])?,
+                    Pattern::Constant(constant) => constant.clone(),
+                    _ => Value::Null
+                };
+
+                Ok(match literal.equals(value.clone()) {
+                    Ok(result) => result.is_true(),
+                    Err(_) => false
+                })
+            },
+            Pattern::Variant(enum_name, variant, patterns) => {
+                match value {
+                    Value::Tagged(name, tag, values) => {
+                        if name != enum_name || tag != variant || values.len() != patterns.len() {
+                            return Ok(false);
+                        }
+
+                        for (pattern, value) in patterns.iter().zip(values.iter()) {
+                            if !self.test(pattern, value, bindings, chunk)? {
+                                return Ok(false);
+                            }
+                        }
+
+                        Ok(true)
+                    },
+                    _ => Ok(false)
+                }
+            }
+        }
+    }
+
+    fn import(&mut self, import: &ImportInfo) -> Result<(), RuntimeError> {
+        let name = &import.module;
+
+        let path = match self.modules.resolve(name) {
+            Some(path) => path,
+            None => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Cannot find module '") + name + "'"))
+        };
+
+        let module_context = self.load_module(&path)?;
+
+        let exports = match self.modules.get(&path) {
+            Some(module) => module.exports.clone(),
+            None => vec![]
+        };
+
+        let imported = match &import.names {
+            Some(names) => names.clone(),
+            None => exports.clone()
+        };
+
+        let scope = self.scope();
+
+        for import in imported {
+            if !exports.contains(&import) {
+                return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Module '") + name + "' has no export named '" + &import + "'"));
+            }
+
+            let value = match self.manager.get(module_context, &import) {
+                Some(value) => value.clone(),
+                None => Value::Null
+            };
+
+            self.manager.set(scope, &import, value);
+        }
+
+        self.stack.push(Value::Null);
+
+        Ok(())
+    }
+
+    // Compiles and runs the module at `path` in a fresh root context the first time it is imported and returns that context.
+    fn load_module(&mut self, path: &Path) -> Result<ContextId, RuntimeError> {
+        if let Some(module) = self.modules.get(path) {
+            return Ok(module.context_id);
+        }
+
+        if self.modules.is_loading(path) {
+            let chain: Vec<String> = self.modules.cycle(path).iter().map(|path| path.display().to_string()).collect();
+
+            return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Import cycle detected: ") + &chain.join(" -> ")));
+        }
+
+        let display = path.display().to_string();
+
+        let code = match fs::read_to_string(path) {
+            Ok(code) => code,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("Cannot read module '") + &display + "': " + &error.to_string()))
+        };
+
+        let tokens = match Lexer::new(&code).tokenize() {
+            Ok(tokens) => tokens,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let node = match Parser::new(tokens).parse() {
+            Ok(node) => node,
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let prelude = self.manager.prelude();
+        let mut checker = Checker::new();
+
+        checker.set_globals(self.manager, prelude);
+
+        if let Err(errors) = checker.check(&node) {
+            return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&TypeErrors::new(errors))));
+        }
+
+        let mut optimizer = Optimizer::new();
+        let mut resolver = Resolver::new();
+
+        optimizer.set_globals(shadowing(self.manager, prelude));
+        resolver.set_globals(self.manager.names(prelude));
+
+        let node = match resolver.resolve(node) {
+            Ok(node) => optimizer.optimize(node),
+            Err(error) => return Err(RuntimeError::with_kind(ErrorKind::Import, String::from("In module '") + &display + "': " + &Error::to_string(&error)))
+        };
+
+        let module_context = self.manager.create_root_context();
+
+        self.modules.begin(path);
+
+        match self.run(Compiler::new().compile(&node), module_context) {
+            Ok(_) => {
+                self.modules.end(module_context);
+
+                Ok(module_context)
+            },
+            Err(error) => {
+                self.modules.abort();
+
+                Err(error)
+            }
+        }
+    }
+}
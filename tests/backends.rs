@@ -0,0 +1,104 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::engine::Backend;
+use rust_parser::limits::Limits;
+use rust_parser::value::Value;
+
+use common::eval_both;
+
+use std::fs;
+use std::path::Path;
+
+// Runs `source` on both backends, with the corpus library on the search path, and returns the
+// result they agree on.
+fn eval(source: &str) -> String {
+    eval_both(source, |engine| engine.add_search_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/lib")))
+}
+
+#[test]
+fn backends_agree_on_the_corpus() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+
+    let mut files: Vec<_> = fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+
+    files.sort();
+
+    assert!(!files.is_empty());
+
+    for path in files {
+        let source = fs::read_to_string(&path).unwrap();
+
+        let expected = source.lines()
+            .find_map(|line| line.strip_prefix("# expect: "))
+            .unwrap_or_else(|| panic!("{} has no '# expect:' line", path.display()));
+
+        assert_eq!(eval(&source), expected, "in {}", path.display());
+    }
+}
+
+#[test]
+fn backends_agree_on_errors() {
+    assert_eq!(eval("missing"), "Runtime Error: missing is not defined");
+    assert_eq!(eval("1 - \"a\""), "Runtime Error: Operator '-' cannot be applied to '1', 'a'.");
+    assert_eq!(eval("[1, 2][3]"), "Runtime Error: Index 3 is out of range for length 2");
+    assert_eq!(eval("match (3) { 1 => 1 }"), "Runtime Error: No match arm matches '3'");
+    assert_eq!(eval("throw \"oops\""), "Uncaught Error: oops");
+}
+
+#[test]
+fn reads_before_a_definition_see_the_outer_variable() {
+    assert_eq!(eval("let x = 1; function f() { let y = x; let x = 2; [y, x] }; f()"), "[1, 2]");
+}
+
+#[test]
+fn vm_enforces_the_recursion_limit() {
+    let mut engine = Engine::new();
+
+    engine.set_backend(Backend::Vm);
+    engine.set_max_depth(50);
+
+    let error = engine.eval("function f(n) { 1 + f(n + 1) }; f(0)").unwrap_err();
+
+    assert_eq!(error.to_string(), "Runtime Error: Maximum recursion depth of 50 exceeded");
+}
+
+#[test]
+fn vm_does_not_recurse_on_the_rust_stack() {
+    let mut engine = Engine::new();
+
+    engine.set_backend(Backend::Vm);
+    engine.set_max_depth(100000);
+
+    let value = engine.eval("function down(n) { if (n == 0) { 0 } else { down(n - 1) + 1 } }; down(50000)").unwrap();
+
+    assert_eq!(value.to_string(), "50000");
+}
+
+#[test]
+fn vm_enforces_the_step_budget() {
+    let mut engine = Engine::new();
+    let mut limits = Limits::new();
+
+    limits.fuel = Some(1000);
+
+    engine.set_backend(Backend::Vm);
+    engine.set_limits(limits);
+
+    let source = "let items = []; try { while (true) { items.push(1) } } catch (error) { \"caught\" }";
+
+    assert_eq!(engine.eval(source).unwrap_err().to_string(), "Runtime Error: Step budget of 1000 exhausted");
+}
+
+#[test]
+fn engine_calls_functions_compiled_for_the_vm() {
+    let mut engine = Engine::new();
+
+    engine.set_backend(Backend::Vm);
+    engine.eval("function add(a, b) { a + b }").unwrap();
+
+    assert_eq!(engine.call("add", vec![Value::Int(2), Value::Int(3)]).unwrap().to_string(), "5");
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+
+use common::{eval_with, LOGGING};
+
+#[test]
+fn blocks_ending_in_a_semicolon_keep_their_statements_and_give_null() {
+    assert_eq!(eval_with(LOGGING, "function g() { let a = 1; f(a); }; [g(), log]"), "[null, [1]]");
+    assert_eq!(eval_with(LOGGING, "let r = if (true) { let a = 2; f(a); }; [r, log]"), "[null, [2]]");
+    assert_eq!(eval_with(LOGGING, "for (i in 0..2) { let a = i; f(a); }; log"), "[0, 1]");
+}
+
+#[test]
+fn blocks_without_a_trailing_semicolon_give_their_last_value() {
+    assert_eq!(eval_with(LOGGING, "function g() { let a = 1; f(a) }; [g(), log]"), "[1, [1]]");
+    assert_eq!(eval_with(LOGGING, "if (true) { let a = 2; f(a) }"), "2");
+}
+
+#[test]
+fn parsing_keeps_every_statement_of_a_block() {
+    let tokens = Lexer::new("if (true) { let a = 1; f(a); }").tokenize().unwrap();
+    let node = Parser::new(tokens).parse().unwrap();
+
+    assert_eq!(format!("{:?}", node), "Statements([If(VarAcc(\"true\"), Statements([VarDef(\"a\", None, Int(1)), FuncCall(VarAcc(\"f\"), [VarAcc(\"a\")])], false), None)], true)");
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use rust_parser::Engine;
+
+use common::{eval, eval_in};
+
+#[test]
+fn annotations_are_checked_against_values() {
+    assert_eq!(eval("let x: int = \"a\"; x"), "Type Error: Cannot assign a value of type 'str' to 'x' of type 'int'");
+    assert_eq!(eval("function f(x: int): str { x }; 1"), "Type Error: Function 'f' is declared to return 'str' but returns 'int'");
+    assert_eq!(eval("function f(x: int) { x }; f(\"a\")"), "Type Error: Argument 1 of 'f' expects 'int', got 'str'");
+    assert_eq!(eval("let x: number = 1.5; x"), "1.5");
+}
+
+#[test]
+fn operators_are_checked_against_their_operands() {
+    assert_eq!(eval("let s: str = \"a\"; s - 1"), "Type Error: Operator '-' cannot be applied to 'str' and 'int'");
+    assert_eq!(eval("let s: str = \"a\"; -s"), "Type Error: Operator '-' cannot be applied to 'str'");
+    assert_eq!(eval("let f: float = 1.5; ~f"), "Type Error: Operator '~' cannot be applied to 'float'");
+    assert_eq!(eval("let n: int = 1; n == \"a\""), "Type Error: Cannot compare 'int' with 'str'");
+    assert_eq!(eval("let n: int = 1; let m = n + 1; m - \"a\""), "Type Error: Operator '-' cannot be applied to 'int' and 'str'");
+}
+
+#[test]
+fn indexing_iterating_and_calling_are_checked() {
+    assert_eq!(eval("let n: int = 5; n[0]"), "Type Error: Type 'int' cannot be indexed");
+    assert_eq!(eval("let n: int = 5; for (i in n) { i }"), "Type Error: Type 'int' is not iterable");
+    assert_eq!(eval("let n: int = 1; n()"), "Type Error: 'n' of type 'int' is not callable");
+    assert_eq!(eval("let s: str = \"a\"; s.foo"), "Type Error: Type 'str' has no field 'foo'");
+    assert_eq!(eval("let f: float = 2.5; 1..f"), "Type Error: Range bounds must be integers, got 'float'");
+}
+
+#[test]
+fn unknown_types_are_not_reported() {
+    assert_eq!(eval("function f(x) { x }; f(\"a\") + 1"), "a1");
+}
+
+#[test]
+fn mismatches_between_literals_are_left_to_run_time() {
+    assert_eq!(eval("let x = \"s\"; if (type(x) == \"int\") { x - 1 } else { 0 }"), "0");
+    assert_eq!(eval("let x = \"s\"; try { x - 1 } catch (e) { \"caught\" }"), "caught");
+    assert_eq!(eval("\"a\" - 1"), "Runtime Error: Operator '-' cannot be applied to 'a', '1'.");
+}
+
+#[test]
+fn functions_the_host_registers_are_known() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("len", |text: String| text.to_uppercase());
+
+    assert_eq!(eval_in(&mut engine, "let s: str = len(\"a\"); s"), "A");
+}
+
+#[test]
+fn every_error_is_reported_before_anything_runs() {
+    let mut engine = Engine::new();
+
+    assert_eq!(eval_in(&mut engine, "let ran = 1; let x: int = \"a\"; let y: str = 1; 0"), "Type Error: Cannot assign a value of type 'str' to 'x' of type 'int'\nType Error: Cannot assign a value of type 'int' to 'y' of type 'str'");
+    assert!(engine.get_global("ran").is_none());
+}
@@ -0,0 +1,45 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use rust_parser::Engine;
+use rust_parser::engine::Backend;
+
+// Runs `source` in `engine` and returns its value or its error as text.
+pub fn eval_in(engine: &mut Engine, source: &str) -> String {
+    match engine.eval(source) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.to_string()
+    }
+}
+
+// Runs `source` on both backends, each in a fresh engine, and returns the result they agree on.
+pub fn eval(source: &str) -> String {
+    eval_both(source, |_| {})
+}
+
+// Runs `fixture`, definitions several tests share, and then `source` on both backends.
+pub fn eval_with(fixture: &str, source: &str) -> String {
+    eval(&(String::from(fixture) + source))
+}
+
+// Defines `log` and `f`, which appends its argument to `log` and returns it.
+pub const LOGGING: &str = "let log = []; function f(x) { log.push(x); x }; ";
+
+pub const SHAPE: &str = "enum Shape { Circle(r), Rect(w, h), Empty }; ";
+
+// Runs `source` on both backends, each in a fresh engine set up by `configure`, and returns the
+// result they agree on.
+pub fn eval_both(source: &str, configure: impl Fn(&mut Engine)) -> String {
+    let results: Vec<String> = [Backend::Interpreter, Backend::Vm].iter().map(|backend| {
+        let mut engine = Engine::new();
+
+        engine.set_backend(*backend);
+        configure(&mut engine);
+
+        eval_in(&mut engine, source)
+    }).collect();
+
+    assert_eq!(results[0], results[1], "the backends disagree on:\n{}", source);
+
+    results[0].clone()
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::error::{Error, RuntimeError};
+
+use common::eval_in;
+
+#[test]
+fn integers_that_fit_in_an_int_convert_to_ints() {
+    let mut engine = Engine::new();
+
+    engine.set_global("small", 7u64).unwrap();
+    engine.set_global("negative", -2_147_483_648i64).unwrap();
+    engine.register_fn("big", || 2_147_483_647usize);
+
+    assert_eq!(eval_in(&mut engine, "[small, negative, big()]"), "[7, -2147483648, 2147483647]");
+}
+
+#[test]
+fn integers_beyond_i32_are_out_of_range() {
+    let mut engine = Engine::new();
+
+    assert_eq!(engine.set_global("large", 3_000_000_000u64).unwrap_err().to_string(), "Runtime Error: 3000000000 is out of range for int");
+    assert_eq!(engine.set_global("items", vec![1, -3_000_000_001i64]).unwrap_err().to_string(), "Runtime Error: -3000000001 is out of range for int");
+    assert!(engine.get_global("large").is_none());
+
+    engine.register_fn("big", || usize::MAX);
+    engine.register_fn("pair", || (1, 2_147_483_648u32));
+    engine.register_fn("checked", |n: i64| if n < 0 { Err(RuntimeError::new(String::from("negative"))) } else { Ok(n * n) });
+
+    assert_eq!(eval_in(&mut engine, "big()"), "Runtime Error: Result of 'big': 18446744073709551615 is out of range for int");
+    assert_eq!(eval_in(&mut engine, "pair()"), "Runtime Error: Result of 'pair': 2147483648 is out of range for int");
+    assert_eq!(eval_in(&mut engine, "[checked(3), checked(-1)]"), "Runtime Error: negative");
+    assert_eq!(eval_in(&mut engine, "checked(50000)"), "Runtime Error: Result of 'checked': 2500000000 is out of range for int");
+    assert_eq!(eval_in(&mut engine, "try { big() } catch (e) { e.kind }"), "TypeError");
+}
+
+#[test]
+fn arguments_of_the_wrong_type_are_type_errors() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("shout", |text: String| text.to_uppercase());
+    engine.register_fn("flag", |on: bool| !on);
+    engine.register_fn("total", |items: Vec<i32>| items.iter().sum::<i32>());
+
+    assert_eq!(eval_in(&mut engine, "[shout(\"a\"), flag(true), total([1, 2])]"), "[A, false, 3]");
+    assert_eq!(eval_in(&mut engine, "shout(1)"), "Runtime Error: Argument 1 of 'shout': Expected 'str', got 'int'");
+    assert_eq!(eval_in(&mut engine, "flag(1)"), "Runtime Error: Argument 1 of 'flag': Expected 'bool', got 'int'");
+    assert_eq!(eval_in(&mut engine, "total([1, \"2\"])"), "Runtime Error: Argument 1 of 'total': Expected 'int', got 'str'");
+    assert_eq!(eval_in(&mut engine, "try { shout(1) } catch (e) { e.kind }"), "ArgumentError");
+}
+
+#[test]
+fn integers_that_do_not_fit_the_parameter_type_are_out_of_range() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("byte", |n: u8| n);
+    engine.register_fn("index", |n: usize| n);
+
+    assert_eq!(eval_in(&mut engine, "[byte(255), index(3)]"), "[255, 3]");
+    assert_eq!(eval_in(&mut engine, "byte(300)"), "Runtime Error: Argument 1 of 'byte': 300 is out of range for u8");
+    assert_eq!(eval_in(&mut engine, "index(-1)"), "Runtime Error: Argument 1 of 'index': -1 is out of range for usize");
+}
+
+#[test]
+fn tuples_need_lists_of_their_length() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("swap", |pair: (i32, String)| (pair.1, pair.0));
+
+    assert_eq!(eval_in(&mut engine, "swap([1, \"a\"])"), "[a, 1]");
+    assert_eq!(eval_in(&mut engine, "swap([1])"), "Runtime Error: Argument 1 of 'swap': Expected a list of 2 items, got 1");
+    assert_eq!(eval_in(&mut engine, "swap([\"a\", 1])"), "Runtime Error: Argument 1 of 'swap': Expected 'int', got 'str'");
+    assert_eq!(eval_in(&mut engine, "swap(1)"), "Runtime Error: Argument 1 of 'swap': Expected 'list', got 'int'");
+}
+
+#[test]
+fn optional_parameters_accept_null() {
+    let mut engine = Engine::new();
+
+    engine.register_fn("or_zero", |n: Option<i32>| n.unwrap_or(0));
+
+    assert_eq!(eval_in(&mut engine, "[or_zero(null), or_zero(4)]"), "[0, 4]");
+    assert_eq!(eval_in(&mut engine, "or_zero(\"4\")"), "Runtime Error: Argument 1 of 'or_zero': Expected 'int', got 'str'");
+}
@@ -0,0 +1,100 @@
+use rust_parser::dump::{dump_tokens, dump_node, Format};
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+use rust_parser::node::{Node, NodeSpans};
+
+fn tokens(source: &str, format: Format) -> String {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize().unwrap();
+
+    dump_tokens(&tokens, lexer.spans(), format)
+}
+
+fn ast(source: &str, format: Format) -> String {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize().unwrap();
+
+    let mut parser = Parser::with_spans(tokens, lexer.spans().to_vec());
+    let node = parser.parse().unwrap();
+
+    dump_node(&node, &NodeSpans::new(&node, parser.spans()), format)
+}
+
+// Parses without spans, so every span in the dump is null.
+fn bare_ast(source: &str, format: Format) -> String {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let node = Parser::new(tokens).parse().unwrap();
+
+    dump_node(&node, &NodeSpans::new(&node, &[]), format)
+}
+
+#[test]
+fn format_names_are_parsed() {
+    assert_eq!(Format::from_name("json"), Some(Format::Json));
+    assert_eq!(Format::from_name("sexp"), Some(Format::SExpr));
+    assert_eq!(Format::from_name("xml"), None);
+}
+
+#[test]
+fn tokens_are_dumped_with_their_spans() {
+    assert_eq!(tokens("x+1", Format::Json), String::from("[")
+        + "{\"type\":\"Identifier\",\"span\":{\"start\":{\"line\":1,\"column\":1},\"end\":{\"line\":1,\"column\":2}},\"value\":\"x\"},"
+        + "{\"type\":\"Plus\",\"span\":{\"start\":{\"line\":1,\"column\":2},\"end\":{\"line\":1,\"column\":3}}},"
+        + "{\"type\":\"Int\",\"span\":{\"start\":{\"line\":1,\"column\":3},\"end\":{\"line\":1,\"column\":4}},\"value\":1},"
+        + "{\"type\":\"EOF\",\"span\":{\"start\":{\"line\":1,\"column\":4},\"end\":{\"line\":1,\"column\":4}}}]");
+
+    assert_eq!(tokens("x+1", Format::SExpr), String::from("(")
+        + "(Identifier :span (:start (:line 1 :column 1) :end (:line 1 :column 2)) :value \"x\") "
+        + "(Plus :span (:start (:line 1 :column 2) :end (:line 1 :column 3))) "
+        + "(Int :span (:start (:line 1 :column 3) :end (:line 1 :column 4)) :value 1) "
+        + "(EOF :span (:start (:line 1 :column 4) :end (:line 1 :column 4))))");
+}
+
+#[test]
+fn the_tree_is_dumped_with_its_spans() {
+    assert_eq!(ast("-2", Format::Json), String::from("")
+        + "{\"type\":\"Statements\",\"span\":{\"start\":{\"line\":1,\"column\":1},\"end\":{\"line\":1,\"column\":3}},\"statements\":["
+        + "{\"type\":\"UnaryOp\",\"span\":{\"start\":{\"line\":1,\"column\":1},\"end\":{\"line\":1,\"column\":3}},\"op\":\"-\",\"operand\":"
+        + "{\"type\":\"Int\",\"span\":{\"start\":{\"line\":1,\"column\":2},\"end\":{\"line\":1,\"column\":3}},\"value\":2}}],"
+        + "\"returns_last\":true}");
+
+    assert_eq!(ast("-2", Format::SExpr), String::from("")
+        + "(Statements :span (:start (:line 1 :column 1) :end (:line 1 :column 3)) :statements ("
+        + "(UnaryOp :span (:start (:line 1 :column 1) :end (:line 1 :column 3)) :op \"-\" :operand "
+        + "(Int :span (:start (:line 1 :column 2) :end (:line 1 :column 3)) :value 2))) :returns_last true)");
+}
+
+#[test]
+fn absent_spans_and_optional_values_are_null() {
+    assert_eq!(bare_ast("let x = a?.b", Format::Json), String::from("")
+        + "{\"type\":\"Statements\",\"span\":null,\"statements\":["
+        + "{\"type\":\"VarDef\",\"span\":null,\"name\":\"x\",\"annotation\":null,\"value\":"
+        + "{\"type\":\"OptionalFieldAcc\",\"span\":null,\"object\":{\"type\":\"VarAcc\",\"span\":null,\"name\":\"a\"},\"field\":\"b\"}}],"
+        + "\"returns_last\":true}");
+
+    assert_eq!(bare_ast("let x = a?.b", Format::SExpr), String::from("")
+        + "(Statements :span nil :statements ("
+        + "(VarDef :span nil :name \"x\" :annotation nil :value "
+        + "(OptionalFieldAcc :span nil :object (VarAcc :span nil :name \"a\") :field \"b\"))) :returns_last true)");
+}
+
+#[test]
+fn patterns_and_nested_objects_are_dumped() {
+    assert_eq!(bare_ast("match (a) { E.V(b, _) => 1 }", Format::SExpr), String::from("")
+        + "(Statements :span nil :statements ((Match :span nil :subject (VarAcc :span nil :name \"a\") :arms ("
+        + "(:pattern (Variant :enum \"E\" :variant \"V\" :patterns ((Binding :name \"b\") (Wildcard))) :body (Int :span nil :value 1)))))"
+        + " :returns_last true)");
+}
+
+#[test]
+fn floats_json_cannot_represent_are_written_as_strings() {
+    let huge = "9".repeat(40) + ".0";
+
+    assert_eq!(bare_ast(&huge, Format::Json), "{\"type\":\"Statements\",\"span\":null,\"statements\":[{\"type\":\"Float\",\"span\":null,\"value\":\"Infinity\"}],\"returns_last\":true}");
+
+    for (value, text) in [(f32::NEG_INFINITY, "\"-Infinity\""), (f32::NAN, "\"NaN\""), (1.5, "1.5")] {
+        let node = Node::Float(value);
+
+        assert_eq!(dump_node(&node, &NodeSpans::new(&node, &[]), Format::Json), String::from("{\"type\":\"Float\",\"span\":null,\"value\":") + text + "}");
+    }
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::path::Path;
+
+use rust_parser::Engine;
+use rust_parser::engine::Backend;
+use rust_parser::native::Arity;
+use rust_parser::value::Value;
+use rust_parser::error::Error;
+
+use common::eval_in;
+
+fn engines() -> Vec<Engine> {
+    [Backend::Interpreter, Backend::Vm].iter().map(|backend| {
+        let mut engine = Engine::new();
+
+        engine.set_backend(*backend);
+
+        engine
+    }).collect()
+}
+
+#[test]
+fn definitions_persist_between_evals() {
+    for mut engine in engines() {
+        assert_eq!(eval_in(&mut engine, "let x = 2; function double(n) { n * 2 }"), "double(n)");
+        assert_eq!(eval_in(&mut engine, "double(x) + 1"), "5");
+        assert_eq!(eval_in(&mut engine, "let x = 10; x"), "10");
+        assert_eq!(eval_in(&mut engine, "double(x)"), "20");
+    }
+}
+
+#[test]
+fn globals_are_shared_with_the_host() {
+    for mut engine in engines() {
+        engine.set_global("limit", 3).unwrap();
+        engine.set_global("name", "ada").unwrap();
+        engine.set_global("items", vec![1, 2]).unwrap();
+
+        assert_eq!(eval_in(&mut engine, "[limit + 1, name, len(items)]"), "[4, ada, 2]");
+        assert_eq!(eval_in(&mut engine, "let total = limit * 2"), "6");
+
+        assert_eq!(engine.get_global("total").map(|value| value.to_string()), Some(String::from("6")));
+        assert!(engine.get_global("missing").is_none());
+    }
+}
+
+#[test]
+fn script_functions_can_be_called_from_the_host() {
+    for mut engine in engines() {
+        eval_in(&mut engine, "let x = 1; function add(a, b) { a + b }");
+
+        assert_eq!(engine.call("add", vec![Value::Int(2), Value::Int(3)]).unwrap().to_string(), "5");
+        assert_eq!(engine.call("missing", vec![]).unwrap_err().to_string(), "Runtime Error: missing is not defined");
+        assert_eq!(engine.call("add", vec![Value::Str("a".into()), Value::Int(1)]).unwrap().to_string(), "a1");
+        assert_eq!(engine.call("x", vec![]).unwrap_err().to_string(), "Runtime Error: 1 is not a function");
+    }
+}
+
+#[test]
+fn host_functions_can_be_called_from_scripts() {
+    for mut engine in engines() {
+        engine.register("sum", Arity::Variadic, |args| {
+            Ok(Value::Int(args.iter().map(|arg| if let Value::Int(value) = arg { *value } else { 0 }).sum()))
+        });
+        engine.register_fn("greet", |name: String| String::from("hello ") + &name);
+
+        assert_eq!(eval_in(&mut engine, "[sum(1, 2, 3), greet(\"bob\")]"), "[6, hello bob]");
+        assert_eq!(engine.call("greet", vec![Value::Str("eve".into())]).unwrap().to_string(), "hello eve");
+    }
+}
+
+#[test]
+fn errors_are_reported_by_stage() {
+    for mut engine in engines() {
+        assert_eq!(eval_in(&mut engine, "\"open"), "Lex Error: Expected '\"'");
+        assert_eq!(eval_in(&mut engine, "let = 1"), "Syntax Error: Expected identifier");
+        assert_eq!(eval_in(&mut engine, "missing"), "Runtime Error: missing is not defined");
+        assert_eq!(eval_in(&mut engine, "1"), "1");
+    }
+}
+
+#[test]
+fn a_missing_file_cannot_be_read() {
+    let mut engine = Engine::new();
+    let error = engine.eval_file(Path::new("no/such/file.txt")).unwrap_err().to_string();
+
+    assert!(error.starts_with("Runtime Error: Cannot read 'no/such/file.txt': "), "{}", error);
+}
+
+#[test]
+fn files_are_evaluated_in_the_global_context() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/arithmetic.txt");
+
+    for mut engine in engines() {
+        assert!(engine.eval_file(&path).is_ok());
+        assert_eq!(eval_in(&mut engine, "1"), "1");
+    }
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{eval_with, SHAPE};
+
+#[test]
+fn variants_build_tagged_values() {
+    assert_eq!(eval_with(SHAPE, "[Shape.Circle(1), Shape.Empty, Shape.Rect]"), "[Shape.Circle(1), Shape.Empty, <variant Shape.Rect(w, h)>]");
+    assert_eq!(eval_with(SHAPE, "[Shape.Circle(1) == Shape.Circle(1), Shape.Circle(1) == Shape.Circle(2), Shape.Empty == Shape.Empty]"), "[true, false, true]");
+}
+
+#[test]
+fn patterns_destructure_nested_variants() {
+    assert_eq!(eval_with(SHAPE, "match (Shape.Rect(Shape.Empty, 2)) { Shape.Rect(Shape.Circle(r), h) => r, Shape.Rect(Shape.Empty, h) => h * 10 }"), "20");
+}
+
+#[test]
+fn a_value_no_arm_matches_is_a_match_error() {
+    assert_eq!(eval_with(SHAPE, "match (Shape.Empty) { Shape.Circle(r) => r }"), "Runtime Error: No match arm matches 'Shape.Empty'");
+    assert_eq!(eval_with(SHAPE, "try { match (3) { 1 => 1, 2 => 2 } } catch (e) { e.kind }"), "MatchError");
+}
+
+#[test]
+fn variants_check_their_arguments_and_names() {
+    assert_eq!(eval_with(SHAPE, "Shape.Circle(1, 2)"), "Runtime Error: Shape.Circle expects 1 argument(s), got 2");
+    assert_eq!(eval_with(SHAPE, "Shape.Square"), "Type Error: Enum 'Shape' has no variant 'Square'");
+}
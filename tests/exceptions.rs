@@ -0,0 +1,35 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn catch_runs_before_finally() {
+    let source = "let log = []; function f() { try { log.push(1); throw \"x\"; log.push(2) } catch (e) { log.push(3); e } finally { log.push(4) } }; [f(), log]";
+
+    assert_eq!(eval(source), "[x, [1, 3, 4]]");
+}
+
+#[test]
+fn finally_runs_before_an_outer_catch() {
+    assert_eq!(eval("let log = []; try { try { throw 1 } finally { log.push(\"inner\") } } catch (e) { log.push(e) }; log"), "[inner, 1]");
+    assert_eq!(eval("let log = []; try { try { throw \"a\" } catch (e) { throw e + \"b\" } finally { log.push(1) } } catch (e) { [e, log] }"), "[ab, [1]]");
+}
+
+#[test]
+fn finally_does_not_change_the_value() {
+    assert_eq!(eval("let log = []; let r = try { 1 } finally { log.push(2); 3 }; [r, log]"), "[1, [2]]");
+}
+
+#[test]
+fn an_error_thrown_in_finally_replaces_the_first() {
+    assert_eq!(eval("try { throw 1 } finally { throw 2 }"), "Uncaught Error: 2");
+    assert_eq!(eval("try { throw 1 } catch (e) { throw 2 } finally { 3 }"), "Uncaught Error: 2");
+}
+
+#[test]
+fn thrown_values_and_runtime_errors_can_be_caught() {
+    assert_eq!(eval("try { throw [1, 2] } catch (e) { e }"), "[1, 2]");
+    assert_eq!(eval("try { [1][5] } catch (e) { [e.kind, e.message] }"), "[IndexError, Index 5 is out of range for length 1]");
+    assert_eq!(eval("try { while (true) { throw \"out\" } } catch (e) { e }"), "out");
+    assert_eq!(eval("throw \"boom\""), "Uncaught Error: boom");
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use rust_parser::formatter::{format, WIDTH};
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+
+fn parse(source: &str) -> String {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+
+    format!("{:?}", Parser::new(tokens).parse().unwrap())
+}
+
+fn corpus() -> Vec<(String, String)> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+
+    let mut files: Vec<_> = fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+
+    files.sort();
+
+    files.into_iter().map(|path| (path.display().to_string(), fs::read_to_string(&path).unwrap())).collect()
+}
+
+#[test]
+fn formatting_keeps_the_meaning_of_the_corpus() {
+    for (path, source) in corpus() {
+        let formatted = format(&source).unwrap();
+
+        assert_eq!(parse(&formatted), parse(&source), "in {}", path);
+    }
+}
+
+#[test]
+fn formatting_is_idempotent_on_the_corpus() {
+    for (path, source) in corpus() {
+        let formatted = format(&source).unwrap();
+
+        assert_eq!(format(&formatted).unwrap(), formatted, "in {}", path);
+    }
+}
+
+#[test]
+fn formatting_normalizes_spacing_and_indentation() {
+    assert_eq!(format("let x=1+2*3").unwrap(), "let x = 1 + 2 * 3\n");
+    assert_eq!(format("if(a){b}else{c}").unwrap(), "if (a) {\n    b\n} else {\n    c\n}\n");
+    assert_eq!(format("function f(a,b){a+b}").unwrap(), "function f(a, b) {\n    a + b\n}\n");
+}
+
+#[test]
+fn formatting_keeps_only_the_parentheses_precedence_requires() {
+    assert_eq!(format("(1 + 2) * 3").unwrap(), "(1 + 2) * 3\n");
+    assert_eq!(format("1 + (2 * 3)").unwrap(), "1 + 2 * 3\n");
+    assert_eq!(format("1 - (2 - 3)").unwrap(), "1 - (2 - 3)\n");
+}
+
+#[test]
+fn formatting_keeps_comments() {
+    assert_eq!(format("# top\nlet a = 1;   # trailing\nlet b = 2").unwrap(), "# top\nlet a = 1;  # trailing\nlet b = 2\n");
+}
+
+#[test]
+fn long_lists_are_broken_one_item_per_line() {
+    let items: Vec<String> = (0..30).map(|i| (i * 1000).to_string()).collect();
+    let formatted = format(&(String::from("[") + &items.join(", ") + "]")).unwrap();
+
+    assert_eq!(formatted, String::from("[\n    ") + &items.join(",\n    ") + "\n]\n");
+    assert_eq!(format(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn lists_are_broken_when_the_text_before_them_pushes_them_past_the_width() {
+    let items: Vec<String> = (0..10).map(|i| (i * 100000).to_string()).collect();
+    let list = String::from("[") + &items.join(", ") + "]";
+    let source = String::from("let numbers_counted_in_hundreds_of_thousands = ") + &list;
+
+    assert!(list.len() <= WIDTH && source.len() > WIDTH);
+
+    let formatted = format(&source).unwrap();
+
+    assert_eq!(formatted, String::from("let numbers_counted_in_hundreds_of_thousands = [\n    ") + &items.join(",\n    ") + "\n]\n");
+    assert_eq!(parse(&formatted), parse(&source));
+    assert_eq!(format(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn source_that_does_not_parse_is_an_error() {
+    assert!(format("let = 1").is_err());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn lists_are_shared_between_variables() {
+    assert_eq!(eval("let a = [1]; let b = a; b.push(2); a"), "[1, 2]");
+}
+
+#[test]
+fn nested_lists_are_shared_not_copied() {
+    assert_eq!(eval("let inner = [1]; let outer = [inner, inner]; inner.push(2); outer"), "[[1, 2], [1, 2]]");
+}
+
+#[test]
+fn instances_are_shared_between_variables() {
+    assert_eq!(eval("struct P { x, y }; let p = P(1, 2); let q = p; q.x = 10; p"), "P { x: 10, y: 2 }");
+}
+
+#[test]
+fn rebinding_a_variable_does_not_affect_copies() {
+    assert_eq!(eval("let a = [1]; let b = a; let a = [2]; b"), "[1]");
+}
+
+#[test]
+fn closures_share_the_lists_they_capture() {
+    let source = "function counter() { let items = []; function add(v) { items.push(v); items }; add }; let add = counter(); add(1); add(2)";
+
+    assert_eq!(eval(source), "[1, 2]");
+}
+
+#[test]
+fn pop_removes_the_last_item() {
+    assert_eq!(eval("let a = [1, 2]; [a.pop(), a]"), "[2, [1]]");
+    assert_eq!(eval("[].pop()"), "Runtime Error: Cannot pop from an empty list");
+}
+
+#[test]
+fn values_that_contain_themselves_can_be_printed() {
+    assert_eq!(eval("let l = []; l.push(l); l"), "[[...]]");
+    assert_eq!(eval("let l = [1]; l.push([l, l]); l"), "[1, [[...], [...]]]");
+    assert_eq!(eval("struct N { next }; let a = N(null); a.next = a; a"), "N { next: N {...} }");
+}
+
+#[test]
+fn instances_that_contain_themselves_can_be_compared() {
+    assert_eq!(eval("struct N { next }; let a = N(null); a.next = a; let b = N(null); b.next = b; [a == a, a == b]"), "[true, true]");
+    assert_eq!(eval("struct N { v, next }; let a = N(1, null); a.next = N(2, a); let c = N(1, null); c.next = N(3, c); a == c"), "false");
+}
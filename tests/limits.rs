@@ -0,0 +1,211 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::engine;
+use rust_parser::limits::Limits;
+
+use common::{eval, eval_both, eval_in};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The default depth limit is sized for the 8 MiB main thread, while test threads get 2 MiB.
+fn eval_on_main_sized_stack(source: &str) -> String {
+    engine::run_with_stack_size(8 * 1024 * 1024, || eval_in(&mut Engine::new(), source))
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    assert_eq!(eval_on_main_sized_stack("function f(n) { 1 + f(n + 1) }; f(0)"), "Runtime Error: Maximum recursion depth of 256 exceeded");
+}
+
+#[test]
+fn nested_tail_positions_do_not_deepen_the_stack() {
+    let source = "function f(n) { if (n > 0) { if (true) { 1 + f(n - 1) } else 0 } else 0 }; f(100000)";
+
+    assert_eq!(eval_on_main_sized_stack(source), "Runtime Error: Maximum recursion depth of 256 exceeded");
+}
+
+#[test]
+fn nesting_within_calls_is_limited_before_the_stack_overflows() {
+    let source = "function f(n) { if (n > 0) { let l = [n]; for (i in l) { try { if (true) { if (true) { if (true) { 1 + f(n - 1) } else 0 } else 0 } else 0 } finally { l } } } else 0 }; f(100000)";
+
+    let result = engine::run_with_stack_size(8 * 1024 * 1024, || {
+        let mut engine = Engine::new();
+
+        engine.set_max_depth(100000);
+
+        eval_in(&mut engine, source)
+    });
+
+    assert_eq!(result, "Runtime Error: Maximum nesting depth exceeded");
+}
+
+#[test]
+fn both_backends_reach_the_default_depth_limit() {
+    let source = "function f(n) { if (n == 0) { 0 } else { let r = f(n - 1); r + 1 } }; [f(255), try { f(256) } catch (e) { e.kind }]";
+
+    let result = engine::run_with_stack_size(8 * 1024 * 1024, || eval(source));
+
+    assert_eq!(result, "[255, RecursionError]");
+}
+
+#[test]
+fn recursion_errors_can_be_caught() {
+    let source = "function f(n) { 1 + f(n + 1) }; let caught = try { f(0) } catch (e) { e.kind }; function g(n) { if (n == 0) 0 else 1 + g(n - 1) }; [caught, g(50)]";
+
+    assert_eq!(eval_on_main_sized_stack(source), "[RecursionError, 50]");
+}
+
+#[test]
+fn the_depth_limit_is_configurable() {
+    let mut engine = Engine::new();
+
+    engine.set_max_depth(10);
+
+    assert_eq!(eval_in(&mut engine, "function f(n) { if (n == 0) 0 else 1 + f(n - 1) }; f(9)"), "9");
+    assert_eq!(eval_in(&mut engine, "f(10)"), "Runtime Error: Maximum recursion depth of 10 exceeded");
+}
+
+#[test]
+fn deep_recursion_runs_on_a_larger_stack() {
+    let result = engine::run_with_stack_size(512 * 1024 * 1024, || {
+        let mut engine = Engine::new();
+
+        engine.set_max_depth(20000);
+
+        eval_in(&mut engine, "function f(n) { if (n == 0) 0 else 1 + f(n - 1) }; f(10000)")
+    });
+
+    assert_eq!(result, "10000");
+}
+
+#[test]
+fn integer_overflow_is_an_error_rather_than_a_crash() {
+    assert_eq!(eval("let a = 2147483647; a + 1"), "Runtime Error: Operator '+' overflows on '2147483647', '1'.");
+    assert_eq!(eval("let a = -2147483647; a - 2"), "Runtime Error: Operator '-' overflows on '-2147483647', '2'.");
+    assert_eq!(eval("let a = 65536; a * a"), "Runtime Error: Operator '*' overflows on '65536', '65536'.");
+    assert_eq!(eval("let a = 2; a ^ 31"), "Runtime Error: Operator '^' overflows on '2', '31'.");
+    assert_eq!(eval("let a = 1; [a << 31, a >> 31]"), "[-2147483648, 0]");
+    assert_eq!(eval("let a = 1; a << 32"), "Runtime Error: Operator '<<' overflows on '1', '32'.");
+    assert_eq!(eval("let a = 1; a >> -1"), "Runtime Error: Operator '>>' overflows on '1', '-1'.");
+    assert_eq!(eval("let a = -2147483647 - 1; -a"), "Runtime Error: Operator '-' overflows on '-2147483648'.");
+    assert_eq!(eval("try { 2147483647 + 1 } catch (e) { e.kind }"), "RuntimeError");
+    assert_eq!(eval("2147483648"), "Lex Error: Integer '2147483648' does not fit in an int");
+}
+
+#[test]
+fn the_step_budget_stops_infinite_loops() {
+    let mut engine = Engine::new();
+
+    engine.set_limits(Limits { fuel: Some(10000), ..Limits::new() });
+
+    assert_eq!(eval_in(&mut engine, "while (true) {}"), "Runtime Error: Step budget of 10000 exhausted");
+    assert_eq!(eval_in(&mut engine, "let items = []; for (i in 0..10) { items.push(i) }; len(items)"), "10");
+}
+
+#[test]
+fn running_out_of_steps_cannot_be_caught() {
+    let mut engine = Engine::new();
+
+    engine.set_limits(Limits { fuel: Some(10000), ..Limits::new() });
+
+    assert_eq!(eval_in(&mut engine, "while (true) { try { while (true) {} } catch (e) {} }"), "Runtime Error: Step budget of 10000 exhausted");
+}
+
+#[test]
+fn the_deadline_stops_long_running_scripts() {
+    let mut engine = Engine::new();
+
+    engine.set_limits(Limits { deadline: Some(Instant::now() + Duration::from_millis(50)), ..Limits::new() });
+
+    let source = "while (true) { try { while (true) {} } catch (e) { e } }";
+
+    assert_eq!(eval_in(&mut engine, source), "Runtime Error: Deadline exceeded");
+}
+
+#[test]
+fn the_context_limit_counts_live_contexts() {
+    let mut engine = Engine::new();
+
+    engine.set_limits(Limits { max_contexts: Some(64), ..Limits::new() });
+
+    assert_eq!(eval_in(&mut engine, "let items = []; for (i in 0..1000) { if (i > 0) { items.push(i) } }; len(items)"), "999");
+    assert_eq!(eval_in(&mut engine, "function f(n) { if (n == 0) 0 else 1 + f(n - 1) }; f(100)"), "Runtime Error: Context limit of 64 reached");
+    assert_eq!(eval_in(&mut engine, "let kind = try { f(100) } catch (e) { e.kind }; [kind, f(10)]"), "[ContextLimitError, 10]");
+}
+
+#[test]
+fn the_size_limit_applies_to_strings_and_lists() {
+    let mut engine = Engine::new();
+
+    engine.set_limits(Limits { max_heap_size: Some(100), ..Limits::new() });
+
+    assert_eq!(eval_in(&mut engine, "function grow(s) { grow(s + s) }; try { grow(\"ab\") } catch (e) { e.kind }"), "SizeLimitError");
+    assert_eq!(eval_in(&mut engine, "let items = []; for (i in 0..1000) { items.push(i) }"), "Runtime Error: Strings and lists of total size 101 exceed the size limit of 100");
+    assert_eq!(eval_in(&mut engine, "len(items)"), "100");
+}
+
+#[test]
+fn the_size_limit_applies_to_all_values_together() {
+    let source = "let outer = []; for (i in 0..10) { let inner = []; for (j in 0..10) { inner.push(j) }; outer.push(inner) }; len(outer)";
+
+    assert_eq!(eval_both(source, |engine| engine.set_limits(Limits { max_heap_size: Some(200), ..Limits::new() })), "10");
+    assert_eq!(eval_both(source, |engine| engine.set_limits(Limits { max_heap_size: Some(100), ..Limits::new() })), "Runtime Error: Strings and lists of total size 101 exceed the size limit of 100");
+}
+
+#[test]
+fn values_that_are_no_longer_reachable_do_not_count_against_the_size_limit() {
+    let source = "function pad(s, n) { if (n == 0) s else pad(s + \"a\", n - 1) }; for (i in 0..100) { let items = []; for (j in 0..50) { items.push(j) }; items }; len(pad(\"\", 60))";
+
+    assert_eq!(eval_both(source, |engine| engine.set_limits(Limits { max_heap_size: Some(150), ..Limits::new() })), "60");
+}
+
+#[test]
+fn scripts_can_be_interrupted_from_another_thread() {
+    let mut engine = Engine::new();
+    let handle = engine.interrupt_handle();
+
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    let source = "let items = [1, 2]; function spin() { while (true) {} }; while (true) { try { spin() } catch (e) { e } }";
+
+    assert_eq!(eval_in(&mut engine, source), "Runtime Error: Interrupted");
+
+    stopper.join().unwrap();
+
+    assert_eq!(eval_in(&mut engine, "items.push(3); [items, len(items)]"), "[[1, 2, 3], 3]");
+    assert!(!engine.interrupt_handle().is_interrupted());
+}
+
+#[test]
+fn interrupted_scripts_cannot_run_on_in_finally_blocks() {
+    for backend in [engine::Backend::Interpreter, engine::Backend::Vm].iter() {
+        let mut engine = Engine::new();
+        let handle = engine.interrupt_handle();
+
+        engine.set_backend(*backend);
+
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        });
+
+        assert_eq!(eval_in(&mut engine, "try { while (true) {} } finally { while (true) {} }"), "Runtime Error: Interrupted");
+
+        stopper.join().unwrap();
+    }
+}
+
+#[test]
+fn interrupting_between_runs_stops_only_the_next_one() {
+    let mut engine = Engine::new();
+
+    engine.interrupt_handle().interrupt();
+
+    assert_eq!(eval_in(&mut engine, "function f() { 1 }; f() + f()"), "Runtime Error: Interrupted");
+    assert_eq!(eval_in(&mut engine, "f() + f()"), "2");
+}
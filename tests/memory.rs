@@ -0,0 +1,89 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::engine::Backend;
+use rust_parser::value::Value;
+use std::rc::Rc;
+
+use common::{eval, eval_in};
+
+#[test]
+fn calls_in_a_loop_do_not_grow_the_context_table() {
+    let mut engine = Engine::new();
+
+    let source = "function f(n) { if (n > 0) { let m = n * 2; m } else 0 }; for (i in 0..100000) { f(i) }";
+
+    assert_eq!(eval_in(&mut engine, source), "199998");
+    assert!(engine.manager().context_count() < 5000);
+}
+
+#[test]
+fn closures_survive_collection() {
+    let mut engine = Engine::new();
+
+    let source = "function make(n) { function adder(x) { x + n }; adder }; let add = make(5); function g(i) { [i] }; for (i in 0..20000) { g(i) }; add(1)";
+
+    assert_eq!(eval_in(&mut engine, source), "6");
+}
+
+#[test]
+fn values_held_during_evaluation_survive_collection() {
+    let mut engine = Engine::new();
+
+    let source = "function make(n) { function adder(x) { x + n }; adder }; function g(i) { i }; make(1)(for (i in 0..20000) { g(i) }) + [make(2)][0](for (i in 0..20000) { g(i) })";
+
+    assert_eq!(eval_in(&mut engine, source), "40001");
+}
+
+#[test]
+fn lists_and_instances_that_contain_themselves_are_freed() {
+    for backend in &[Backend::Interpreter, Backend::Vm] {
+        let mut engine = Engine::new();
+
+        engine.set_backend(*backend);
+
+        let list = match engine.eval("function make() { let l = []; l.push(l); l }; make()").unwrap() {
+            Value::List(items) => Rc::downgrade(&items),
+            value => panic!("expected a list, got {}", value)
+        };
+
+        let object = match engine.eval("struct N { next }; function node() { let n = N(null); n.next = n; n }; node()").unwrap() {
+            Value::Instance(object) => Rc::downgrade(&object),
+            value => panic!("expected an instance, got {}", value)
+        };
+
+        assert_eq!(eval_in(&mut engine, "for (i in 0..5000) { let l = []; l.push(l); let n = N(null); n.next = n; i }"), "4999");
+        assert!(list.upgrade().is_none());
+        assert!(object.upgrade().is_none());
+    }
+}
+
+#[test]
+fn cycles_that_are_still_reachable_survive_collection() {
+    let source = "struct N { next }; let keep = []; keep.push(keep); let n = N(null); n.next = n; for (i in 0..5000) { let l = []; l.push(l); i }; [len(keep), len(keep[0]), n.next.next == n]";
+
+    assert_eq!(eval(source), "[1, 1, true]");
+}
+
+#[test]
+fn values_the_host_keeps_survive_collection_in_later_evals() {
+    for backend in &[Backend::Interpreter, Backend::Vm] {
+        let mut engine = Engine::new();
+
+        engine.set_backend(*backend);
+
+        let add = engine.eval("function make(n) { function inner(x) { x + n }; inner }; make(5)").unwrap();
+        let point = engine.eval("function define() { struct P { x, function get() { self.x } }; P }; define()").unwrap();
+        let instance = engine.eval("function build() { struct Q { y, function get() { self.y } }; Q(3) }; build()").unwrap();
+
+        for _ in 0..50 {
+            assert_eq!(eval_in(&mut engine, "function g(i) { [i] }; for (i in 0..2000) { g(i) }; 1"), "1");
+        }
+
+        engine.set_global("add", add).unwrap();
+        engine.set_global("P", point).unwrap();
+        engine.set_global("instance", instance).unwrap();
+
+        assert_eq!(eval_in(&mut engine, "[add(1), P(2).get(), instance.get()]"), "[6, 2, 3]");
+    }
+}
@@ -0,0 +1,128 @@
+use rust_parser::Engine;
+use rust_parser::engine::Backend;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Writes `files` into a fresh directory named after the test and returns it.
+fn directory(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = env::temp_dir().join(format!("rust-parser-{}-{}", test, process::id()));
+
+    let _ = fs::remove_dir_all(&directory);
+
+    for (name, source) in files {
+        let path = directory.join(name);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    directory
+}
+
+// Runs the file `main.txt` in `directory` on both backends and returns the result they agree on.
+fn run(directory: &Path, configure: impl Fn(&mut Engine)) -> String {
+    let results: Vec<String> = [Backend::Interpreter, Backend::Vm].iter().map(|backend| {
+        let mut engine = Engine::new();
+
+        engine.set_backend(*backend);
+        configure(&mut engine);
+
+        match engine.eval_file(&directory.join("main.txt")) {
+            Ok(value) => value.to_string(),
+            Err(error) => error.to_string()
+        }
+    }).collect();
+
+    assert_eq!(results[0], results[1], "the backends disagree in {}", directory.display());
+
+    results[0].replace(&directory.canonicalize().unwrap().display().to_string(), "<dir>")
+}
+
+#[test]
+fn imports_resolve_next_to_the_importing_file() {
+    let directory = directory("next-to", &[
+        ("main.txt", "import { double, base } from \"lib/math\"; double(base)"),
+        ("lib/math.txt", "import { base } from \"base\"; export let base = base; export function double(x) { x * 2 }"),
+        ("lib/base.txt", "export let base = 21;")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "42");
+}
+
+#[test]
+fn imports_fall_back_to_the_search_paths() {
+    let directory = directory("search-path", &[
+        ("main.txt", "import { name } from \"package\"; name"),
+        ("vendor/package.txt", "export let name = \"vendored\";")
+    ]);
+
+    let vendor = directory.join("vendor");
+
+    assert_eq!(run(&directory, |engine| engine.add_search_path(&vendor)), "vendored");
+    assert_eq!(run(&directory, |_| {}), "Runtime Error: Cannot find module 'package'");
+}
+
+#[test]
+fn importing_a_whole_module_binds_only_its_exports() {
+    let directory = directory("whole", &[
+        ("main.txt", "import \"shapes\"; [square(3), try { hidden } catch (e) { e.kind }]"),
+        ("shapes.txt", "let hidden = 1; export function square(x) { x * x };")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "[9, NameError]");
+}
+
+#[test]
+fn modules_run_once_however_often_they_are_imported() {
+    let directory = directory("once", &[
+        ("main.txt", "import { add } from \"a\"; import { items } from \"log\"; add(); [items, len(items)]"),
+        ("a.txt", "import { items } from \"log\"; items.push(\"a\"); export function add() { items.push(\"main\") };"),
+        ("log.txt", "export let items = [];")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "[[a, main], 2]");
+}
+
+#[test]
+fn import_cycles_are_reported_with_the_chain() {
+    let directory = directory("cycle", &[
+        ("main.txt", "import { a } from \"a\"; a"),
+        ("a.txt", "import { b } from \"b\"; export let a = 1;"),
+        ("b.txt", "import { a } from \"a\"; export let b = 2;")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "Runtime Error: Import cycle detected: <dir>/a.txt -> <dir>/b.txt -> <dir>/a.txt");
+}
+
+#[test]
+fn importing_a_missing_name_is_an_error() {
+    let directory = directory("missing-name", &[
+        ("main.txt", "import { secret } from \"a\"; secret"),
+        ("a.txt", "let secret = 1; export let open = 2;")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "Runtime Error: Module 'a' has no export named 'secret'");
+}
+
+#[test]
+fn modules_are_type_checked_before_they_run() {
+    let directory = directory("type-error", &[
+        ("main.txt", "import { n } from \"a\"; n"),
+        ("a.txt", "export let n: int = \"one\";")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "Runtime Error: In module '<dir>/a.txt': Type Error: Cannot assign a value of type 'str' to 'n' of type 'int'");
+}
+
+#[test]
+fn structs_and_enums_can_be_exported() {
+    let directory = directory("types", &[
+        ("main.txt", "import { Point, Shape } from \"shapes\"; let p = Point(1, 2); match (Shape.Square(p.x + p.y)) { Shape.Square(side) => side * side, _ => 0 }"),
+        ("shapes.txt", "export struct Point { x, y }; export enum Shape { Square(side), Dot };")
+    ]);
+
+    assert_eq!(run(&directory, |_| {}), "9");
+}
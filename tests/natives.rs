@@ -0,0 +1,36 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::native::Arity;
+use rust_parser::value::Value;
+
+use common::{eval, eval_both};
+
+fn configure(engine: &mut Engine) {
+    engine.register("pair", Arity::Range(1, 2), |args| Ok(Value::Int(args.len() as i32)));
+    engine.register("count", Arity::Variadic, |args| Ok(Value::Int(args.len() as i32)));
+    engine.register_fn("add", |a: i32, b: i32| a + b);
+}
+
+#[test]
+fn builtins_called_with_the_wrong_number_of_arguments_are_argument_errors() {
+    assert_eq!(eval("len()"), "Runtime Error: len expects 1 argument(s), got 0");
+    assert_eq!(eval("len([1], 2)"), "Runtime Error: len expects 1 argument(s), got 2");
+    assert_eq!(eval("input(1, 2)"), "Runtime Error: input expects 0 to 1 argument(s), got 2");
+    assert_eq!(eval("[1].push()"), "Runtime Error: push expects 1 argument(s), got 0");
+    assert_eq!(eval("try { len() } catch (e) { [e.kind, e.message] }"), "[ArgumentError, len expects 1 argument(s), got 0]");
+}
+
+#[test]
+fn the_arity_check_applies_to_natives_called_through_a_variable() {
+    assert_eq!(eval("let f = len; f([1], 2)"), "Runtime Error: len expects 1 argument(s), got 2");
+    assert_eq!(eval("let f = len; f([1, 2])"), "2");
+}
+
+#[test]
+fn registered_functions_declare_their_arity() {
+    assert_eq!(eval_both("[pair(1), pair(1, 2), count(), count(1, 2, 3), add(1, 2)]", configure), "[1, 2, 0, 3, 3]");
+    assert_eq!(eval_both("pair()", configure), "Runtime Error: pair expects 1 to 2 argument(s), got 0");
+    assert_eq!(eval_both("pair(1, 2, 3)", configure), "Runtime Error: pair expects 1 to 2 argument(s), got 3");
+    assert_eq!(eval_both("add(1)", configure), "Runtime Error: add expects 2 argument(s), got 1");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+use rust_parser::optimizer::Optimizer;
+
+use common::eval;
+
+fn optimize(source: &str) -> String {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let node = Parser::new(tokens).parse().unwrap();
+
+    format!("{:?}", Optimizer::new().optimize(node))
+}
+
+#[test]
+fn operations_on_literals_are_folded() {
+    assert_eq!(optimize("1 + 2 * 3"), "Statements([Int(7)], true)");
+    assert_eq!(optimize("-(2 + 3)"), "Statements([Int(-5)], true)");
+    assert_eq!(optimize("\"a\" + 1"), "Statements([Str(\"a1\")], true)");
+    assert_eq!(optimize("1 < 2 && null ?? 3"), "Statements([Int(3)], true)");
+}
+
+#[test]
+fn dividing_integers_folds_to_the_type_the_interpreter_would_give() {
+    assert_eq!(optimize("7 / 2"), "Statements([Float(3.5)], true)");
+    assert_eq!(optimize("4 / 2"), "Statements([Int(2)], true)");
+}
+
+#[test]
+fn operations_that_fail_or_overflow_are_left_to_run() {
+    assert_eq!(optimize("2 ^ 31"), "Statements([BinaryOp(Int(2), Pow, Int(31))], true)");
+    assert_eq!(optimize("1 << 40"), "Statements([BinaryOp(Int(1), BitwiseLeftShift, Int(40))], true)");
+    assert_eq!(optimize("[1] + 2 * 3"), "Statements([BinaryOp(ListDef([Int(1)]), Plus, Int(6))], true)");
+}
+
+#[test]
+fn shadowed_literals_are_not_folded() {
+    assert_eq!(optimize("let true = 0; true && 1"), "Statements([VarDef(\"true\", None, Int(0)), BinaryOp(VarAcc(\"true\"), And, Int(1))], true)");
+    assert_eq!(eval("let false = 1; if (false) { \"taken\" } else { \"skipped\" }"), "taken");
+
+    let mut engine = Engine::new();
+
+    engine.set_global("null", 1).unwrap();
+
+    assert_eq!(engine.eval("null ?? 2").unwrap().to_string(), "1");
+}
+
+#[test]
+fn branches_and_loops_that_cannot_run_are_dropped() {
+    assert_eq!(optimize("if (1 < 2) { 3 } else { 4 }"), "Statements([If(Int(1), Statements([Int(3)], true), None)], true)");
+    assert_eq!(optimize("if (false) { 1 }"), "Statements([Empty], true)");
+    assert_eq!(optimize("while (null) { 1 }"), "Statements([Empty], true)");
+    assert_eq!(eval("let x = 1; if (true) { let x = 2 }; x"), "1");
+}
+
+#[test]
+fn identities_are_dropped_for_the_types_they_hold_for() {
+    assert_eq!(optimize("let x = 1.5; -(-x) / 1"), "Statements([VarDef(\"x\", None, Float(1.5)), VarAcc(\"x\")], true)");
+    assert_eq!(optimize("let x = 5; (x + 1) + 2"), "Statements([VarDef(\"x\", None, Int(5)), BinaryOp(VarAcc(\"x\"), Plus, Int(3))], true)");
+    assert_eq!(optimize("let s = \"a\"; s + 0"), "Statements([VarDef(\"s\", None, Str(\"a\")), BinaryOp(VarAcc(\"s\"), Plus, Int(0))], true)");
+    assert_eq!(optimize("let x = 2; let x = 0.5; x * 1 + 0"), "Statements([VarDef(\"x\", None, Int(2)), VarDef(\"x\", None, Float(0.5)), BinaryOp(BinaryOp(VarAcc(\"x\"), Mul, Int(1)), Plus, Int(0))], true)");
+}
+
+#[test]
+fn dividing_an_integer_by_one_is_kept() {
+    assert_eq!(optimize("let x = 16777217; x / 1"), "Statements([VarDef(\"x\", None, Int(16777217)), BinaryOp(VarAcc(\"x\"), Div, Int(1))], true)");
+    assert_eq!(eval("let x = 16777217; x / 1"), "16777216");
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn a_null_found_by_optional_access_skips_the_rest_of_the_chain() {
+    assert_eq!(eval("let a = null; a?.b.c"), "null");
+    assert_eq!(eval("let a = null; a?.b.c(1)[0].d"), "null");
+    assert_eq!(eval("let f = null; f?.(1).x"), "null");
+    assert_eq!(eval("struct P { q }; let p = P(null); [p.q?.r.s, P(P(5)).q?.q]"), "[null, 5]");
+    assert_eq!(eval("function g(a) { a?.b(1)(2) }; g(null)"), "null");
+}
+
+#[test]
+fn coalescing_replaces_only_null() {
+    assert_eq!(eval("[null ?? 1, 0 ?? 1, false ?? 1, \"\" ?? 2]"), "[1, 0, false, ]");
+    assert_eq!(eval("null ?? null ?? 3"), "3");
+}
+
+#[test]
+fn coalescing_does_not_evaluate_an_unused_right_side() {
+    assert_eq!(eval("let log = []; function f() { log.push(1); 2 }; 3 ?? f(); log"), "[]");
+    assert_eq!(eval("let log = []; function f() { log.push(1); 2 }; null ?? f(); log"), "[1]");
+}
+
+#[test]
+fn optional_calls_skip_null_callees_and_their_arguments() {
+    assert_eq!(eval("let f = null; f?.(1)"), "null");
+    assert_eq!(eval("function g(x) { x + 1 }; g?.(1)"), "2");
+    assert_eq!(eval("let log = []; function f() { log.push(1) }; let n = null; n?.(f()); log"), "[]");
+}
+
+#[test]
+fn optional_method_calls_run_on_values_and_skip_null() {
+    assert_eq!(eval("let n = null; n?.push(2)"), "null");
+    assert_eq!(eval("let l = [1]; l?.push(2); l"), "[1, 2]");
+    assert_eq!(eval("let x: int = 3; x?.push(1)"), "Type Error: Type 'int' has no method 'push'");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn ranges_can_be_compared() {
+    assert_eq!(eval("[0..2 == 0..2, 0..2 == 0..=1, 0..2 != 1..3, 3..3 == 5..5]"), "[true, true, true, true]");
+}
+
+#[test]
+fn ranges_slice_lists_and_strings() {
+    assert_eq!(eval("[1, 2, 3, 4][1..3]"), "[2, 3]");
+    assert_eq!(eval("\"hello\"[1..=3]"), "ell");
+    assert_eq!(eval("\"héllo\"[1..3]"), "él");
+}
+
+#[test]
+fn slices_are_clamped_to_the_bounds() {
+    assert_eq!(eval("[[1, 2][0..10], [1, 2, 3][2..1], [1, 2, 3][0..0]]"), "[[1, 2], [], []]");
+    assert_eq!(eval("\"abc\"[-1..2]"), "ab");
+}
+
+#[test]
+fn slices_are_copies() {
+    assert_eq!(eval("let a = [1, 2, 3]; let b = a[0..2]; b.push(9); [a, b]"), "[[1, 2, 3], [1, 2, 9]]");
+}
+
+#[test]
+fn ranges_are_lazy_sequences() {
+    assert_eq!(eval("let r = 1..4; [len(r), 2 in r, 4 in r, r[2], r]"), "[3, true, false, 3, 1..4]");
+    assert_eq!(eval("let out = []; for (i in 3..=5) { out.push(i) }; out"), "[3, 4, 5]");
+}
+
+#[test]
+fn membership_is_exact_for_large_bounds() {
+    assert_eq!(eval("[16777217 in 0..=16777216, 16777216 in 0..16777217, 2147483647 in 0..=2147483647]"), "[false, true, true]");
+    assert_eq!(eval("[16777216.0 in 0..16777217, 2.5 in 0..5, 5.0 in 0..5]"), "[true, false, false]");
+}
+
+#[test]
+fn lengths_that_do_not_fit_in_an_int_are_errors() {
+    assert_eq!(eval("len(-2147483647..=2147483647)"), "Runtime Error: Length 4294967295 of '-2147483647..=2147483647' does not fit in an int");
+    assert_eq!(eval("len(0..=2147483646)"), "2147483647");
+}
+
+#[test]
+fn slices_of_ranges_ending_at_the_largest_int_stay_in_bounds() {
+    assert_eq!(eval("(1..=2147483647)[0..=2147483647]"), "1..=2147483647");
+    assert_eq!(eval("(1..=2147483647)[2147483647..=2147483647]"), "2147483647..2147483647");
+    assert_eq!(eval("(0..10)[2..4]"), "2..4");
+}
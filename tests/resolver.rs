@@ -0,0 +1,119 @@
+mod common;
+
+use rust_parser::Engine;
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+use rust_parser::node::Node;
+use rust_parser::resolver::{Resolver, Step};
+use rust_parser::visitor::{Visitor, walk};
+
+use common::eval;
+
+fn resolve(source: &str) -> Node {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let node = Parser::new(tokens).parse().unwrap();
+
+    Resolver::new().resolve(node).unwrap()
+}
+
+// Collects the steps of every resolved read of `name`.
+struct Reads {
+    name: &'static str,
+    steps: Vec<Vec<Step>>
+}
+
+impl Visitor for Reads {
+    fn visit_resolved_acc_node(&mut self, node: &Node) {
+        if let Node::ResolvedAcc(lookup) = node {
+            if lookup.name == self.name {
+                self.steps.push(lookup.steps.clone());
+            }
+        }
+
+        walk(self, node);
+    }
+}
+
+fn reads(source: &str, name: &'static str) -> Vec<Vec<Step>> {
+    let mut reads = Reads { name, steps: vec![] };
+
+    reads.visit(&resolve(source));
+
+    reads.steps
+}
+
+// Collects the slot of every definition stored in one.
+struct Definitions {
+    slots: Vec<u32>
+}
+
+impl Visitor for Definitions {
+    fn visit_define_node(&mut self, node: &Node) {
+        if let Node::Define(slot, _) = node {
+            self.slots.push(*slot);
+        }
+
+        walk(self, node);
+    }
+}
+
+#[test]
+fn parameters_are_read_from_slots() {
+    // The function's context is a child of the empty context it captures, which is a child of the top level.
+    assert_eq!(reads("function f(a, b) { b }", "b"), vec![vec![Step::Slot(0, 1), Step::Global(2)]]);
+}
+
+#[test]
+fn definitions_in_scopes_with_slots_are_stored_in_them() {
+    let mut definitions = Definitions { slots: vec![] };
+
+    // `f` is defined at the top level, by name; in its body `a` takes slot 0.
+    definitions.visit(&resolve("function f(a) { let b = a; function g() { b }; g() }; let c = 1"));
+
+    assert_eq!(definitions.slots, vec![1, 2]);
+}
+
+#[test]
+fn enclosing_slots_are_read_at_their_depth() {
+    let source = "function f(a) { if (a) { let b = a; b } }";
+
+    assert_eq!(reads(source, "a"), vec![vec![Step::Slot(0, 0), Step::Global(2)], vec![Step::Slot(1, 0), Step::Global(3)]]);
+    assert_eq!(reads(source, "b"), vec![vec![Step::Slot(0, 0), Step::Global(3)]]);
+}
+
+#[test]
+fn scopes_that_import_bind_names() {
+    assert_eq!(reads("if (1) { import \"m\"; x }", "x"), vec![vec![Step::Local(0), Step::Global(1)]]);
+}
+
+#[test]
+fn undefined_variables_are_reported_before_running() {
+    let mut engine = Engine::new();
+
+    let error = engine.eval("let before = 1; if (false) { missing }").unwrap_err();
+
+    assert_eq!(error.to_string(), "Runtime Error: missing is not defined");
+    assert!(engine.get_global("before").is_none());
+}
+
+#[test]
+fn globals_of_earlier_scripts_are_defined() {
+    let mut engine = Engine::new();
+
+    engine.set_global("limit", 3).unwrap();
+    engine.eval("function double(x) { x * 2 }").unwrap();
+
+    assert_eq!(engine.eval("double(limit)").unwrap().to_string(), "6");
+}
+
+#[test]
+fn later_declarations_in_the_same_scope_are_defined() {
+    assert_eq!(eval("function even(n) { if (n == 0) { true } else { odd(n - 1) } }; function odd(n) { if (n == 0) { false } else { even(n - 1) } }; even(4)"), "true");
+}
+
+#[test]
+fn methods_read_fields_through_self() {
+    assert_eq!(eval("struct C { n function get() { self.n } function twice() { self.gets() } }; C(4).twice()"), "Runtime Error: C { n: 4 } has no method 'gets'");
+    assert_eq!(eval("struct C { n function get() { n } }; C(4).get()"), "Runtime Error: n is not defined");
+    assert_eq!(eval("struct C { n function get() { self.n } function twice() { self.get() * 2 } }; C(4).twice()"), "8");
+}
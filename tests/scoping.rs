@@ -0,0 +1,52 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn arguments_are_evaluated_in_the_caller_scope() {
+    assert_eq!(eval("function add(a, b) { a + b }; function twice(x) { add(x, x) }; twice(4)"), "8");
+}
+
+#[test]
+fn arguments_cannot_see_the_callee_closure() {
+    let source = "function outer() { let secret = 1; function inner(v) { v }; inner }; let f = outer(); f(secret)";
+
+    assert_eq!(eval(source), "Runtime Error: secret is not defined");
+}
+
+#[test]
+fn parameters_do_not_leak_into_the_caller() {
+    assert_eq!(eval("function f(p) { p }; f(1); p"), "Runtime Error: p is not defined");
+}
+
+#[test]
+fn nested_closures_capture_each_enclosing_scope() {
+    let source = "function outer(a) { function middle(b) { function inner(c) { a + b + c }; inner }; middle }; outer(1)(2)(3)";
+
+    assert_eq!(eval(source), "6");
+}
+
+#[test]
+fn closures_keep_separate_environments() {
+    let source = "function make(n) { function adder(x) { x + n }; adder }; let one = make(1); let ten = make(10); [one(1), ten(1)]";
+
+    assert_eq!(eval(source), "[2, 11]");
+}
+
+#[test]
+fn recursion() {
+    assert_eq!(eval("function fact(n) { if (n <= 1) 1 else n * fact(n - 1) }; fact(10)"), "3628800");
+    assert_eq!(eval("function fib(n) { if (n < 2) n else fib(n - 1) + fib(n - 2) }; fib(15)"), "610");
+}
+
+#[test]
+fn recursive_calls_keep_their_own_parameters() {
+    assert_eq!(eval("function sum(n) { if (n == 0) 0 else { let rest = sum(n - 1); n + rest } }; sum(5)"), "15");
+}
+
+#[test]
+fn mutual_recursion() {
+    let source = "function even(n) { if (n == 0) true else odd(n - 1) }; function odd(n) { if (n == 0) false else even(n - 1) }; [even(10), odd(7)]";
+
+    assert_eq!(eval(source), "[true, true]");
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn fields_are_separated_by_commas() {
+    assert_eq!(eval("struct P { x, y, }; P(1, 2)"), "P { x: 1, y: 2 }");
+    assert_eq!(eval("struct P { x y }"), "Syntax Error: Expected ',' between fields");
+}
+
+#[test]
+fn methods_see_their_instance_as_self() {
+    let source = "struct C { n; function get() { self.n } function bump() { self.n = self.n + 1; self } }; let c = C(1); c.bump().bump(); c.get()";
+
+    assert_eq!(eval(source), "3");
+    assert_eq!(eval("struct C { n, function twice() { self.add(self.n) } function add(x) { self.n + x } }; C(4).twice()"), "8");
+}
+
+#[test]
+fn instances_are_built_from_their_fields_in_order() {
+    assert_eq!(eval("struct P { x, y }; [P(1, 2), P(2, 1)]"), "[P { x: 1, y: 2 }, P { x: 2, y: 1 }]");
+}
+
+#[test]
+fn constructors_take_one_argument_per_field() {
+    assert_eq!(eval("struct P { x, y }; P(1)"), "Runtime Error: P expects 2 argument(s), got 1");
+    assert_eq!(eval("struct P { x, y }; P(1, 2, 3)"), "Runtime Error: P expects 2 argument(s), got 3");
+    assert_eq!(eval("struct P { x, y }; try { P() } catch (e) { e.kind }"), "ArgumentError");
+}
+
+#[test]
+fn missing_fields_and_methods_are_errors() {
+    assert_eq!(eval("struct P { x }; P(1).y"), "Runtime Error: P { x: 1 } has no field 'y'");
+    assert_eq!(eval("struct P { x }; P(1).nope()"), "Runtime Error: P { x: 1 } has no method 'nope'");
+    assert_eq!(eval("struct C { n, function get() { self.n } }; let get = C(5).get; get()"), "Runtime Error: C { n: 5 } has no field 'get'");
+}
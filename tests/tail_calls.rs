@@ -0,0 +1,49 @@
+mod common;
+
+use rust_parser::limits::Limits;
+
+use common::eval_both;
+
+// Runs `source` on both backends, with a depth limit of 50, and returns the result they agree on.
+fn eval(source: &str) -> String {
+    eval_both(source, |engine| engine.set_max_depth(50))
+}
+
+#[test]
+fn self_recursion_in_tail_position_is_not_limited_by_depth() {
+    assert_eq!(eval("function count(n, total) { if (n == 0) total else count(n - 1, total + 1) }; count(100000, 0)"), "100000");
+    assert_eq!(eval("function last(n) { let next = n - 1; if (next > 0) { last(next) } else { str(n) } }; last(1000)"), "1");
+}
+
+#[test]
+fn mutual_recursion_in_tail_position_is_not_limited_by_depth() {
+    let source = "function even(n) { if (n == 0) { true } else { odd(n - 1) } }; function odd(n) { if (n == 0) { false } else { even(n - 1) } }; [even(10001), odd(10001)]";
+
+    assert_eq!(eval(source), "[false, true]");
+}
+
+#[test]
+fn tail_calls_run_in_constant_scope_space() {
+    let source = "function loop(n) { if (n > 0) { let half = n / 2; loop(n - 1) } else { \"done\" } }; loop(10000)";
+
+    let result = eval_both(source, |engine| {
+        engine.set_max_depth(50);
+        engine.set_limits(Limits { max_contexts: Some(64), ..Limits::new() });
+    });
+
+    assert_eq!(result, "done");
+}
+
+#[test]
+fn calls_outside_tail_position_still_count_towards_the_depth() {
+    assert_eq!(eval("function f(n) { if (n == 0) 0 else 1 + f(n - 1) }; f(100)"), "Runtime Error: Maximum recursion depth of 50 exceeded");
+    assert_eq!(eval("function g(n) { try { g(n + 1) } catch (e) { e.kind } }; g(0)"), "RecursionError");
+    assert_eq!(eval("struct C { function down(n) { if (n == 0) 0 else 1 + self.down(n - 1) } }; C().down(100)"), "Runtime Error: Maximum recursion depth of 50 exceeded");
+}
+
+#[test]
+fn method_calls_in_tail_position_are_not_limited_by_depth() {
+    assert_eq!(eval("struct C { k, function down(n) { if (n == 0) self.k else self.down(n - 1) } }; C(7).down(1000)"), "7");
+    assert_eq!(eval("struct C { other, function ping(n) { if (n == 0) \"done\" else self.other?.ping(n - 1) } }; let a = C(null); let b = C(a); a.other = b; b.ping(1000)"), "done");
+    assert_eq!(eval("function fill(items, n) { if (n == 0) { items } else { items.push(n); fill(items, n - 1) } }; len(fill([], 1000))"), "1000");
+}
@@ -0,0 +1,108 @@
+use rust_parser::lexer::Lexer;
+use rust_parser::parser::Parser;
+use rust_parser::node::{Node, Pattern};
+use rust_parser::visitor::{Visitor, Folder, walk, walk_pattern, fold_children};
+
+fn parse(source: &str) -> Node {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+
+    Parser::new(tokens).parse().unwrap()
+}
+
+fn debug(node: Node) -> String {
+    format!("{:?}", node)
+}
+
+// Records every variable read and every name bound by a pattern, in visiting order.
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+    skip_functions: bool
+}
+
+impl Visitor for Names {
+    fn visit_var_acc_node(&mut self, node: &Node) {
+        if let Node::VarAcc(name) = node {
+            self.names.push(name.clone());
+        }
+    }
+
+    fn visit_func_def_node(&mut self, node: &Node) {
+        if !self.skip_functions {
+            walk(self, node);
+        }
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let Pattern::Binding(name) = pattern {
+            self.names.push(String::from("pattern ") + name);
+        }
+
+        walk_pattern(self, pattern);
+    }
+}
+
+fn names(source: &str, skip_functions: bool) -> Vec<String> {
+    let mut visitor = Names { skip_functions, ..Names::default() };
+
+    visitor.visit(&parse(source));
+
+    visitor.names
+}
+
+#[test]
+fn the_visitor_reaches_every_child_in_source_order() {
+    assert_eq!(names("a + b * c", false), ["a", "b", "c"]);
+    assert_eq!(names("if (a) { b } else { c }; while (d) { e }; for (x in f) { g }", false), ["a", "b", "c", "d", "e", "f", "g"]);
+    assert_eq!(names("a.b(c)[d]; e?.f?.(g)", false), ["a", "c", "d", "e", "g"]);
+    assert_eq!(names("try { a } catch (e) { b } finally { c }", false), ["a", "b", "c"]);
+}
+
+#[test]
+fn the_visitor_reaches_match_patterns_and_literal_patterns() {
+    assert_eq!(names("match (a) { E.V(x, E.W(y)) => b, z => c, 1 => d }", false), ["a", "pattern x", "pattern y", "b", "pattern z", "c", "d"]);
+}
+
+#[test]
+fn an_override_that_does_not_walk_stops_the_descent() {
+    assert_eq!(names("a; function f() { b }; c", false), ["a", "b", "c"]);
+    assert_eq!(names("a; function f() { b }; c", true), ["a", "c"]);
+}
+
+// Renames every variable read and doubles every integer literal.
+struct Rename;
+
+impl Folder for Rename {
+    fn fold_var_acc_node(&mut self, node: Node) -> Node {
+        match node {
+            Node::VarAcc(name) => Node::VarAcc(name + "_"),
+            node => node
+        }
+    }
+
+    fn fold_int_node(&mut self, node: Node) -> Node {
+        match node {
+            Node::Int(value) => Node::Int(value * 2),
+            node => node
+        }
+    }
+}
+
+#[test]
+fn the_folder_rebuilds_the_tree_with_rewritten_nodes() {
+    assert_eq!(debug(Rename.fold(parse("a + 1"))), debug(parse("a_ + 2")));
+    assert_eq!(debug(Rename.fold(parse("if (a) { f(b, 2) } else { [c, 3] }"))), debug(parse("if (a_) { f_(b_, 4) } else { [c_, 6] }")));
+    assert_eq!(debug(Rename.fold(parse("match (a) { 1 => b, x => 2 }"))), debug(parse("match (a_) { 2 => b_, x => 4 }")));
+}
+
+#[test]
+fn the_folder_leaves_nodes_it_does_not_override_unchanged() {
+    struct Identity;
+
+    impl Folder for Identity {}
+
+    let source = "struct P { x, y }; let p = P(1, 2.5); try { p.x = \"s\" } catch (e) { e } finally { null }";
+
+    assert_eq!(debug(Identity.fold(parse(source))), debug(parse(source)));
+    assert_eq!(debug(fold_children(&mut Identity, parse(source))), debug(parse(source)));
+}